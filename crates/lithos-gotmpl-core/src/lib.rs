@@ -1,9 +1,13 @@
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 pub use lithos_gotmpl_engine::{
-    analyze_template, coerce_number, is_empty, is_truthy, value_to_string, AnalysisIssue,
-    Certainty, ControlKind, ControlUsage, Error, EvalContext, FunctionCall, FunctionRegistry,
-    FunctionRegistryBuilder, FunctionSource, Precision, Template, TemplateAnalysis, TemplateCall,
-    VariableAccess, VariableKind,
+    analyze_template, analyze_with_templates, coerce_number, format_template, is_empty,
+    is_formatted, is_truthy, parse_template_recovering, value_to_string, ActionSpacing,
+    AnalysisIssue, AsyncFunction, CancelReason, CancellationToken, Certainty, ControlKind,
+    ControlUsage, Dialect, Error, EvalContext, FormatOptions, FunctionCall, FunctionRegistry,
+    FunctionRegistryBuilder, FunctionSource, GoVersion, HelperFuture, LazyArgs, LazyFunction,
+    Limit, LineColumn, Method, MissingKey, ParseOptions, Precision, RecoveredParse, RenderOptions,
+    SourceMap, SourceRange, Template, TemplateAnalysis, TemplateCall, TemplateSet, VariableAccess,
+    VariableKind,
 };
 use serde_json::Number;
 use serde_json::Value;
@@ -87,7 +91,7 @@ pub fn text_template_functions() -> FunctionRegistry {
 /// Installs the standard Go text/template helper functions into an existing registry builder.
 pub fn install_text_template_functions(builder: &mut FunctionRegistryBuilder) {
     builder
-        .register("and", builtin_and)
+        .register_lazy("and", builtin_and)
         .register("call", builtin_call)
         .register("html", builtin_html)
         .register("eq", builtin_eq)
@@ -102,7 +106,7 @@ pub fn install_text_template_functions(builder: &mut FunctionRegistryBuilder) {
         .register("not", builtin_not)
         .register("print", builtin_print)
         .register("println", builtin_println)
-        .register("or", builtin_or)
+        .register_lazy("or", builtin_or)
         .register("printf", builtin_printf)
         .register("slice", builtin_slice)
         .register("urlquery", builtin_urlquery);
@@ -173,9 +177,9 @@ fn builtin_printf(_ctx: &mut EvalContext, args: &[Value]) -> Result<Value, Error
     if args.is_empty() {
         return Err(Error::render("printf expects format string", None));
     }
-    let format = args[0]
-        .as_str()
-        .ok_or_else(|| Error::render("printf expects format string as first argument", None))?;
+    let format = args[0].as_str().ok_or_else(|| {
+        Error::render("printf expects format string as first argument", None).with_argument(0)
+    })?;
 
     let mut output = String::new();
     let mut chars = format.chars();
@@ -323,7 +327,7 @@ fn builtin_index(_ctx: &mut EvalContext, args: &[Value]) -> Result<Value, Error>
     }
 
     let mut current = args[0].clone();
-    for key in &args[1..] {
+    for (position, key) in args.iter().enumerate().skip(1) {
         current = match (&current, key) {
             (Value::Object(map), Value::String(s)) => map.get(s).cloned().unwrap_or(Value::Null),
             (Value::Object(map), Value::Number(num)) => {
@@ -331,16 +335,16 @@ fn builtin_index(_ctx: &mut EvalContext, args: &[Value]) -> Result<Value, Error>
                 map.get(&key).cloned().unwrap_or(Value::Null)
             }
             (Value::Array(list), Value::Number(num)) => {
-                let idx = num
-                    .as_u64()
-                    .ok_or_else(|| Error::render("array index must be unsigned integer", None))?
-                    as usize;
+                let idx = num.as_u64().ok_or_else(|| {
+                    Error::render("array index must be unsigned integer", None)
+                        .with_argument(position)
+                })? as usize;
                 list.get(idx).cloned().unwrap_or(Value::Null)
             }
             (Value::Array(list), Value::String(s)) => {
-                let idx = s
-                    .parse::<usize>()
-                    .map_err(|_| Error::render("array index must be integer", None))?;
+                let idx = s.parse::<usize>().map_err(|_| {
+                    Error::render("array index must be integer", None).with_argument(position)
+                })?;
                 list.get(idx).cloned().unwrap_or(Value::Null)
             }
             _ => return Err(Error::render("index expects map or array container", None)),
@@ -350,22 +354,30 @@ fn builtin_index(_ctx: &mut EvalContext, args: &[Value]) -> Result<Value, Error>
     Ok(current)
 }
 
-fn builtin_and(_ctx: &mut EvalContext, args: &[Value]) -> Result<Value, Error> {
-    for value in args {
-        if !is_truthy(value) {
-            return Ok(value.clone());
+/// Returns the first falsy argument or the last one, evaluating arguments only
+/// until the result is known (Go 1.18+).
+fn builtin_and(ctx: &mut EvalContext, args: LazyArgs<'_>) -> Result<Value, Error> {
+    let mut last = Value::Bool(true);
+    for index in 0..args.len() {
+        last = args.eval(ctx, index)?;
+        if !is_truthy(&last) {
+            break;
         }
     }
-    Ok(args.last().cloned().unwrap_or(Value::Bool(true)))
+    Ok(last)
 }
 
-fn builtin_or(_ctx: &mut EvalContext, args: &[Value]) -> Result<Value, Error> {
-    for value in args {
-        if is_truthy(value) {
-            return Ok(value.clone());
+/// Returns the first truthy argument or the last one, evaluating arguments only
+/// until the result is known (Go 1.18+).
+fn builtin_or(ctx: &mut EvalContext, args: LazyArgs<'_>) -> Result<Value, Error> {
+    let mut last = Value::Bool(false);
+    for index in 0..args.len() {
+        last = args.eval(ctx, index)?;
+        if is_truthy(&last) {
+            break;
         }
     }
-    Ok(args.last().cloned().unwrap_or(Value::Bool(false)))
+    Ok(last)
 }
 
 fn builtin_len(_ctx: &mut EvalContext, args: &[Value]) -> Result<Value, Error> {
@@ -405,10 +417,9 @@ fn builtin_slice(_ctx: &mut EvalContext, args: &[Value]) -> Result<Value, Error>
             Ok(Value::Array(list[start..end].to_vec()))
         }
         Value::Null => Ok(Value::Array(Vec::new())),
-        _ => Err(Error::render(
-            "slice expects string or array as first argument",
-            None,
-        )),
+        _ => Err(
+            Error::render("slice expects string or array as first argument", None).with_argument(0),
+        ),
     }
 }
 
@@ -525,6 +536,27 @@ mod tests {
     use super::*;
     use serde_json::json;
 
+    #[test]
+    fn and_or_stop_evaluating_once_result_is_known() {
+        let functions = text_template_functions();
+        let tmpl = Template::parse_with_functions(
+            "short-circuit",
+            "{{and 0 (index .missing 0)}}|{{or .name (index .missing 0)}}|{{1 | and .name}}|{{call \"or\" 0 2}}",
+            functions,
+        )
+        .unwrap();
+        let result = tmpl.render(&json!({"name": "lithos"})).unwrap();
+        assert_eq!(result, "0|lithos|1|2");
+
+        let failing = Template::parse_with_functions(
+            "evaluated",
+            "{{and 1 (index .missing 0)}}",
+            text_template_functions(),
+        )
+        .unwrap();
+        assert!(failing.render(&json!({})).is_err());
+    }
+
     #[test]
     fn html_escapes_like_go_docs() {
         let functions = text_template_functions();
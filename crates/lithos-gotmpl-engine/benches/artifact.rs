@@ -0,0 +1,63 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+//! Compares parsing template sources with loading precompiled artifacts.
+//!
+//! Run with `cargo bench -p lithos-gotmpl-engine --features artifact`.
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use common::engine_cases;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use lithos_gotmpl_engine::{FunctionRegistry, Template, TemplateSet};
+
+/// Engine fixtures that parse, standing in for a service's template directory.
+fn sources() -> Vec<(String, String)> {
+    engine_cases()
+        .into_iter()
+        .filter(|case| Template::parse_str(&case.name, &case.template).is_ok())
+        .map(|case| (case.name, case.template))
+        .collect()
+}
+
+fn parse_set(sources: &[(String, String)]) -> TemplateSet {
+    let mut set = TemplateSet::new();
+    for (name, source) in sources {
+        // Fixtures may redefine each other's templates; keep the first parse.
+        let _ = set.parse(name, source);
+    }
+    set
+}
+
+fn bench_template(c: &mut Criterion) {
+    let source = r#"{{ define "row" }}<tr>{{ range $i, $v := . }}<td>{{ $i }}: {{ if $v }}{{ $v | printf "%v" }}{{ else }}-{{ end }}</td>{{ end }}</tr>{{ end }}
+{{- range .rows }}{{ template "row" . }}{{ end }}"#;
+    let template = Template::parse_str("table", source).unwrap();
+    let artifact = template.to_artifact().unwrap();
+
+    let mut group = c.benchmark_group("template");
+    group.bench_function("parse", |b| {
+        b.iter(|| Template::parse_str("table", black_box(source)).unwrap());
+    });
+    group.bench_function("load_artifact", |b| {
+        b.iter(|| {
+            Template::from_artifact(black_box(&artifact), FunctionRegistry::empty()).unwrap()
+        });
+    });
+    group.finish();
+}
+
+fn bench_set(c: &mut Criterion) {
+    let sources = sources();
+    let artifact = parse_set(&sources).to_artifact().unwrap();
+
+    let mut group = c.benchmark_group("template_set");
+    group.bench_function("parse", |b| b.iter(|| parse_set(black_box(&sources))));
+    group.bench_function("load_artifact", |b| {
+        b.iter(|| {
+            TemplateSet::from_artifact(black_box(&artifact), FunctionRegistry::empty()).unwrap()
+        });
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_template, bench_set);
+criterion_main!(benches);
@@ -1,35 +1,65 @@
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 use std::collections::HashSet;
 
-use crate::ast::{ActionNode, Ast, Command, Expression, IfNode, Node, RangeNode, Span, WithNode};
+use crate::ast::visit::{
+    walk_action, walk_block_node, walk_define, walk_else_if, walk_expression, walk_if, walk_range,
+    walk_with, Visit,
+};
+use crate::ast::{
+    ActionNode, Ast, BlockNode, BreakNode, Command, ContinueNode, DefineNode, ElseIfBranch,
+    Expression, IfNode, RangeNode, Span, TemplateNode, WithNode,
+};
 use crate::lexer::{Token, TokenKind};
 use crate::runtime::FunctionRegistry;
+use crate::source_map::{SourceMap, SourceRange};
 
 /// Walks the parsed template AST and records helper, variable, and control flow
 /// usage so tooling can reason about dependencies without reinterpreting the
 /// template.
+///
+/// Template invocations are resolved against the templates defined inside `ast`.
 pub fn analyze_template(ast: &Ast, registry: Option<&FunctionRegistry>) -> TemplateAnalysis {
+    analyze_with_templates(ast, registry, std::iter::empty())
+}
+
+/// Variant of [`analyze_template`] that also treats `templates` as defined when
+/// resolving `{{template}}` invocations, e.g. the other members of a template set.
+pub fn analyze_with_templates<'t>(
+    ast: &Ast,
+    registry: Option<&FunctionRegistry>,
+    templates: impl IntoIterator<Item = &'t str>,
+) -> TemplateAnalysis {
     let mut analyzer = Analyzer::new(registry);
-    analyzer.walk_block(&ast.root);
+    analyzer
+        .defined_templates
+        .extend(templates.into_iter().map(str::to_string));
+    analyzer.defined_templates.insert(ast.name.clone());
+    analyzer.visit_block(&ast.root);
     analyzer.finish()
 }
 
 /// Summary of static insights gathered from a template analysis pass.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TemplateAnalysis {
-    pub version: &'static str,
+    /// Version of the engine that produced the analysis, kept as written when
+    /// deserialized; the document schema is versioned separately.
+    pub version: String,
     pub precision: Precision,
     pub has_template_invocation: bool,
     pub variables: Vec<VariableAccess>,
     pub functions: Vec<FunctionCall>,
     pub unknown_functions: Vec<FunctionCall>,
     pub templates: Vec<TemplateCall>,
+    pub unknown_templates: Vec<TemplateCall>,
     pub controls: Vec<ControlUsage>,
     pub issues: Vec<AnalysisIssue>,
 }
 
 /// Signals how confident the analyzer is in its findings.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum Precision {
     /// The analyzer tracked every construct exactly.
     Precise,
@@ -39,6 +69,7 @@ pub enum Precision {
 
 /// Describes an observed template variable access.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VariableAccess {
     pub path: String,
     pub span: Span,
@@ -46,8 +77,17 @@ pub struct VariableAccess {
     pub certainty: Certainty,
 }
 
+impl VariableAccess {
+    /// Resolves [`VariableAccess::span`] to line/column positions in `source`.
+    pub fn range(&self, source: &SourceMap) -> SourceRange {
+        source.range(self.span)
+    }
+}
+
 /// Distinguishes whether a variable came from dot or dollar notation.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum VariableKind {
     Dot,
     Dollar,
@@ -55,6 +95,8 @@ pub enum VariableKind {
 
 /// Indicates whether the analyzer is sure the usage will execute.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum Certainty {
     Certain,
     Uncertain,
@@ -62,14 +104,24 @@ pub enum Certainty {
 
 /// Represents a helper invocation encountered in the template.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FunctionCall {
     pub name: String,
     pub span: Span,
     pub source: FunctionSource,
 }
 
+impl FunctionCall {
+    /// Resolves [`FunctionCall::span`] to line/column positions in `source`.
+    pub fn range(&self, source: &SourceMap) -> SourceRange {
+        source.range(self.span)
+    }
+}
+
 /// Identifies where a helper came from.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum FunctionSource {
     /// The helper resolved to a registry entry.
     Registered,
@@ -79,38 +131,68 @@ pub enum FunctionSource {
 
 /// Notes a call to another template, including indirect invocations via variables.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TemplateCall {
     pub span: Span,
     pub name: Option<String>,
     pub indirect: bool,
+    /// Whether the invoked name matched a known template definition.
+    pub resolved: bool,
+}
+
+impl TemplateCall {
+    /// Resolves [`TemplateCall::span`] to line/column positions in `source`.
+    pub fn range(&self, source: &SourceMap) -> SourceRange {
+        source.range(self.span)
+    }
 }
 
 /// Records uses of control-flow constructs such as `if` and `range`.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ControlUsage {
     pub kind: ControlKind,
     pub span: Span,
 }
 
+impl ControlUsage {
+    /// Resolves [`ControlUsage::span`] to line/column positions in `source`.
+    pub fn range(&self, source: &SourceMap) -> SourceRange {
+        source.range(self.span)
+    }
+}
+
 /// Enumerates the control-flow primitives surfaced in analysis.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum ControlKind {
     If,
     Range,
     With,
     Block,
     Define,
+    Break,
+    Continue,
     Else,
     End,
 }
 
 /// Captures non-fatal issues the analyzer noticed, such as syntax ambiguities.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AnalysisIssue {
     pub message: String,
     pub span: Option<Span>,
 }
 
+impl AnalysisIssue {
+    /// Resolves the issue's span, if any, to line/column positions in `source`.
+    pub fn range(&self, source: &SourceMap) -> Option<SourceRange> {
+        self.span.map(|span| source.range(span))
+    }
+}
+
 struct Analyzer<'a> {
     registry: Option<&'a FunctionRegistry>,
     variables: Vec<VariableAccess>,
@@ -122,6 +204,10 @@ struct Analyzer<'a> {
     has_template: bool,
     conservative: bool,
     seen_vars: HashSet<(String, Span)>,
+    defined_templates: HashSet<String>,
+    /// Span of the action being walked; everything found inside it is
+    /// reported there.
+    span: Span,
 }
 
 impl<'a> Analyzer<'a> {
@@ -137,12 +223,28 @@ impl<'a> Analyzer<'a> {
             has_template: false,
             conservative: false,
             seen_vars: HashSet::new(),
+            defined_templates: HashSet::new(),
+            span: Span::new(0, 0),
         }
     }
 
-    fn finish(self) -> TemplateAnalysis {
+    fn finish(mut self) -> TemplateAnalysis {
+        // Definitions may follow their first use, so resolve calls once the walk is done.
+        for call in &mut self.templates {
+            call.resolved = call
+                .name
+                .as_ref()
+                .is_some_and(|name| self.defined_templates.contains(name));
+        }
+        let unknown_templates = self
+            .templates
+            .iter()
+            .filter(|call| !call.indirect && !call.resolved)
+            .cloned()
+            .collect();
+
         TemplateAnalysis {
-            version: env!("CARGO_PKG_VERSION"),
+            version: env!("CARGO_PKG_VERSION").to_string(),
             precision: if self.conservative {
                 Precision::Conservative
             } else {
@@ -153,72 +255,12 @@ impl<'a> Analyzer<'a> {
             functions: self.functions,
             unknown_functions: self.unknown_functions,
             templates: self.templates,
+            unknown_templates,
             controls: self.controls,
             issues: self.issues,
         }
     }
 
-    fn walk_block(&mut self, block: &crate::ast::Block) {
-        for node in &block.nodes {
-            match node {
-                Node::Action(action) => self.visit_action(action),
-                Node::If(if_node) => self.visit_if(if_node),
-                Node::Range(range_node) => self.visit_range(range_node),
-                Node::With(with_node) => self.visit_with(with_node),
-                Node::Text(_) | Node::Comment(_) => {}
-            }
-        }
-    }
-
-    fn visit_action(&mut self, action: &ActionNode) {
-        self.inspect_tokens(&action.tokens);
-        self.visit_pipeline(&action.pipeline, action.span);
-    }
-
-    fn visit_if(&mut self, node: &IfNode) {
-        self.inspect_tokens(&node.tokens);
-        self.controls.push(ControlUsage {
-            kind: ControlKind::If,
-            span: node.span,
-        });
-        self.visit_pipeline(&node.pipeline, node.span);
-        self.walk_block(&node.then_block);
-        for branch in &node.else_if_branches {
-            self.inspect_tokens(&branch.tokens);
-            self.visit_pipeline(&branch.pipeline, branch.span);
-            self.walk_block(&branch.block);
-        }
-        if let Some(else_block) = &node.else_block {
-            self.walk_block(else_block);
-        }
-    }
-
-    fn visit_range(&mut self, node: &RangeNode) {
-        self.inspect_tokens(&node.tokens);
-        self.controls.push(ControlUsage {
-            kind: ControlKind::Range,
-            span: node.span,
-        });
-        self.visit_pipeline(&node.pipeline, node.span);
-        self.walk_block(&node.then_block);
-        if let Some(else_block) = &node.else_block {
-            self.walk_block(else_block);
-        }
-    }
-
-    fn visit_with(&mut self, node: &WithNode) {
-        self.inspect_tokens(&node.tokens);
-        self.controls.push(ControlUsage {
-            kind: ControlKind::With,
-            span: node.span,
-        });
-        self.visit_pipeline(&node.pipeline, node.span);
-        self.walk_block(&node.then_block);
-        if let Some(else_block) = &node.else_block {
-            self.walk_block(else_block);
-        }
-    }
-
     fn inspect_tokens(&mut self, tokens: &[Token]) {
         for token in tokens {
             match token.kind {
@@ -236,56 +278,43 @@ impl<'a> Analyzer<'a> {
         }
     }
 
-    fn visit_pipeline(&mut self, pipeline: &crate::ast::Pipeline, span: Span) {
-        for command in &pipeline.commands {
-            self.visit_command(command, span);
-        }
-    }
-
-    fn visit_command(&mut self, command: &Command, span: Span) {
-        self.collect_expr(&command.target, span);
-
-        match &command.target {
-            Expression::Identifier(name) => {
-                let lowered = name.as_str();
-                if lowered == "template" || lowered == "block" {
-                    self.record_template(command, span, lowered == "block");
-                } else if let Some(control) = control_kind(lowered) {
-                    self.controls.push(ControlUsage {
-                        kind: control,
-                        span,
-                    });
-                } else {
-                    self.record_function(name.clone(), span);
-                }
+    /// Records the path reached through a chain. Chains rooted at a plain field
+    /// or variable collapse into that path; anything computed is reported with
+    /// its source text and marked uncertain.
+    fn collect_chain(&mut self, target: &Expression, fields: &[String]) {
+        let span = self.span;
+        let base = match target {
+            Expression::PipelineExpr(pipeline)
+                if pipeline.commands.len() == 1 && pipeline.commands[0].args.is_empty() =>
+            {
+                &pipeline.commands[0].target
             }
-            Expression::Variable(name) => {
-                self.record_variable(name.clone(), span, VariableKind::Dollar, Certainty::Certain);
-            }
-            _ => {}
-        }
-
-        for arg in &command.args {
-            self.collect_expr(arg, span);
-        }
-    }
-
-    fn collect_expr(&mut self, expr: &Expression, span: Span) {
-        match expr {
-            Expression::Field(parts) => {
-                let (path, certainty) = normalize_field(parts);
+            other => other,
+        };
+        match base {
+            Expression::Field(parts) if parts.first().map_or(true, |p| !p.starts_with('$')) => {
+                let mut joined = parts.clone();
+                joined.extend(fields.iter().cloned());
+                let (path, certainty) = normalize_field(&joined);
                 self.record_variable(path, span, VariableKind::Dot, certainty);
             }
-            Expression::Identifier(name) if name.starts_with('$') => {
-                self.record_variable(name.clone(), span, VariableKind::Dollar, Certainty::Certain);
-            }
-            Expression::PipelineExpr(pipeline) => {
-                self.visit_pipeline(pipeline, span);
+            Expression::Field(parts) => {
+                let path = format!("{}.{}", parts.join("."), fields.join("."));
+                self.record_variable(path, span, VariableKind::Dollar, Certainty::Certain);
             }
             Expression::Variable(name) => {
-                self.record_variable(name.clone(), span, VariableKind::Dollar, Certainty::Certain);
+                let path = format!("{name}.{}", fields.join("."));
+                self.record_variable(path, span, VariableKind::Dollar, Certainty::Certain);
+            }
+            _ => {
+                self.visit_expression(target);
+                let path = format!(
+                    "{}.{}",
+                    crate::expression_to_string(target),
+                    fields.join(".")
+                );
+                self.record_variable(path, span, VariableKind::Dot, Certainty::Uncertain);
             }
-            _ => {}
         }
     }
 
@@ -324,14 +353,9 @@ impl<'a> Analyzer<'a> {
         self.functions.push(call);
     }
 
-    fn record_template(&mut self, command: &Command, span: Span, is_block: bool) {
+    fn record_template(&mut self, name: Option<String>, span: Span, is_block: bool) {
         self.has_template = true;
-        let template_name = command.args.first();
-        let (name, indirect) = match template_name {
-            Some(Expression::StringLiteral(lit)) => (Some(lit.clone()), false),
-            Some(_) => (None, true),
-            None => (None, true),
-        };
+        let indirect = name.is_none();
         if indirect {
             self.mark_conservative("dynamic template invocation", Some(span));
         }
@@ -339,15 +363,17 @@ impl<'a> Analyzer<'a> {
             span,
             name,
             indirect,
+            resolved: false,
         });
         if is_block {
-            self.controls.push(ControlUsage {
-                kind: ControlKind::Block,
-                span,
-            });
+            self.record_control(ControlKind::Block, span);
         }
     }
 
+    fn record_control(&mut self, kind: ControlKind, span: Span) {
+        self.controls.push(ControlUsage { kind, span });
+    }
+
     fn mark_conservative(&mut self, message: impl Into<String>, span: Option<Span>) {
         self.conservative = true;
         self.issues.push(AnalysisIssue {
@@ -357,6 +383,120 @@ impl<'a> Analyzer<'a> {
     }
 }
 
+impl<'ast> Visit<'ast> for Analyzer<'_> {
+    fn visit_action(&mut self, node: &'ast ActionNode) {
+        self.inspect_tokens(&node.tokens);
+        self.span = node.span;
+        walk_action(self, node);
+    }
+
+    fn visit_if(&mut self, node: &'ast IfNode) {
+        self.inspect_tokens(&node.tokens);
+        self.record_control(ControlKind::If, node.span);
+        self.span = node.span;
+        walk_if(self, node);
+    }
+
+    fn visit_else_if(&mut self, branch: &'ast ElseIfBranch) {
+        self.inspect_tokens(&branch.tokens);
+        self.span = branch.span;
+        walk_else_if(self, branch);
+    }
+
+    fn visit_range(&mut self, node: &'ast RangeNode) {
+        self.inspect_tokens(&node.tokens);
+        self.record_control(ControlKind::Range, node.span);
+        self.span = node.span;
+        walk_range(self, node);
+    }
+
+    fn visit_with(&mut self, node: &'ast WithNode) {
+        self.inspect_tokens(&node.tokens);
+        self.record_control(ControlKind::With, node.span);
+        self.span = node.span;
+        walk_with(self, node);
+    }
+
+    fn visit_define(&mut self, node: &'ast DefineNode) {
+        self.record_control(ControlKind::Define, node.span);
+        self.defined_templates.insert(node.name.clone());
+        walk_define(self, node);
+    }
+
+    fn visit_template(&mut self, node: &'ast TemplateNode) {
+        self.inspect_tokens(&node.tokens);
+        self.span = node.span;
+        self.record_template(Some(node.name.clone()), node.span, false);
+        if let Some(pipeline) = &node.pipeline {
+            self.visit_pipeline(pipeline);
+        }
+    }
+
+    fn visit_block_node(&mut self, node: &'ast BlockNode) {
+        self.inspect_tokens(&node.tokens);
+        self.defined_templates.insert(node.name.clone());
+        self.record_template(Some(node.name.clone()), node.span, true);
+        self.span = node.span;
+        walk_block_node(self, node);
+    }
+
+    fn visit_break(&mut self, node: &'ast BreakNode) {
+        self.record_control(ControlKind::Break, node.span);
+    }
+
+    fn visit_continue(&mut self, node: &'ast ContinueNode) {
+        self.record_control(ControlKind::Continue, node.span);
+    }
+
+    fn visit_command(&mut self, command: &'ast Command) {
+        let span = self.span;
+        self.visit_expression(&command.target);
+
+        match &command.target {
+            Expression::Identifier(name) => {
+                let lowered = name.as_str();
+                if lowered == "template" || lowered == "block" {
+                    let name = match command.args.first() {
+                        Some(Expression::StringLiteral(lit)) => Some(lit.clone()),
+                        _ => None,
+                    };
+                    self.record_template(name, span, lowered == "block");
+                } else if let Some(control) = control_kind(lowered) {
+                    self.record_control(control, span);
+                } else {
+                    self.record_function(name.clone(), span);
+                }
+            }
+            Expression::Variable(name) => {
+                self.record_variable(name.clone(), span, VariableKind::Dollar, Certainty::Certain);
+            }
+            _ => {}
+        }
+
+        for arg in &command.args {
+            self.visit_expression(arg);
+        }
+    }
+
+    fn visit_expression(&mut self, expr: &'ast Expression) {
+        let span = self.span;
+        match expr {
+            Expression::Field(parts) => {
+                let (path, certainty) = normalize_field(parts);
+                self.record_variable(path, span, VariableKind::Dot, certainty);
+            }
+            Expression::Identifier(name) if name.starts_with('$') => {
+                self.record_variable(name.clone(), span, VariableKind::Dollar, Certainty::Certain);
+            }
+            Expression::Variable(name) => {
+                self.record_variable(name.clone(), span, VariableKind::Dollar, Certainty::Certain);
+            }
+            Expression::Chain { target, fields } => self.collect_chain(target, fields),
+            _ => walk_expression(self, expr),
+        }
+    }
+}
+
 fn control_kind(name: &str) -> Option<ControlKind> {
     match name {
         "if" => Some(ControlKind::If),
@@ -364,6 +504,8 @@ fn control_kind(name: &str) -> Option<ControlKind> {
         "with" => Some(ControlKind::With),
         "block" => Some(ControlKind::Block),
         "define" => Some(ControlKind::Define),
+        "break" => Some(ControlKind::Break),
+        "continue" => Some(ControlKind::Continue),
         "else" => Some(ControlKind::Else),
         "end" => Some(ControlKind::End),
         _ => None,
@@ -0,0 +1,231 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use bincode::Options;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::ast::{Ast, Delimiters};
+use crate::dialect::Dialect;
+use crate::document::TemplateDocument;
+use crate::error::Error;
+use crate::parser::ParseOptions;
+use crate::runtime::FunctionRegistry;
+use crate::set::TemplateSet;
+use crate::source_map::SourceMap;
+use crate::Template;
+
+/// Leading bytes of every artifact.
+const MAGIC: &[u8; 4] = b"LGTA";
+
+/// Precedes the payload so that version and registry mismatches are reported
+/// before the engine tries to decode a tree it may not understand.
+#[derive(Serialize, Deserialize)]
+struct Header {
+    engine_version: String,
+    kind: Kind,
+    functions: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum Kind {
+    Template,
+    Set,
+}
+
+impl Kind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Kind::Template => "template",
+            Kind::Set => "template set",
+        }
+    }
+}
+
+/// Payload of a [`TemplateSet`] artifact. Templates parsed from the same source
+/// share one entry in `sources`.
+#[derive(Serialize, Deserialize)]
+struct SetPayload {
+    delimiters: Delimiters,
+    dialect: Dialect,
+    sources: Vec<String>,
+    trees: Vec<(Ast, Option<usize>)>,
+}
+
+fn options() -> impl Options {
+    bincode::DefaultOptions::new()
+}
+
+fn encode(
+    kind: Kind,
+    functions: &FunctionRegistry,
+    payload: &impl Serialize,
+) -> Result<Vec<u8>, Error> {
+    let header = Header {
+        engine_version: env!("CARGO_PKG_VERSION").to_string(),
+        kind,
+        functions: functions.function_names(),
+    };
+    let mut out = MAGIC.to_vec();
+    options()
+        .serialize_into(&mut out, &header)
+        .and_then(|()| options().serialize_into(&mut out, payload))
+        .map_err(|err| artifact_error(format!("cannot write {} artifact", kind.as_str()), err))?;
+    Ok(out)
+}
+
+fn decode<T: DeserializeOwned>(
+    bytes: &[u8],
+    kind: Kind,
+    functions: &FunctionRegistry,
+) -> Result<T, Error> {
+    let bytes = bytes
+        .strip_prefix(MAGIC.as_slice())
+        .ok_or_else(|| Error::parse("not a template artifact", None))?;
+    let header: Header = options()
+        .allow_trailing_bytes()
+        .deserialize(bytes)
+        .map_err(|err| artifact_error("corrupt artifact header", err))?;
+    if header.engine_version != env!("CARGO_PKG_VERSION") {
+        return Err(Error::parse(
+            format!(
+                "artifact was compiled by engine {}, this is {}",
+                header.engine_version,
+                env!("CARGO_PKG_VERSION")
+            ),
+            None,
+        ));
+    }
+    if header.kind != kind {
+        return Err(Error::parse(
+            format!(
+                "artifact holds a {}, not a {}",
+                header.kind.as_str(),
+                kind.as_str()
+            ),
+            None,
+        ));
+    }
+    check_function_names(&header.functions, &functions.function_names())?;
+    // The header encodes to the bytes it was read from, so its size is
+    // where the payload starts.
+    let start = options()
+        .serialized_size(&header)
+        .map_err(|err| artifact_error("corrupt artifact header", err))?;
+    let payload = usize::try_from(start)
+        .ok()
+        .and_then(|start| bytes.get(start..))
+        .ok_or_else(|| Error::parse("corrupt artifact header", None))?;
+    options()
+        .deserialize(payload)
+        .map_err(|err| artifact_error("corrupt artifact payload", err))
+}
+
+/// Parsing depends on the registry (Go-dialect templates reject unknown
+/// functions, and `break`/`continue` can be function names), so an artifact
+/// only loads with a registry offering exactly the functions it was built with.
+fn check_function_names(compiled: &[String], registry: &[String]) -> Result<(), Error> {
+    if compiled == registry {
+        return Ok(());
+    }
+    let missing: Vec<&str> = compiled
+        .iter()
+        .filter(|name| !registry.contains(name))
+        .map(String::as_str)
+        .collect();
+    let extra: Vec<&str> = registry
+        .iter()
+        .filter(|name| !compiled.contains(name))
+        .map(String::as_str)
+        .collect();
+    Err(Error::parse(
+        format!(
+            "artifact was compiled with a different function registry (missing: {missing:?}, unexpected: {extra:?})"
+        ),
+        None,
+    ))
+}
+
+fn artifact_error(message: impl Into<String>, err: bincode::Error) -> Error {
+    Error::Parse {
+        message: message.into(),
+        source: Some(err),
+        span: None,
+    }
+}
+
+impl Template {
+    /// Compiles the template into a compact binary artifact that
+    /// [`Template::from_artifact`] loads without lexing or parsing. The
+    /// artifact records the engine version and the names of the template's
+    /// functions.
+    pub fn to_artifact(&self) -> Result<Vec<u8>, Error> {
+        encode(Kind::Template, &self.functions, &self.to_document())
+    }
+
+    /// Loads a template compiled by [`Template::to_artifact`]. Fails unless the
+    /// artifact was written by this engine version and `functions` has exactly
+    /// the function names the template was compiled with.
+    pub fn from_artifact(bytes: &[u8], functions: FunctionRegistry) -> Result<Self, Error> {
+        let document: TemplateDocument = decode(bytes, Kind::Template, &functions)?;
+        Ok(Self::from_document_unchecked(document, functions))
+    }
+}
+
+impl TemplateSet {
+    /// Compiles every associated template into a binary artifact; see
+    /// [`Template::to_artifact`]. The set's delimiters and dialect are kept.
+    pub fn to_artifact(&self) -> Result<Vec<u8>, Error> {
+        let mut sources: Vec<String> = Vec::new();
+        let mut indices: HashMap<*const SourceMap, usize> = HashMap::new();
+        let mut trees = Vec::new();
+        for (tree, source) in self.trees() {
+            let source = source.map(|source| {
+                *indices.entry(Arc::as_ptr(source)).or_insert_with(|| {
+                    sources.push(source.source().to_string());
+                    sources.len() - 1
+                })
+            });
+            trees.push((Ast::clone(tree), source));
+        }
+        let payload = SetPayload {
+            delimiters: self.parse_options().delimiters().clone(),
+            dialect: self.parse_options().dialect(),
+            sources,
+            trees,
+        };
+        encode(Kind::Set, &self.functions(), &payload)
+    }
+
+    /// Loads a set compiled by [`TemplateSet::to_artifact`], under the same
+    /// conditions as [`Template::from_artifact`].
+    pub fn from_artifact(bytes: &[u8], functions: FunctionRegistry) -> Result<Self, Error> {
+        let payload: SetPayload = decode(bytes, Kind::Set, &functions)?;
+        let sources: Vec<Arc<SourceMap>> = payload
+            .sources
+            .into_iter()
+            .map(|source| Arc::new(SourceMap::new(source)))
+            .collect();
+        let mut trees = HashMap::with_capacity(payload.trees.len());
+        let mut tree_sources = HashMap::new();
+        for (ast, source) in payload.trees {
+            if let Some(source) = source {
+                let source = sources
+                    .get(source)
+                    .ok_or_else(|| Error::parse("corrupt artifact payload", None))?;
+                tree_sources.insert(ast.name.clone(), source.clone());
+            }
+            trees.insert(ast.name.clone(), Arc::new(ast));
+        }
+        let options = ParseOptions::new()
+            .with_delimiters(payload.delimiters.left, payload.delimiters.right)
+            .with_dialect(payload.dialect);
+        Ok(TemplateSet::from_parts(
+            trees,
+            tree_sources,
+            functions,
+            options,
+        ))
+    }
+}
@@ -1,8 +1,15 @@
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 use std::fmt;
 
+use crate::dialect::Dialect;
+
+pub mod visit;
+
+pub use visit::{Visit, VisitMut};
+
 /// Byte offsets into the original template source.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Span {
     pub start: usize,
     pub end: usize,
@@ -14,24 +21,95 @@ impl Span {
     }
 }
 
+/// Action delimiters (Go's `Template.Delims`), `{{` and `}}` by default.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Delimiters {
+    pub left: String,
+    pub right: String,
+}
+
+impl Delimiters {
+    /// Creates a delimiter pair; like Go, an empty string selects the default.
+    pub fn new(left: impl Into<String>, right: impl Into<String>) -> Self {
+        let left = left.into();
+        let right = right.into();
+        Self {
+            left: if left.is_empty() { "{{".into() } else { left },
+            right: if right.is_empty() { "}}".into() } else { right },
+        }
+    }
+}
+
+impl Default for Delimiters {
+    fn default() -> Self {
+        Self::new("", "")
+    }
+}
+
 /// Root AST structure for a parsed template.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Ast {
     pub name: String,
+    /// Name of the template whose source this tree was parsed from; differs
+    /// from `name` for `{{define}}`/`{{block}}` trees, like Go's `ParseName`.
+    pub parse_name: String,
     pub root: Block,
+    /// Delimiters the template was parsed with, reused when printing it.
+    pub delimiters: Delimiters,
+    /// Dialect the template was parsed with; execution follows the same rules.
+    pub dialect: Dialect,
 }
 
 impl Ast {
     pub fn new(name: impl Into<String>, root: Block) -> Self {
+        let name = name.into();
+        Self {
+            parse_name: name.clone(),
+            name,
+            root,
+            delimiters: Delimiters::default(),
+            dialect: Dialect::default(),
+        }
+    }
+
+    pub fn with_delimiters(mut self, delimiters: Delimiters) -> Self {
+        self.delimiters = delimiters;
+        self
+    }
+
+    pub fn with_dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Prints the tree from the concrete syntax recorded by the parser. For an
+    /// unmodified tree this reproduces the parsed source byte for byte; edits
+    /// to text nodes and to the [`ActionSyntax`] of actions show up in the
+    /// output, while edits to parsed pipelines alone do not.
+    pub fn to_source(&self) -> String {
+        let mut out = String::new();
+        self.root.write_source(&mut out, &self.delimiters);
+        out
+    }
+
+    /// Creates the tree of a `{{define}}`/`{{block}}` nested in this template,
+    /// inheriting its delimiters and dialect.
+    pub fn nested(&self, name: impl Into<String>, root: Block) -> Self {
         Self {
             name: name.into(),
+            parse_name: self.parse_name.clone(),
             root,
+            delimiters: self.delimiters.clone(),
+            dialect: self.dialect,
         }
     }
 }
 
 /// A sequential block of nodes (equivalent to Go's `parse.ListNode`).
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Block {
     pub nodes: Vec<Node>,
 }
@@ -40,10 +118,131 @@ impl Block {
     pub fn push(&mut self, node: Node) {
         self.nodes.push(node);
     }
+
+    fn write_source(&self, out: &mut String, delimiters: &Delimiters) {
+        for node in &self.nodes {
+            match node {
+                Node::Text(text) => out.push_str(&text.text),
+                Node::Action(ActionNode { syntax, .. })
+                | Node::Comment(CommentNode { syntax, .. })
+                | Node::Template(TemplateNode { syntax, .. })
+                | Node::Break(BreakNode { syntax, .. })
+                | Node::Continue(ContinueNode { syntax, .. }) => syntax.write(out, delimiters),
+                Node::If(node) => {
+                    node.syntax.open.write(out, delimiters);
+                    node.then_block.write_source(out, delimiters);
+                    for branch in &node.else_if_branches {
+                        branch.syntax.write(out, delimiters);
+                        branch.block.write_source(out, delimiters);
+                    }
+                    node.syntax
+                        .write_else(out, delimiters, node.else_block.as_ref());
+                }
+                Node::Range(node) => {
+                    node.syntax.open.write(out, delimiters);
+                    node.then_block.write_source(out, delimiters);
+                    node.syntax
+                        .write_else(out, delimiters, node.else_block.as_ref());
+                }
+                Node::With(node) => {
+                    node.syntax.open.write(out, delimiters);
+                    node.then_block.write_source(out, delimiters);
+                    node.syntax
+                        .write_else(out, delimiters, node.else_block.as_ref());
+                }
+                Node::Define(node) => {
+                    node.syntax.open.write(out, delimiters);
+                    node.block.write_source(out, delimiters);
+                    node.syntax.write_else(out, delimiters, None);
+                }
+                Node::Block(node) => {
+                    node.syntax.open.write(out, delimiters);
+                    node.block.write_source(out, delimiters);
+                    node.syntax.write_else(out, delimiters, None);
+                }
+            }
+        }
+    }
+}
+
+/// One action exactly as written: trim markers, the whitespace around its
+/// body, and the whitespace its trim markers removed from the neighbouring
+/// text, which the parsed [`TextNode`]s no longer contain.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ActionSyntax {
+    pub trim_left: bool,
+    pub trim_right: bool,
+    /// Whitespace between the left delimiter (or trim marker) and the body.
+    pub leading: String,
+    /// The body as written, e.g. `if .ready` or `/* note */`.
+    pub body: String,
+    /// Whitespace between the body and the trim marker or right delimiter.
+    pub trailing: String,
+    /// Whitespace removed from the end of the preceding text by `trim_left`.
+    pub trimmed_before: String,
+    /// Whitespace skipped after the action because of `trim_right`.
+    pub trimmed_after: String,
+}
+
+impl ActionSyntax {
+    /// Creates the syntax of an untrimmed action, e.g. `ActionSyntax::new("end")`
+    /// when inserting nodes into a tree.
+    pub fn new(body: impl Into<String>) -> Self {
+        Self {
+            body: body.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Appends the action, and the whitespace it trimmed, to `out`.
+    pub fn write(&self, out: &mut String, delimiters: &Delimiters) {
+        out.push_str(&self.trimmed_before);
+        out.push_str(&delimiters.left);
+        if self.trim_left {
+            out.push('-');
+        }
+        out.push_str(&self.leading);
+        out.push_str(&self.body);
+        out.push_str(&self.trailing);
+        if self.trim_right {
+            out.push('-');
+        }
+        out.push_str(&delimiters.right);
+        out.push_str(&self.trimmed_after);
+    }
+}
+
+/// Concrete syntax of the actions that delimit a control structure. The
+/// `{{else if}}` actions of an `if` live on its [`ElseIfBranch`]es.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ControlSyntax {
+    /// The opening action, e.g. `{{range .items}}`.
+    pub open: ActionSyntax,
+    /// The `{{else}}` action, present when the structure has an else block.
+    pub else_action: Option<ActionSyntax>,
+    /// The closing `{{end}}`.
+    pub end: ActionSyntax,
+}
+
+impl ControlSyntax {
+    fn write_else(&self, out: &mut String, delimiters: &Delimiters, else_block: Option<&Block>) {
+        if let Some(else_block) = else_block {
+            match &self.else_action {
+                Some(action) => action.write(out, delimiters),
+                None => ActionSyntax::new("else").write(out, delimiters),
+            }
+            else_block.write_source(out, delimiters);
+        }
+        self.end.write(out, delimiters);
+    }
 }
 
 /// Node types recognised by the parser.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 #[non_exhaustive]
 pub enum Node {
     Text(TextNode),
@@ -52,6 +251,11 @@ pub enum Node {
     If(IfNode),
     Range(RangeNode),
     With(WithNode),
+    Define(DefineNode),
+    Template(TemplateNode),
+    Block(BlockNode),
+    Break(BreakNode),
+    Continue(ContinueNode),
 }
 
 impl Node {
@@ -63,12 +267,18 @@ impl Node {
             Node::If(node) => node.span,
             Node::Range(node) => node.span,
             Node::With(node) => node.span,
+            Node::Define(node) => node.span,
+            Node::Template(node) => node.span,
+            Node::Block(node) => node.span,
+            Node::Break(node) => node.span,
+            Node::Continue(node) => node.span,
         }
     }
 }
 
 /// Raw text literal.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TextNode {
     pub span: Span,
     pub text: String,
@@ -85,6 +295,7 @@ impl TextNode {
 
 /// Template action with parsed pipeline information.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ActionNode {
     pub span: Span,
     pub source: String,
@@ -92,6 +303,8 @@ pub struct ActionNode {
     pub pipeline: Pipeline,
     pub trim_left: bool,
     pub trim_right: bool,
+    /// Source spelling of the action, for lossless printing.
+    pub syntax: ActionSyntax,
 }
 
 impl ActionNode {
@@ -110,30 +323,45 @@ impl ActionNode {
             pipeline,
             trim_left,
             trim_right,
+            syntax: ActionSyntax::default(),
         }
     }
 
+    /// Records how the action was written; see [`Ast::to_source`].
+    pub fn with_syntax(mut self, syntax: ActionSyntax) -> Self {
+        self.syntax = syntax;
+        self
+    }
+
     pub fn to_template_fragment(&self) -> String {
-        let mut out = String::from("{{");
+        self.to_template_fragment_with(&Delimiters::default())
+    }
+
+    /// Prints the action using the given delimiters.
+    pub fn to_template_fragment_with(&self, delimiters: &Delimiters) -> String {
+        let mut out = delimiters.left.clone();
         if self.trim_left {
-            out.push('-');
+            out.push_str("- ");
         }
         out.push_str(&self.source);
         if self.trim_right {
-            out.push('-');
+            out.push_str(" -");
         }
-        out.push_str("}}");
+        out.push_str(&delimiters.right);
         out
     }
 }
 
 /// Template comment (e.g. `{{/* comment */}}`).
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CommentNode {
     pub span: Span,
     pub text: String,
     pub trim_left: bool,
     pub trim_right: bool,
+    /// Source spelling of the action, for lossless printing.
+    pub syntax: ActionSyntax,
 }
 
 impl CommentNode {
@@ -143,27 +371,40 @@ impl CommentNode {
             text: text.into(),
             trim_left,
             trim_right,
+            syntax: ActionSyntax::default(),
         }
     }
 
+    /// Records how the action was written; see [`Ast::to_source`].
+    pub fn with_syntax(mut self, syntax: ActionSyntax) -> Self {
+        self.syntax = syntax;
+        self
+    }
+
     pub fn to_template_fragment(&self) -> String {
-        let mut out = String::from("{{");
+        self.to_template_fragment_with(&Delimiters::default())
+    }
+
+    /// Prints the comment using the given delimiters.
+    pub fn to_template_fragment_with(&self, delimiters: &Delimiters) -> String {
+        let mut out = delimiters.left.clone();
         if self.trim_left {
-            out.push('-');
+            out.push_str("- ");
         }
         out.push_str("/*");
         out.push_str(&self.text);
         out.push_str("*/");
         if self.trim_right {
-            out.push('-');
+            out.push_str(" -");
         }
-        out.push_str("}}");
+        out.push_str(&delimiters.right);
         out
     }
 }
 
 /// Conditional branch node (mirrors Go's `parse.IfNode`).
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IfNode {
     pub span: Span,
     pub tokens: Vec<crate::lexer::Token>,
@@ -171,6 +412,8 @@ pub struct IfNode {
     pub then_block: Block,
     pub else_if_branches: Vec<ElseIfBranch>,
     pub else_block: Option<Block>,
+    /// Source spelling of the delimiting actions, for lossless printing.
+    pub syntax: ControlSyntax,
 }
 
 impl IfNode {
@@ -189,17 +432,27 @@ impl IfNode {
             then_block,
             else_if_branches,
             else_block,
+            syntax: ControlSyntax::default(),
         }
     }
+
+    /// Records how the structure was written; see [`Ast::to_source`].
+    pub fn with_syntax(mut self, syntax: ControlSyntax) -> Self {
+        self.syntax = syntax;
+        self
+    }
 }
 
 /// Captures an `{{else if ...}}` branch.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ElseIfBranch {
     pub span: Span,
     pub tokens: Vec<crate::lexer::Token>,
     pub pipeline: Pipeline,
     pub block: Block,
+    /// Source spelling of the action, for lossless printing.
+    pub syntax: ActionSyntax,
 }
 
 impl ElseIfBranch {
@@ -214,18 +467,28 @@ impl ElseIfBranch {
             tokens,
             pipeline,
             block,
+            syntax: ActionSyntax::default(),
         }
     }
+
+    /// Records how the action was written; see [`Ast::to_source`].
+    pub fn with_syntax(mut self, syntax: ActionSyntax) -> Self {
+        self.syntax = syntax;
+        self
+    }
 }
 
 /// Range iteration node.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RangeNode {
     pub span: Span,
     pub tokens: Vec<crate::lexer::Token>,
     pub pipeline: Pipeline,
     pub then_block: Block,
     pub else_block: Option<Block>,
+    /// Source spelling of the delimiting actions, for lossless printing.
+    pub syntax: ControlSyntax,
 }
 
 impl RangeNode {
@@ -242,18 +505,28 @@ impl RangeNode {
             pipeline,
             then_block,
             else_block,
+            syntax: ControlSyntax::default(),
         }
     }
+
+    /// Records how the structure was written; see [`Ast::to_source`].
+    pub fn with_syntax(mut self, syntax: ControlSyntax) -> Self {
+        self.syntax = syntax;
+        self
+    }
 }
 
 /// Scoped context node (`with`).
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct WithNode {
     pub span: Span,
     pub tokens: Vec<crate::lexer::Token>,
     pub pipeline: Pipeline,
     pub then_block: Block,
     pub else_block: Option<Block>,
+    /// Source spelling of the delimiting actions, for lossless printing.
+    pub syntax: ControlSyntax,
 }
 
 impl WithNode {
@@ -270,12 +543,184 @@ impl WithNode {
             pipeline,
             then_block,
             else_block,
+            syntax: ControlSyntax::default(),
         }
     }
+
+    /// Records how the structure was written; see [`Ast::to_source`].
+    pub fn with_syntax(mut self, syntax: ControlSyntax) -> Self {
+        self.syntax = syntax;
+        self
+    }
+}
+
+/// Named template definition (`{{define "name"}}...{{end}}`).
+///
+/// Definitions stay in the tree so the template can be printed back out, but
+/// they produce no output where they appear; rendering happens through
+/// `template` or `block` invocations.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DefineNode {
+    pub span: Span,
+    pub tokens: Vec<crate::lexer::Token>,
+    pub name: String,
+    pub block: Block,
+    /// Source spelling of the delimiting actions, for lossless printing.
+    pub syntax: ControlSyntax,
+}
+
+impl DefineNode {
+    pub fn new(
+        span: Span,
+        tokens: Vec<crate::lexer::Token>,
+        name: impl Into<String>,
+        block: Block,
+    ) -> Self {
+        Self {
+            span,
+            tokens,
+            name: name.into(),
+            block,
+            syntax: ControlSyntax::default(),
+        }
+    }
+
+    /// Records how the structure was written; see [`Ast::to_source`].
+    pub fn with_syntax(mut self, syntax: ControlSyntax) -> Self {
+        self.syntax = syntax;
+        self
+    }
+}
+
+/// Invocation of a named template (`{{template "name" pipeline}}`).
+///
+/// The name is usually a string literal; other operands are accepted so that
+/// dynamic invocations can be evaluated at render time.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TemplateNode {
+    pub span: Span,
+    pub tokens: Vec<crate::lexer::Token>,
+    pub name: String,
+    pub pipeline: Option<Pipeline>,
+    /// Source spelling of the action, for lossless printing.
+    pub syntax: ActionSyntax,
+}
+
+impl TemplateNode {
+    pub fn new(
+        span: Span,
+        tokens: Vec<crate::lexer::Token>,
+        name: String,
+        pipeline: Option<Pipeline>,
+    ) -> Self {
+        Self {
+            span,
+            tokens,
+            name,
+            pipeline,
+            syntax: ActionSyntax::default(),
+        }
+    }
+
+    /// Records how the action was written; see [`Ast::to_source`].
+    pub fn with_syntax(mut self, syntax: ActionSyntax) -> Self {
+        self.syntax = syntax;
+        self
+    }
+}
+
+/// Block node (`{{block "name" pipeline}}default{{end}}`), shorthand for
+/// defining a template and executing it in place.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BlockNode {
+    pub span: Span,
+    pub tokens: Vec<crate::lexer::Token>,
+    pub name: String,
+    pub pipeline: Pipeline,
+    pub block: Block,
+    /// Source spelling of the delimiting actions, for lossless printing.
+    pub syntax: ControlSyntax,
+}
+
+impl BlockNode {
+    pub fn new(
+        span: Span,
+        tokens: Vec<crate::lexer::Token>,
+        name: impl Into<String>,
+        pipeline: Pipeline,
+        block: Block,
+    ) -> Self {
+        Self {
+            span,
+            tokens,
+            name: name.into(),
+            pipeline,
+            block,
+            syntax: ControlSyntax::default(),
+        }
+    }
+
+    /// Records how the structure was written; see [`Ast::to_source`].
+    pub fn with_syntax(mut self, syntax: ControlSyntax) -> Self {
+        self.syntax = syntax;
+        self
+    }
+}
+
+/// `{{break}}` inside a `range` body; stops the innermost loop.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BreakNode {
+    pub span: Span,
+    /// Source spelling of the action, for lossless printing.
+    pub syntax: ActionSyntax,
+}
+
+impl BreakNode {
+    pub fn new(span: Span) -> Self {
+        Self {
+            span,
+            syntax: ActionSyntax::default(),
+        }
+    }
+
+    /// Records how the action was written; see [`Ast::to_source`].
+    pub fn with_syntax(mut self, syntax: ActionSyntax) -> Self {
+        self.syntax = syntax;
+        self
+    }
+}
+
+/// `{{continue}}` inside a `range` body; skips to the next iteration.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ContinueNode {
+    pub span: Span,
+    /// Source spelling of the action, for lossless printing.
+    pub syntax: ActionSyntax,
+}
+
+impl ContinueNode {
+    pub fn new(span: Span) -> Self {
+        Self {
+            span,
+            syntax: ActionSyntax::default(),
+        }
+    }
+
+    /// Records how the action was written; see [`Ast::to_source`].
+    pub fn with_syntax(mut self, syntax: ActionSyntax) -> Self {
+        self.syntax = syntax;
+        self
+    }
 }
 
 /// A complete pipeline inside an action.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Pipeline {
     pub declarations: Option<PipelineDeclarations>,
     pub commands: Vec<Command>,
@@ -292,6 +737,7 @@ impl Pipeline {
 
 /// Variable declarations leading a pipeline (e.g. `{{$x := ...}}`).
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PipelineDeclarations {
     pub kind: BindingKind,
     pub variables: Vec<String>,
@@ -305,6 +751,8 @@ impl PipelineDeclarations {
 
 /// Whether the pipeline introduces (`:=`) or assigns (`=`) variables.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 #[non_exhaustive]
 pub enum BindingKind {
     Declare,
@@ -313,25 +761,55 @@ pub enum BindingKind {
 
 /// Individual command in a pipeline.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Command {
     pub target: Expression,
     pub args: Vec<Expression>,
+    /// Source range of the whole command, when parsed from a template.
+    pub span: Option<Span>,
+    /// Source range of each argument, parallel to `args` (empty for commands
+    /// built by hand).
+    pub arg_spans: Vec<Span>,
 }
 
 impl Command {
     pub fn new(target: Expression, args: Vec<Expression>) -> Self {
-        Self { target, args }
+        Self {
+            target,
+            args,
+            span: None,
+            arg_spans: Vec::new(),
+        }
+    }
+
+    /// Records where the command and each of its arguments appear in the source.
+    pub fn with_spans(mut self, span: Span, arg_spans: Vec<Span>) -> Self {
+        self.span = Some(span);
+        self.arg_spans = arg_spans;
+        self
+    }
+
+    /// Returns the span of the argument at `index`, if known.
+    pub fn arg_span(&self, index: usize) -> Option<Span> {
+        self.arg_spans.get(index).copied()
     }
 }
 
 /// Expression node.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 #[non_exhaustive]
 pub enum Expression {
     Identifier(String),
     Field(Vec<String>),
     Variable(String),
     PipelineExpr(Pipeline),
+    /// Field access on a computed value, e.g. `(index .items 0).name` (Go's `ChainNode`).
+    Chain {
+        target: Box<Expression>,
+        fields: Vec<String>,
+    },
     StringLiteral(String),
     NumberLiteral(String),
     BoolLiteral(bool),
@@ -346,6 +824,13 @@ impl Expression {
     pub fn field(path: Vec<String>) -> Self {
         Expression::Field(path)
     }
+
+    pub fn chain(target: Expression, fields: Vec<String>) -> Self {
+        Expression::Chain {
+            target: Box::new(target),
+            fields,
+        }
+    }
 }
 
 impl fmt::Display for Node {
@@ -357,6 +842,11 @@ impl fmt::Display for Node {
             Node::If(_) => write!(f, "If"),
             Node::Range(_) => write!(f, "Range"),
             Node::With(_) => write!(f, "With"),
+            Node::Define(node) => write!(f, "Define({:?})", node.name),
+            Node::Template(node) => write!(f, "Template({:?})", node.name),
+            Node::Block(node) => write!(f, "Block({:?})", node.name),
+            Node::Break(_) => write!(f, "Break"),
+            Node::Continue(_) => write!(f, "Continue"),
         }
     }
 }
@@ -0,0 +1,406 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+//! Traversal of the template tree.
+//!
+//! [`Visit`] walks a borrowed tree and [`VisitMut`] a mutable one. Every method
+//! defaults to the matching `walk_*` function, which visits the children in
+//! source order, so an implementation overrides only the nodes it cares about
+//! and calls the `walk_*` function to keep descending.
+
+use super::{
+    ActionNode, Block, BlockNode, BreakNode, Command, CommentNode, ContinueNode, DefineNode,
+    ElseIfBranch, Expression, IfNode, Node, Pipeline, RangeNode, TemplateNode, TextNode, WithNode,
+};
+
+/// Read-only visitor over a template tree.
+pub trait Visit<'ast> {
+    fn visit_block(&mut self, block: &'ast Block) {
+        walk_block(self, block);
+    }
+
+    fn visit_node(&mut self, node: &'ast Node) {
+        walk_node(self, node);
+    }
+
+    fn visit_text(&mut self, _node: &'ast TextNode) {}
+
+    fn visit_comment(&mut self, _node: &'ast CommentNode) {}
+
+    fn visit_action(&mut self, node: &'ast ActionNode) {
+        walk_action(self, node);
+    }
+
+    fn visit_if(&mut self, node: &'ast IfNode) {
+        walk_if(self, node);
+    }
+
+    fn visit_else_if(&mut self, branch: &'ast ElseIfBranch) {
+        walk_else_if(self, branch);
+    }
+
+    fn visit_range(&mut self, node: &'ast RangeNode) {
+        walk_range(self, node);
+    }
+
+    fn visit_with(&mut self, node: &'ast WithNode) {
+        walk_with(self, node);
+    }
+
+    fn visit_define(&mut self, node: &'ast DefineNode) {
+        walk_define(self, node);
+    }
+
+    fn visit_template(&mut self, node: &'ast TemplateNode) {
+        walk_template(self, node);
+    }
+
+    fn visit_block_node(&mut self, node: &'ast BlockNode) {
+        walk_block_node(self, node);
+    }
+
+    fn visit_break(&mut self, _node: &'ast BreakNode) {}
+
+    fn visit_continue(&mut self, _node: &'ast ContinueNode) {}
+
+    fn visit_pipeline(&mut self, pipeline: &'ast Pipeline) {
+        walk_pipeline(self, pipeline);
+    }
+
+    fn visit_command(&mut self, command: &'ast Command) {
+        walk_command(self, command);
+    }
+
+    fn visit_expression(&mut self, expr: &'ast Expression) {
+        walk_expression(self, expr);
+    }
+}
+
+pub fn walk_block<'ast, V: Visit<'ast> + ?Sized>(visitor: &mut V, block: &'ast Block) {
+    for node in &block.nodes {
+        visitor.visit_node(node);
+    }
+}
+
+pub fn walk_node<'ast, V: Visit<'ast> + ?Sized>(visitor: &mut V, node: &'ast Node) {
+    match node {
+        Node::Text(node) => visitor.visit_text(node),
+        Node::Action(node) => visitor.visit_action(node),
+        Node::Comment(node) => visitor.visit_comment(node),
+        Node::If(node) => visitor.visit_if(node),
+        Node::Range(node) => visitor.visit_range(node),
+        Node::With(node) => visitor.visit_with(node),
+        Node::Define(node) => visitor.visit_define(node),
+        Node::Template(node) => visitor.visit_template(node),
+        Node::Block(node) => visitor.visit_block_node(node),
+        Node::Break(node) => visitor.visit_break(node),
+        Node::Continue(node) => visitor.visit_continue(node),
+    }
+}
+
+pub fn walk_action<'ast, V: Visit<'ast> + ?Sized>(visitor: &mut V, node: &'ast ActionNode) {
+    visitor.visit_pipeline(&node.pipeline);
+}
+
+pub fn walk_if<'ast, V: Visit<'ast> + ?Sized>(visitor: &mut V, node: &'ast IfNode) {
+    visitor.visit_pipeline(&node.pipeline);
+    visitor.visit_block(&node.then_block);
+    for branch in &node.else_if_branches {
+        visitor.visit_else_if(branch);
+    }
+    if let Some(else_block) = &node.else_block {
+        visitor.visit_block(else_block);
+    }
+}
+
+pub fn walk_else_if<'ast, V: Visit<'ast> + ?Sized>(visitor: &mut V, branch: &'ast ElseIfBranch) {
+    visitor.visit_pipeline(&branch.pipeline);
+    visitor.visit_block(&branch.block);
+}
+
+pub fn walk_range<'ast, V: Visit<'ast> + ?Sized>(visitor: &mut V, node: &'ast RangeNode) {
+    visitor.visit_pipeline(&node.pipeline);
+    visitor.visit_block(&node.then_block);
+    if let Some(else_block) = &node.else_block {
+        visitor.visit_block(else_block);
+    }
+}
+
+pub fn walk_with<'ast, V: Visit<'ast> + ?Sized>(visitor: &mut V, node: &'ast WithNode) {
+    visitor.visit_pipeline(&node.pipeline);
+    visitor.visit_block(&node.then_block);
+    if let Some(else_block) = &node.else_block {
+        visitor.visit_block(else_block);
+    }
+}
+
+pub fn walk_define<'ast, V: Visit<'ast> + ?Sized>(visitor: &mut V, node: &'ast DefineNode) {
+    visitor.visit_block(&node.block);
+}
+
+pub fn walk_template<'ast, V: Visit<'ast> + ?Sized>(visitor: &mut V, node: &'ast TemplateNode) {
+    if let Some(pipeline) = &node.pipeline {
+        visitor.visit_pipeline(pipeline);
+    }
+}
+
+pub fn walk_block_node<'ast, V: Visit<'ast> + ?Sized>(visitor: &mut V, node: &'ast BlockNode) {
+    visitor.visit_pipeline(&node.pipeline);
+    visitor.visit_block(&node.block);
+}
+
+pub fn walk_pipeline<'ast, V: Visit<'ast> + ?Sized>(visitor: &mut V, pipeline: &'ast Pipeline) {
+    for command in &pipeline.commands {
+        visitor.visit_command(command);
+    }
+}
+
+pub fn walk_command<'ast, V: Visit<'ast> + ?Sized>(visitor: &mut V, command: &'ast Command) {
+    visitor.visit_expression(&command.target);
+    for arg in &command.args {
+        visitor.visit_expression(arg);
+    }
+}
+
+pub fn walk_expression<'ast, V: Visit<'ast> + ?Sized>(visitor: &mut V, expr: &'ast Expression) {
+    match expr {
+        Expression::PipelineExpr(pipeline) => visitor.visit_pipeline(pipeline),
+        Expression::Chain { target, .. } => visitor.visit_expression(target),
+        Expression::Identifier(_)
+        | Expression::Field(_)
+        | Expression::Variable(_)
+        | Expression::StringLiteral(_)
+        | Expression::NumberLiteral(_)
+        | Expression::BoolLiteral(_)
+        | Expression::Nil => {}
+    }
+}
+
+/// Visitor over a mutable template tree, e.g. for codemods.
+pub trait VisitMut {
+    fn visit_block_mut(&mut self, block: &mut Block) {
+        walk_block_mut(self, block);
+    }
+
+    fn visit_node_mut(&mut self, node: &mut Node) {
+        walk_node_mut(self, node);
+    }
+
+    fn visit_text_mut(&mut self, _node: &mut TextNode) {}
+
+    fn visit_comment_mut(&mut self, _node: &mut CommentNode) {}
+
+    fn visit_action_mut(&mut self, node: &mut ActionNode) {
+        walk_action_mut(self, node);
+    }
+
+    fn visit_if_mut(&mut self, node: &mut IfNode) {
+        walk_if_mut(self, node);
+    }
+
+    fn visit_else_if_mut(&mut self, branch: &mut ElseIfBranch) {
+        walk_else_if_mut(self, branch);
+    }
+
+    fn visit_range_mut(&mut self, node: &mut RangeNode) {
+        walk_range_mut(self, node);
+    }
+
+    fn visit_with_mut(&mut self, node: &mut WithNode) {
+        walk_with_mut(self, node);
+    }
+
+    fn visit_define_mut(&mut self, node: &mut DefineNode) {
+        walk_define_mut(self, node);
+    }
+
+    fn visit_template_mut(&mut self, node: &mut TemplateNode) {
+        walk_template_mut(self, node);
+    }
+
+    fn visit_block_node_mut(&mut self, node: &mut BlockNode) {
+        walk_block_node_mut(self, node);
+    }
+
+    fn visit_break_mut(&mut self, _node: &mut BreakNode) {}
+
+    fn visit_continue_mut(&mut self, _node: &mut ContinueNode) {}
+
+    fn visit_pipeline_mut(&mut self, pipeline: &mut Pipeline) {
+        walk_pipeline_mut(self, pipeline);
+    }
+
+    fn visit_command_mut(&mut self, command: &mut Command) {
+        walk_command_mut(self, command);
+    }
+
+    fn visit_expression_mut(&mut self, expr: &mut Expression) {
+        walk_expression_mut(self, expr);
+    }
+}
+
+pub fn walk_block_mut<V: VisitMut + ?Sized>(visitor: &mut V, block: &mut Block) {
+    for node in &mut block.nodes {
+        visitor.visit_node_mut(node);
+    }
+}
+
+pub fn walk_node_mut<V: VisitMut + ?Sized>(visitor: &mut V, node: &mut Node) {
+    match node {
+        Node::Text(node) => visitor.visit_text_mut(node),
+        Node::Action(node) => visitor.visit_action_mut(node),
+        Node::Comment(node) => visitor.visit_comment_mut(node),
+        Node::If(node) => visitor.visit_if_mut(node),
+        Node::Range(node) => visitor.visit_range_mut(node),
+        Node::With(node) => visitor.visit_with_mut(node),
+        Node::Define(node) => visitor.visit_define_mut(node),
+        Node::Template(node) => visitor.visit_template_mut(node),
+        Node::Block(node) => visitor.visit_block_node_mut(node),
+        Node::Break(node) => visitor.visit_break_mut(node),
+        Node::Continue(node) => visitor.visit_continue_mut(node),
+    }
+}
+
+pub fn walk_action_mut<V: VisitMut + ?Sized>(visitor: &mut V, node: &mut ActionNode) {
+    visitor.visit_pipeline_mut(&mut node.pipeline);
+}
+
+pub fn walk_if_mut<V: VisitMut + ?Sized>(visitor: &mut V, node: &mut IfNode) {
+    visitor.visit_pipeline_mut(&mut node.pipeline);
+    visitor.visit_block_mut(&mut node.then_block);
+    for branch in &mut node.else_if_branches {
+        visitor.visit_else_if_mut(branch);
+    }
+    if let Some(else_block) = &mut node.else_block {
+        visitor.visit_block_mut(else_block);
+    }
+}
+
+pub fn walk_else_if_mut<V: VisitMut + ?Sized>(visitor: &mut V, branch: &mut ElseIfBranch) {
+    visitor.visit_pipeline_mut(&mut branch.pipeline);
+    visitor.visit_block_mut(&mut branch.block);
+}
+
+pub fn walk_range_mut<V: VisitMut + ?Sized>(visitor: &mut V, node: &mut RangeNode) {
+    visitor.visit_pipeline_mut(&mut node.pipeline);
+    visitor.visit_block_mut(&mut node.then_block);
+    if let Some(else_block) = &mut node.else_block {
+        visitor.visit_block_mut(else_block);
+    }
+}
+
+pub fn walk_with_mut<V: VisitMut + ?Sized>(visitor: &mut V, node: &mut WithNode) {
+    visitor.visit_pipeline_mut(&mut node.pipeline);
+    visitor.visit_block_mut(&mut node.then_block);
+    if let Some(else_block) = &mut node.else_block {
+        visitor.visit_block_mut(else_block);
+    }
+}
+
+pub fn walk_define_mut<V: VisitMut + ?Sized>(visitor: &mut V, node: &mut DefineNode) {
+    visitor.visit_block_mut(&mut node.block);
+}
+
+pub fn walk_template_mut<V: VisitMut + ?Sized>(visitor: &mut V, node: &mut TemplateNode) {
+    if let Some(pipeline) = &mut node.pipeline {
+        visitor.visit_pipeline_mut(pipeline);
+    }
+}
+
+pub fn walk_block_node_mut<V: VisitMut + ?Sized>(visitor: &mut V, node: &mut BlockNode) {
+    visitor.visit_pipeline_mut(&mut node.pipeline);
+    visitor.visit_block_mut(&mut node.block);
+}
+
+pub fn walk_pipeline_mut<V: VisitMut + ?Sized>(visitor: &mut V, pipeline: &mut Pipeline) {
+    for command in &mut pipeline.commands {
+        visitor.visit_command_mut(command);
+    }
+}
+
+pub fn walk_command_mut<V: VisitMut + ?Sized>(visitor: &mut V, command: &mut Command) {
+    visitor.visit_expression_mut(&mut command.target);
+    for arg in &mut command.args {
+        visitor.visit_expression_mut(arg);
+    }
+}
+
+pub fn walk_expression_mut<V: VisitMut + ?Sized>(visitor: &mut V, expr: &mut Expression) {
+    match expr {
+        Expression::PipelineExpr(pipeline) => visitor.visit_pipeline_mut(pipeline),
+        Expression::Chain { target, .. } => visitor.visit_expression_mut(target),
+        Expression::Identifier(_)
+        | Expression::Field(_)
+        | Expression::Variable(_)
+        | Expression::StringLiteral(_)
+        | Expression::NumberLiteral(_)
+        | Expression::BoolLiteral(_)
+        | Expression::Nil => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{parse_template_with_options, ParseOptions};
+
+    fn parse(source: &str) -> crate::ast::Ast {
+        parse_template_with_options("visit", source, &ParseOptions::new()).unwrap()
+    }
+
+    #[derive(Default)]
+    struct Collect {
+        fields: Vec<String>,
+        nodes: usize,
+    }
+
+    impl<'ast> Visit<'ast> for Collect {
+        fn visit_node(&mut self, node: &'ast Node) {
+            self.nodes += 1;
+            walk_node(self, node);
+        }
+
+        fn visit_expression(&mut self, expr: &'ast Expression) {
+            if let Expression::Field(parts) = expr {
+                self.fields.push(parts.join("."));
+            }
+            walk_expression(self, expr);
+        }
+    }
+
+    #[test]
+    fn visits_every_node_and_nested_expression() {
+        let ast = parse(concat!(
+            "{{if .a}}{{.b}}{{else if (f .c).d}}{{range .e}}{{break}}{{else}}{{.f}}{{end}}",
+            "{{else}}{{with .g}}{{/* c */}}{{end}}{{end}}",
+            "{{define \"x\"}}{{.h}}{{end}}{{template \"x\" (.i)}}{{block \"y\" .j}}{{.k}}{{end}}",
+        ));
+        let mut collect = Collect::default();
+        collect.visit_block(&ast.root);
+        assert_eq!(
+            collect.fields,
+            ["a", "b", "c", "e", "f", "g", "h", "i", "j", "k"]
+        );
+        assert_eq!(collect.nodes, 12);
+    }
+
+    struct Rename;
+
+    impl VisitMut for Rename {
+        fn visit_expression_mut(&mut self, expr: &mut Expression) {
+            if let Expression::Field(parts) = expr {
+                if parts.first().is_some_and(|part| part == "old") {
+                    parts[0] = "new".into();
+                }
+            }
+            walk_expression_mut(self, expr);
+        }
+    }
+
+    #[test]
+    fn mutable_visitor_rewrites_nested_expressions() {
+        let mut ast = parse("{{if .old}}{{else if eq (.old).x 1}}{{template \"t\" .old}}{{end}}");
+        Rename.visit_block_mut(&mut ast.root);
+        let mut collect = Collect::default();
+        collect.visit_block(&ast.root);
+        assert_eq!(collect.fields, ["new", "new", "new"]);
+    }
+}
@@ -0,0 +1,71 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+use std::fmt;
+
+/// Go release used to decide which template features a [`Dialect::Go`] accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GoVersion {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl GoVersion {
+    /// Go 1.18 introduced `{{break}}`/`{{continue}}`.
+    pub const GO1_18: GoVersion = GoVersion::new(1, 18);
+    /// Go 1.22 introduced ranging over integers.
+    pub const GO1_22: GoVersion = GoVersion::new(1, 22);
+    /// Newest Go release whose template grammar the engine tracks.
+    pub const LATEST: GoVersion = GoVersion::new(1, 25);
+
+    pub const fn new(major: u32, minor: u32) -> Self {
+        Self { major, minor }
+    }
+}
+
+impl Default for GoVersion {
+    fn default() -> Self {
+        Self::LATEST
+    }
+}
+
+impl fmt::Display for GoVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "go{}.{}", self.major, self.minor)
+    }
+}
+
+/// Syntax profile a template is parsed and executed with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Dialect {
+    /// Go's `text/template` plus Lithos extensions: infix comparison operators
+    /// (`==`, `<`, ...) and bare identifiers that look up data keys.
+    #[default]
+    Lithos,
+    /// Exactly what `text/template` of the given Go release accepts, so templates
+    /// validated here behave the same in Go services.
+    Go { version: GoVersion },
+}
+
+impl Dialect {
+    /// Strict Go dialect for the newest supported release.
+    pub fn go() -> Self {
+        Dialect::Go {
+            version: GoVersion::LATEST,
+        }
+    }
+
+    /// Reports whether Lithos extensions are rejected.
+    pub fn is_go(&self) -> bool {
+        matches!(self, Dialect::Go { .. })
+    }
+
+    /// Reports whether a feature added in Go `since` is available.
+    pub(crate) fn supports(&self, since: GoVersion) -> bool {
+        match self {
+            Dialect::Lithos => true,
+            Dialect::Go { version } => *version >= since,
+        }
+    }
+}
@@ -0,0 +1,56 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::analyze::TemplateAnalysis;
+use crate::ast::Ast;
+
+/// Version of the document schema written by this engine. It changes whenever
+/// the serialized shape of the AST or of analysis results changes, and
+/// documents with a different version are rejected when deserialized.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Serialized form of a [`Template`](crate::Template), produced by
+/// [`Template::to_document`](crate::Template::to_document) and turned back into
+/// a renderable template by [`Template::from_document`](crate::Template::from_document).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateDocument {
+    #[serde(deserialize_with = "schema_version")]
+    pub schema_version: u32,
+    pub name: String,
+    pub source: String,
+    pub ast: Ast,
+    /// Associated templates other than `ast` itself: definitions from the
+    /// source, or the other members of the set the template came from.
+    #[serde(default)]
+    pub templates: Vec<Ast>,
+}
+
+/// Serialized form of a [`TemplateAnalysis`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalysisDocument {
+    #[serde(deserialize_with = "schema_version")]
+    pub schema_version: u32,
+    pub analysis: TemplateAnalysis,
+}
+
+impl AnalysisDocument {
+    /// Wraps `analysis` in a document of the current [`SCHEMA_VERSION`].
+    pub fn new(analysis: TemplateAnalysis) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            analysis,
+        }
+    }
+}
+
+fn schema_version<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    let version = u32::deserialize(deserializer)?;
+    if version == SCHEMA_VERSION {
+        Ok(version)
+    } else {
+        Err(D::Error::custom(format!(
+            "unsupported schema version {version}, expected {SCHEMA_VERSION}"
+        )))
+    }
+}
@@ -1,5 +1,7 @@
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 use crate::ast::Span;
+use crate::options::{CancelReason, Limit};
+use crate::source_map::{LineColumn, SourceMap, SourceRange};
 use thiserror::Error;
 
 /// Unified error type for the template engine.
@@ -23,6 +25,51 @@ pub enum Error {
         #[source]
         source: Option<Box<dyn std::error::Error + Send + Sync>>,
         span: Option<Span>,
+        /// Zero-based index of the helper argument the error blames; the
+        /// evaluator resolves it to that argument's span.
+        argument: Option<usize>,
+        /// Name of the template that was executing; `span` points into its tree.
+        template: Option<String>,
+    },
+    /// A render exceeded one of the limits set with
+    /// [`RenderOptions::with_limit`](crate::RenderOptions::with_limit).
+    #[error("render error: {message}")]
+    LimitExceeded {
+        limit: Limit,
+        max: usize,
+        message: String,
+        span: Option<Span>,
+        template: Option<String>,
+    },
+    /// A render was stopped by its
+    /// [`CancellationToken`](crate::CancellationToken) or deadline; output
+    /// streamed before the stop stays in the writer.
+    #[error("render error: {message}")]
+    Cancelled {
+        reason: CancelReason,
+        message: String,
+        span: Option<Span>,
+        template: Option<String>,
+    },
+    /// [`Template::render_async`](crate::Template::render_async) evaluated the
+    /// template again and reached async helper `helper` with arguments that
+    /// differ from the earlier pass, as when they come from a helper whose
+    /// result changes between calls.
+    #[error("render error: {message}")]
+    AsyncArgumentsChanged {
+        helper: String,
+        message: String,
+        span: Option<Span>,
+        template: Option<String>,
+    },
+    /// The writer a template streams into failed; output written before the
+    /// failure stays in the writer.
+    #[error("write error: {message}")]
+    Write {
+        message: String,
+        #[source]
+        source: Option<Box<dyn std::error::Error + Send + Sync>>,
+        span: Option<Span>,
     },
 }
 
@@ -44,10 +91,333 @@ impl Error {
             message: message.into(),
             source: None,
             span,
+            argument: None,
+            template: None,
         }
     }
 
     pub fn render_with_span(message: impl Into<String>, span: Span) -> Self {
         Self::render(message, Some(span))
     }
+
+    pub fn limit_exceeded(limit: Limit, max: usize, span: Option<Span>) -> Self {
+        let unit = match limit {
+            Limit::OutputBytes | Limit::ValueSize => " bytes",
+            _ => "",
+        };
+        Error::LimitExceeded {
+            limit,
+            max,
+            message: format!("exceeded maximum {limit} ({max}{unit})"),
+            span,
+            template: None,
+        }
+    }
+
+    pub fn cancelled(reason: CancelReason, span: Option<Span>) -> Self {
+        Error::Cancelled {
+            reason,
+            message: reason.to_string(),
+            span,
+            template: None,
+        }
+    }
+
+    pub fn async_arguments_changed(helper: impl Into<String>, span: Option<Span>) -> Self {
+        let helper = helper.into();
+        Error::AsyncArgumentsChanged {
+            message: format!(
+                "async helper {helper:?} was called with different arguments when the template was evaluated again"
+            ),
+            helper,
+            span,
+            template: None,
+        }
+    }
+
+    /// Wraps the error of a writer that rendered output of the node at `span`
+    /// could not be written to.
+    pub fn write(
+        source: impl std::error::Error + Send + Sync + 'static,
+        span: Option<Span>,
+    ) -> Self {
+        Error::Write {
+            message: source.to_string(),
+            source: Some(Box::new(source)),
+            span,
+        }
+    }
+
+    /// Copies the error for replaying it. The source, if any, is kept as its
+    /// message only.
+    pub(crate) fn duplicate(&self) -> Self {
+        let source = |source: &Option<Box<dyn std::error::Error + Send + Sync>>| {
+            source.as_ref().map(|source| {
+                Box::new(CopiedSource(source.to_string()))
+                    as Box<dyn std::error::Error + Send + Sync>
+            })
+        };
+        match self {
+            Error::Parse {
+                message,
+                source: original,
+                span,
+            } => Error::Parse {
+                message: message.clone(),
+                source: source(original),
+                span: *span,
+            },
+            Error::Render {
+                message,
+                source: original,
+                span,
+                argument,
+                template,
+            } => Error::Render {
+                message: message.clone(),
+                source: source(original),
+                span: *span,
+                argument: *argument,
+                template: template.clone(),
+            },
+            Error::LimitExceeded {
+                limit,
+                max,
+                message,
+                span,
+                template,
+            } => Error::LimitExceeded {
+                limit: *limit,
+                max: *max,
+                message: message.clone(),
+                span: *span,
+                template: template.clone(),
+            },
+            Error::Cancelled {
+                reason,
+                message,
+                span,
+                template,
+            } => Error::Cancelled {
+                reason: *reason,
+                message: message.clone(),
+                span: *span,
+                template: template.clone(),
+            },
+            Error::AsyncArgumentsChanged {
+                helper,
+                message,
+                span,
+                template,
+            } => Error::AsyncArgumentsChanged {
+                helper: helper.clone(),
+                message: message.clone(),
+                span: *span,
+                template: template.clone(),
+            },
+            Error::Write {
+                message,
+                source: original,
+                span,
+            } => Error::Write {
+                message: message.clone(),
+                source: source(original),
+                span: *span,
+            },
+        }
+    }
+
+    /// Returns the message without the `parse error:`/`render error:`/`write error:` prefix.
+    pub fn message(&self) -> &str {
+        let (Error::Parse { message, .. }
+        | Error::Render { message, .. }
+        | Error::LimitExceeded { message, .. }
+        | Error::Cancelled { message, .. }
+        | Error::AsyncArgumentsChanged { message, .. }
+        | Error::Write { message, .. }) = self;
+        message
+    }
+
+    /// Returns the byte range of the template the error points at, if known.
+    pub fn span(&self) -> Option<Span> {
+        let (Error::Parse { span, .. }
+        | Error::Render { span, .. }
+        | Error::LimitExceeded { span, .. }
+        | Error::Cancelled { span, .. }
+        | Error::AsyncArgumentsChanged { span, .. }
+        | Error::Write { span, .. }) = self;
+        *span
+    }
+
+    /// Resolves the error's span to line/column positions in `source`.
+    pub fn range(&self, source: &SourceMap) -> Option<SourceRange> {
+        self.span().map(|span| source.range(span))
+    }
+
+    /// Returns the name of the template that was executing when a render
+    /// error was raised; the error's span points into that template's tree.
+    pub fn template(&self) -> Option<&str> {
+        match self {
+            Error::Render { template, .. }
+            | Error::LimitExceeded { template, .. }
+            | Error::Cancelled { template, .. }
+            | Error::AsyncArgumentsChanged { template, .. } => template.as_deref(),
+            Error::Parse { .. } | Error::Write { .. } => None,
+        }
+    }
+
+    /// Records `name` as the executing template unless one is already set, so
+    /// the innermost template an error was raised in wins.
+    pub(crate) fn in_template(mut self, name: &str) -> Self {
+        if let Error::Render { template, .. }
+        | Error::LimitExceeded { template, .. }
+        | Error::Cancelled { template, .. }
+        | Error::AsyncArgumentsChanged { template, .. } = &mut self
+        {
+            template.get_or_insert_with(|| name.to_string());
+        }
+        self
+    }
+
+    /// Formats the error the way Go's `text/template` reports it, resolving the
+    /// byte span against `source`, the text `name` was parsed from:
+    ///
+    /// - parse errors: `template: name:3: message`
+    /// - render errors: `template: name:3:14: executing "name" at <.foo.bar>: message`
+    /// - write errors: the writer's message, which Go returns unwrapped
+    ///
+    /// The `executing` part names [`Error::template`] when recorded, and `name`
+    /// otherwise. As in Go, the column is a zero-based byte offset within the
+    /// line and the quoted node text is cut to 20 characters.
+    pub fn to_go_string(&self, name: &str, source: &SourceMap) -> String {
+        if let Error::Write { message, .. } = self {
+            return message.clone();
+        }
+        let Some(span) = self.span() else {
+            return format!("template: {name}: {}", self.message());
+        };
+        let LineColumn { line, column } = source.line_column(span.start);
+        match self {
+            Error::Render { message, .. }
+            | Error::LimitExceeded { message, .. }
+            | Error::Cancelled { message, .. }
+            | Error::AsyncArgumentsChanged { message, .. } => {
+                let column = column - 1;
+                let executing = self.template().unwrap_or(name);
+                let text = source.snippet(span);
+                let context = match text.char_indices().nth(20) {
+                    Some((cut, _)) => format!("{}...", &text[..cut]),
+                    None => text.to_string(),
+                };
+                format!(
+                    "template: {name}:{line}:{column}: executing {executing:?} at <{context}>: {message}"
+                )
+            }
+            _ => format!("template: {name}:{line}: {}", self.message()),
+        }
+    }
+
+    /// Marks a helper error as caused by the argument at `index` (zero-based,
+    /// a piped-in value being the last argument), so the evaluator reports it
+    /// at that argument's position in the template.
+    pub fn with_argument(mut self, index: usize) -> Self {
+        if let Error::Render { argument, .. } = &mut self {
+            *argument = Some(index);
+        }
+        self
+    }
+
+    /// Returns the limit a [`Error::LimitExceeded`] error reports.
+    pub fn limit(&self) -> Option<Limit> {
+        match self {
+            Error::LimitExceeded { limit, .. } => Some(*limit),
+            _ => None,
+        }
+    }
+
+    /// Returns why a [`Error::Cancelled`] render was stopped.
+    pub fn cancel_reason(&self) -> Option<CancelReason> {
+        match self {
+            Error::Cancelled { reason, .. } => Some(*reason),
+            _ => None,
+        }
+    }
+
+    /// Returns the helper argument index recorded by [`Error::with_argument`].
+    pub fn argument(&self) -> Option<usize> {
+        match self {
+            Error::Render { argument, .. } => *argument,
+            Error::Parse { .. }
+            | Error::LimitExceeded { .. }
+            | Error::Cancelled { .. }
+            | Error::AsyncArgumentsChanged { .. }
+            | Error::Write { .. } => None,
+        }
+    }
+
+    /// Points the error at `span` unless it already carries a location.
+    pub fn with_span(mut self, span: Span) -> Self {
+        let (Error::Parse { span: slot, .. }
+        | Error::Render { span: slot, .. }
+        | Error::LimitExceeded { span: slot, .. }
+        | Error::Cancelled { span: slot, .. }
+        | Error::AsyncArgumentsChanged { span: slot, .. }
+        | Error::Write { span: slot, .. }) = &mut self;
+        slot.get_or_insert(span);
+        self
+    }
+}
+
+/// Stands in for the source of a duplicated error.
+#[derive(Debug, Error)]
+#[error("{0}")]
+struct CopiedSource(String);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_errors_like_go() {
+        let source = SourceMap::new("line one\n{{.foo.bar | printf \"%d\" | upper}}");
+        let source = &source;
+        let render = Error::render_with_span("boom", Span::new(11, 19));
+        assert_eq!(
+            render.to_go_string("page", source),
+            r#"template: page:2:2: executing "page" at <.foo.bar>: boom"#
+        );
+
+        let long = Error::render_with_span("boom", Span::new(9, source.source().len()));
+        assert_eq!(
+            long.to_go_string("page", source),
+            r#"template: page:2:0: executing "page" at <{{.foo.bar | printf ...>: boom"#
+        );
+
+        let parse = Error::parse_with_span(r#"function "x" not defined"#, Span::new(11, 12));
+        assert_eq!(
+            parse.to_go_string("page", source),
+            r#"template: page:2: function "x" not defined"#
+        );
+
+        let nested = Error::render_with_span("boom", Span::new(11, 19)).in_template("row");
+        assert_eq!(
+            nested.in_template("page").to_go_string("lib", source),
+            r#"template: lib:2:2: executing "row" at <.foo.bar>: boom"#
+        );
+
+        let copy = Error::limit_exceeded(Limit::Iterations, 3, Some(Span::new(11, 19)))
+            .in_template("row")
+            .duplicate();
+        assert_eq!(copy.limit(), Some(Limit::Iterations));
+        assert_eq!(
+            copy.to_go_string("lib", source),
+            r#"template: lib:2:2: executing "row" at <.foo.bar>: exceeded maximum loop iterations (3)"#
+        );
+
+        let unlocated = Error::render("no span", None);
+        assert_eq!(
+            unlocated.to_go_string("page", source),
+            "template: page: no span"
+        );
+    }
 }
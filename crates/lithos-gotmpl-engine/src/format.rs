@@ -0,0 +1,321 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+use crate::ast::Delimiters;
+use crate::error::Error;
+use crate::lexer::{self, Keyword, Token, TokenKind};
+use crate::parser::{self, ActionWindow, ParseOptions};
+
+/// Spacing between the delimiters and the body of an action.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ActionSpacing {
+    /// `{{ .name }}`.
+    #[default]
+    Padded,
+    /// `{{.name}}`, the style of Go's own templates.
+    Compact,
+}
+
+/// Settings for [`format_template`] and [`Template::format`](crate::Template::format).
+#[derive(Debug, Clone, Default)]
+pub struct FormatOptions {
+    spacing: ActionSpacing,
+    indent: Option<usize>,
+}
+
+impl FormatOptions {
+    /// Creates options with padded actions and no re-indentation.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Consumes the options and returns a new instance with `spacing`.
+    pub fn with_spacing(mut self, spacing: ActionSpacing) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    /// Returns the spacing applied inside actions.
+    pub fn spacing(&self) -> ActionSpacing {
+        self.spacing
+    }
+
+    /// Indents nested control blocks by `width` spaces per level.
+    ///
+    /// Only whitespace that a trim marker removes anyway is rewritten: the
+    /// line before a `{{- ...}}` action and the line after a `{{... -}}` one.
+    pub fn with_indent(mut self, width: usize) -> Self {
+        self.indent = Some(width);
+        self
+    }
+
+    /// Returns the indentation width, if nested blocks are re-indented.
+    pub fn indent(&self) -> Option<usize> {
+        self.indent
+    }
+}
+
+/// Rewrites `source` in canonical form without changing what it renders.
+///
+/// Text outside actions is kept byte for byte. Inside actions the formatter
+/// normalises the padding next to the delimiters, puts single spaces around
+/// pipes, operators and declarations, drops spaces inside parentheses, writes
+/// comments as `{{/* text */}}`, and keeps trim markers. Token spelling such
+/// as raw strings or hex numbers is preserved. The source must parse with
+/// `parse_options`.
+pub fn format_template(
+    source: &str,
+    parse_options: &ParseOptions,
+    options: &FormatOptions,
+) -> Result<String, Error> {
+    parser::parse_template_with_options("format", source, parse_options)?;
+    format_source(source, parse_options.delimiters(), options)
+}
+
+/// Check mode of [`format_template`]: reports whether `source` is already in
+/// canonical form.
+pub fn is_formatted(
+    source: &str,
+    parse_options: &ParseOptions,
+    options: &FormatOptions,
+) -> Result<bool, Error> {
+    Ok(format_template(source, parse_options, options)? == source)
+}
+
+/// Formats source that is known to parse with `delimiters`. Actions that do
+/// not lex are reported rather than dropped, which would change the output.
+pub(crate) fn format_source(
+    source: &str,
+    delimiters: &Delimiters,
+    options: &FormatOptions,
+) -> Result<String, Error> {
+    let left = delimiters.left.as_bytes();
+    let right = delimiters.right.as_bytes();
+    let bytes = source.as_bytes();
+    let mut out = String::with_capacity(source.len());
+    let mut cursor = 0;
+    let mut depth = 0usize;
+    let mut trimmed_before = false;
+
+    while let Some(open) = parser::find_action_start(bytes, cursor, left) {
+        let Some(close) = parser::find_action_end(bytes, open + left.len(), right) else {
+            break;
+        };
+        let window =
+            parser::trim_action_delimiters(source, bytes, (open, left.len()), (close, right.len()));
+        let comment = parser::is_comment(window.body);
+        let tokens = if comment {
+            Vec::new()
+        } else {
+            lexer::lex_action(window.body, window.body_start)?
+        };
+        let keyword = match tokens.first().map(|token| &token.kind) {
+            Some(TokenKind::Keyword(keyword)) => Some(keyword.clone()),
+            _ => None,
+        };
+        let action_depth = match keyword {
+            Some(Keyword::Else | Keyword::End) => depth.saturating_sub(1),
+            _ => depth,
+        };
+
+        let text = &source[cursor..open];
+        let lead = trimmed_before.then_some(depth);
+        let trail = window.trim_left.then_some(action_depth);
+        push_text(&mut out, text, lead, trail, options.indent);
+        push_action(&mut out, &window, comment, &tokens, delimiters, options);
+
+        depth = match keyword {
+            Some(
+                Keyword::If | Keyword::Range | Keyword::With | Keyword::Define | Keyword::Block,
+            ) => depth + 1,
+            Some(Keyword::End) => depth.saturating_sub(1),
+            _ => depth,
+        };
+        trimmed_before = window.trim_right;
+        cursor = close + right.len();
+    }
+
+    let lead = trimmed_before.then_some(depth);
+    push_text(&mut out, &source[cursor..], lead, None, options.indent);
+    Ok(out)
+}
+
+fn push_action(
+    out: &mut String,
+    window: &ActionWindow<'_>,
+    comment: bool,
+    tokens: &[Token],
+    delimiters: &Delimiters,
+    options: &FormatOptions,
+) {
+    // Go only accepts a comment directly after the delimiter or a trim marker.
+    let pad = !comment && options.spacing == ActionSpacing::Padded;
+    out.push_str(&delimiters.left);
+    if window.trim_left {
+        out.push_str("- ");
+    } else if pad {
+        out.push(' ');
+    }
+    if comment {
+        let inner = window.body[2..window.body.len() - 2].trim();
+        if inner.is_empty() {
+            out.push_str("/* */");
+        } else {
+            out.push_str("/* ");
+            out.push_str(inner);
+            out.push_str(" */");
+        }
+    } else {
+        push_tokens(out, window, tokens);
+    }
+    if window.trim_right {
+        out.push_str(" -");
+    } else if pad {
+        out.push(' ');
+    }
+    out.push_str(&delimiters.right);
+}
+
+fn push_tokens(out: &mut String, window: &ActionWindow<'_>, tokens: &[Token]) {
+    let mut previous: Option<&Token> = None;
+    for token in tokens {
+        if previous.is_some_and(|previous| space_between(previous, token)) {
+            out.push(' ');
+        }
+        let start = token.span.start - window.body_start;
+        let end = token.span.end - window.body_start;
+        out.push_str(&window.body[start..end]);
+        previous = Some(token);
+    }
+}
+
+/// Decides the spacing between two tokens. Adjacent operands stay adjacent
+/// because `(x).y` and `(x) .y` mean different things.
+fn space_between(previous: &Token, next: &Token) -> bool {
+    use TokenKind::{Assign, Comma, Declare, LeftParen, Operator, Pipe, RightParen};
+    match (&previous.kind, &next.kind) {
+        (_, Comma) => false,
+        (Comma | Pipe | Declare | Assign | Operator(_), _)
+        | (_, Pipe | Declare | Assign | Operator(_)) => true,
+        (LeftParen, _) | (_, RightParen) => false,
+        _ => previous.span.end < next.span.start,
+    }
+}
+
+/// Appends text between actions, re-indenting the whitespace that trim
+/// markers remove: `lead` is the depth after a `-}}`, `trail` the depth of a
+/// following `{{-`.
+fn push_text(
+    out: &mut String,
+    text: &str,
+    lead: Option<usize>,
+    trail: Option<usize>,
+    indent: Option<usize>,
+) {
+    let Some(width) = indent else {
+        out.push_str(text);
+        return;
+    };
+    let is_space = |ch: char| matches!(ch, ' ' | '\t' | '\n' | '\r');
+    let content = text.trim_matches(is_space);
+    if content.is_empty() {
+        // Whitespace-only text disappears entirely once either side trims.
+        match trail.or(lead) {
+            Some(depth) => push_reindented(out, text, width * depth),
+            None => out.push_str(text),
+        }
+        return;
+    }
+    let start = text.len() - text.trim_start_matches(is_space).len();
+    let end = start + content.len();
+    match lead {
+        Some(depth) => push_reindented(out, &text[..start], width * depth),
+        None => out.push_str(&text[..start]),
+    }
+    out.push_str(content);
+    match trail {
+        Some(depth) => push_reindented(out, &text[end..], width * depth),
+        None => out.push_str(&text[end..]),
+    }
+}
+
+/// Replaces the indentation after the last line break of `space`.
+fn push_reindented(out: &mut String, space: &str, columns: usize) {
+    match space.rfind('\n') {
+        Some(newline) => {
+            out.push_str(&space[..=newline]);
+            out.extend(std::iter::repeat(' ').take(columns));
+        }
+        None => out.push_str(space),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(source: &str, options: &FormatOptions) -> String {
+        format_template(source, &ParseOptions::new(), options).unwrap()
+    }
+
+    #[test]
+    fn normalises_spacing_inside_actions() {
+        let padded = FormatOptions::new();
+        assert_eq!(
+            format("a{{.x|upper}}b{{-\t$v:=index .m  \"k\"\n-}}", &padded),
+            r#"a{{ .x | upper }}b{{- $v := index .m "k" -}}"#
+        );
+        assert_eq!(
+            format("{{range $i,$v := ( .items )}}{{(.a).b}}{{end}}", &padded),
+            "{{ range $i, $v := (.items) }}{{ (.a).b }}{{ end }}"
+        );
+        assert_eq!(
+            format("{{if .a==.b}}{{`raw`}} {{0x1F}}{{end}}", &padded),
+            "{{ if .a == .b }}{{ `raw` }} {{ 0x1F }}{{ end }}"
+        );
+
+        let compact = FormatOptions::new().with_spacing(ActionSpacing::Compact);
+        assert_eq!(
+            format("{{ .x | upper }}{{- .y }}{{ -5 }}", &compact),
+            "{{.x | upper}}{{- .y}}{{-5}}"
+        );
+    }
+
+    #[test]
+    fn normalises_comments() {
+        let options = FormatOptions::new();
+        assert_eq!(format("{{/*hi*/}}", &options), "{{/* hi */}}");
+        assert_eq!(format("{{-   /*  hi */  -}}", &options), "{{- /* hi */ -}}");
+        assert_eq!(format("{{/**/}}", &options), "{{/* */}}");
+    }
+
+    #[test]
+    fn indents_only_trimmed_whitespace() {
+        let options = FormatOptions::new().with_indent(2);
+        let source = "{{- range .items -}}\n{{- if . -}}\n      {{ . }}\n{{- else -}}\nnone\n   {{- end -}}\n{{- end }}\nkept  \n";
+        assert_eq!(
+            format(source, &options),
+            "{{- range .items -}}\n  {{- if . -}}\n    {{ . }}\n  {{- else -}}\n    none\n  {{- end -}}\n{{- end }}\nkept  \n"
+        );
+        // Without trim markers the whitespace renders, so it is left alone.
+        let untrimmed = "{{ if .a }}\n{{ .b }}\n{{ end }}";
+        assert_eq!(format(untrimmed, &options), untrimmed);
+    }
+
+    #[test]
+    fn check_mode_reports_formatted_sources() {
+        let options = FormatOptions::new();
+        let parse = ParseOptions::new();
+        assert!(is_formatted("{{ .x | upper }}", &parse, &options).unwrap());
+        assert!(!is_formatted("{{.x|upper}}", &parse, &options).unwrap());
+        assert!(is_formatted("{{if}}", &parse, &options).is_err());
+
+        // Unlexable actions are errors, not silently emptied.
+        let unlexed = format_source("a{{ . @ }}b", &Delimiters::default(), &options);
+        assert!(unlexed.is_err());
+
+        let delims = ParseOptions::new().with_delimiters("[[", "]]");
+        assert_eq!(
+            format_template("[[.x]] {{.y}}", &delims, &options).unwrap(),
+            "[[ .x ]] {{.y}}"
+        );
+    }
+}
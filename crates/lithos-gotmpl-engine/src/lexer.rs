@@ -3,14 +3,18 @@ use std::str::Chars;
 
 use crate::ast::Span;
 use crate::error::Error;
+use crate::runtime;
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Token {
     pub kind: TokenKind,
     pub span: Span,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum TokenKind {
     Identifier(String),
     StringLiteral(String),
@@ -30,6 +34,8 @@ pub enum TokenKind {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum Operator {
     Equal,
     NotEqual,
@@ -39,13 +45,33 @@ pub enum Operator {
     GreaterOrEqual,
 }
 
+impl Operator {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Operator::Equal => "==",
+            Operator::NotEqual => "!=",
+            Operator::Less => "<",
+            Operator::LessOrEqual => "<=",
+            Operator::Greater => ">",
+            Operator::GreaterOrEqual => ">=",
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum Keyword {
     If,
     Else,
     End,
     Range,
     With,
+    Define,
+    Template,
+    Block,
+    Break,
+    Continue,
     Nil,
     True,
     False,
@@ -59,6 +85,11 @@ impl Keyword {
             Keyword::End => "end",
             Keyword::Range => "range",
             Keyword::With => "with",
+            Keyword::Define => "define",
+            Keyword::Template => "template",
+            Keyword::Block => "block",
+            Keyword::Break => "break",
+            Keyword::Continue => "continue",
             Keyword::Nil => "nil",
             Keyword::True => "true",
             Keyword::False => "false",
@@ -80,6 +111,9 @@ struct Lexer<'a> {
     pos: usize,
     offset: usize,
     peeked: Option<char>,
+    /// Set after a `.` directly followed by a digit, so `.items.0` lexes the
+    /// digits as a field index rather than as a float.
+    field_index: bool,
 }
 
 impl<'a> Lexer<'a> {
@@ -89,10 +123,12 @@ impl<'a> Lexer<'a> {
             pos: 0,
             offset,
             peeked: None,
+            field_index: false,
         }
     }
 
     fn next_token(&mut self) -> Result<Option<Token>, Error> {
+        let field_index = std::mem::take(&mut self.field_index);
         self.skip_whitespace();
 
         let start = self.pos;
@@ -102,10 +138,13 @@ impl<'a> Lexer<'a> {
         };
 
         let token = match chr {
-            '.' => Token {
-                kind: TokenKind::Dot,
-                span: self.span_from(start),
-            },
+            '.' => {
+                self.field_index = self.peek_char().is_some_and(|c| c.is_ascii_digit());
+                Token {
+                    kind: TokenKind::Dot,
+                    span: self.span_from(start),
+                }
+            }
             '|' => Token {
                 kind: TokenKind::Pipe,
                 span: self.span_from(start),
@@ -238,6 +277,26 @@ impl<'a> Lexer<'a> {
                         kind: TokenKind::Keyword(Keyword::With),
                         span,
                     },
+                    "define" => Token {
+                        kind: TokenKind::Keyword(Keyword::Define),
+                        span,
+                    },
+                    "template" => Token {
+                        kind: TokenKind::Keyword(Keyword::Template),
+                        span,
+                    },
+                    "block" => Token {
+                        kind: TokenKind::Keyword(Keyword::Block),
+                        span,
+                    },
+                    "break" => Token {
+                        kind: TokenKind::Keyword(Keyword::Break),
+                        span,
+                    },
+                    "continue" => Token {
+                        kind: TokenKind::Keyword(Keyword::Continue),
+                        span,
+                    },
                     "nil" => Token {
                         kind: TokenKind::Keyword(Keyword::Nil),
                         span,
@@ -256,13 +315,31 @@ impl<'a> Lexer<'a> {
                     },
                 }
             }
-            c if c.is_ascii_digit() => {
-                let literal = self.read_number(c);
+            c if c.is_ascii_digit() && field_index => {
+                let literal = self.read_field_index(c);
                 Token {
                     kind: TokenKind::NumberLiteral(literal),
                     span: self.span_from(start),
                 }
             }
+            '+' | '-' | '0'..='9' if chr.is_ascii_digit() || self.starts_number_after_sign() => {
+                let literal = self.read_number(chr, start)?;
+                let span = self.span_from(start);
+                runtime::parse_number(&literal).map_err(|err| err.with_span(span))?;
+                Token {
+                    kind: TokenKind::NumberLiteral(literal),
+                    span,
+                }
+            }
+            '\'' => {
+                let literal = self.read_char_constant(start)?;
+                let span = self.span_from(start);
+                runtime::parse_number(&literal).map_err(|err| err.with_span(span))?;
+                Token {
+                    kind: TokenKind::NumberLiteral(literal),
+                    span,
+                }
+            }
             _ => {
                 return Err(Error::parse(
                     format!("unexpected character '{}'", chr),
@@ -284,6 +361,13 @@ impl<'a> Lexer<'a> {
         }
     }
 
+    /// Reports whether a sign just read begins a number: a digit or a `.`
+    /// follows, as in `-5` and `-.5`.
+    fn starts_number_after_sign(&mut self) -> bool {
+        self.peek_char()
+            .is_some_and(|c| c == '.' || c.is_ascii_digit())
+    }
+
     fn read_identifier(&mut self, first: char) -> String {
         let mut ident = String::new();
         ident.push(first);
@@ -297,30 +381,43 @@ impl<'a> Lexer<'a> {
         ident
     }
 
+    /// Reads an interpreted string literal, decoding escapes exactly like Go's
+    /// `strconv.Unquote`; `\x` and octal escapes produce raw bytes.
     fn read_string(&mut self, start: usize) -> Result<String, Error> {
-        let mut literal = String::new();
-        while let Some(ch) = self.bump_char() {
-            match ch {
-                '"' => return Ok(literal),
-                '\\' => {
-                    if let Some(next) = self.bump_char() {
-                        let escaped = match next {
-                            'n' => '\n',
-                            'r' => '\r',
-                            't' => '\t',
-                            '\\' => '\\',
-                            '"' => '"',
-                            other => other,
-                        };
-                        literal.push(escaped);
-                    } else {
-                        return Err(Error::parse_with_span(
-                            "unterminated escape sequence",
+        let mut bytes = Vec::new();
+        loop {
+            let escape_start = self.pos;
+            match self.bump_char() {
+                Some('"') => {
+                    return String::from_utf8(bytes).map_err(|_| {
+                        Error::parse_with_span(
+                            "string literal is not valid UTF-8",
                             self.span_from(start),
-                        ));
+                        )
+                    });
+                }
+                Some('\\') => {
+                    if matches!(self.chars.as_str().chars().next(), None | Some('\n')) {
+                        break;
+                    }
+                    let before = self.chars.as_str().len();
+                    let escaped = decode_escape(&mut self.chars, '"');
+                    self.pos += before - self.chars.as_str().len();
+                    match escaped {
+                        Some(Escaped::Char(ch)) => {
+                            bytes.extend_from_slice(ch.encode_utf8(&mut [0; 4]).as_bytes());
+                        }
+                        Some(Escaped::Byte(byte)) => bytes.push(byte),
+                        None => {
+                            return Err(Error::parse_with_span(
+                                "invalid escape sequence in string literal",
+                                self.span_from(escape_start),
+                            ));
+                        }
                     }
                 }
-                other => literal.push(other),
+                Some('\n') | None => break,
+                Some(ch) => bytes.extend_from_slice(ch.encode_utf8(&mut [0; 4]).as_bytes()),
             }
         }
         Err(Error::parse_with_span(
@@ -329,12 +426,11 @@ impl<'a> Lexer<'a> {
         ))
     }
 
-    fn read_number(&mut self, first: char) -> String {
+    fn read_field_index(&mut self, first: char) -> String {
         let mut literal = String::new();
         literal.push(first);
-
         while let Some(ch) = self.peek_char() {
-            if ch.is_ascii_digit() || ch == '.' {
+            if ch.is_ascii_digit() {
                 literal.push(self.bump_char().unwrap());
             } else {
                 break;
@@ -343,6 +439,89 @@ impl<'a> Lexer<'a> {
         literal
     }
 
+    /// Scans a number the way Go's `lexer.scanNumber` does; the value itself is
+    /// checked by [`runtime::parse_number`].
+    fn read_number(&mut self, first: char, start: usize) -> Result<String, Error> {
+        const DECIMAL: &str = "0123456789_";
+        const HEX: &str = "0123456789abcdefABCDEF_";
+        let mut literal = String::new();
+        literal.push(first);
+
+        let zero = first == '0' || (!first.is_ascii_digit() && self.accept(&mut literal, "0"));
+        let mut digits = DECIMAL;
+        if zero {
+            if self.accept(&mut literal, "xX") {
+                digits = HEX;
+            } else if self.accept(&mut literal, "oO") {
+                digits = "01234567_";
+            } else if self.accept(&mut literal, "bB") {
+                digits = "01_";
+            }
+        }
+        self.accept_run(&mut literal, digits);
+        if self.accept(&mut literal, ".") {
+            self.accept_run(&mut literal, digits);
+        }
+        if digits == DECIMAL && self.accept(&mut literal, "eE") {
+            self.accept(&mut literal, "+-");
+            self.accept_run(&mut literal, DECIMAL);
+        }
+        if digits == HEX && self.accept(&mut literal, "pP") {
+            self.accept(&mut literal, "+-");
+            self.accept_run(&mut literal, DECIMAL);
+        }
+        self.accept(&mut literal, "i");
+        if let Some(next) = self.peek_char().filter(|c| is_identifier_part(*c)) {
+            literal.push(next);
+            self.bump_char();
+            return Err(Error::parse_with_span(
+                format!("bad number syntax: {literal:?}"),
+                self.span_from(start),
+            ));
+        }
+        Ok(literal)
+    }
+
+    fn read_char_constant(&mut self, start: usize) -> Result<String, Error> {
+        let mut literal = String::from('\'');
+        loop {
+            match self.bump_char() {
+                Some('\'') => {
+                    literal.push('\'');
+                    return Ok(literal);
+                }
+                Some('\\') => {
+                    literal.push('\\');
+                    match self.bump_char() {
+                        Some(next) if next != '\n' => literal.push(next),
+                        _ => break,
+                    }
+                }
+                Some(ch) if ch != '\n' => literal.push(ch),
+                _ => break,
+            }
+        }
+        Err(Error::parse_with_span(
+            "unterminated character constant",
+            self.span_from(start),
+        ))
+    }
+
+    fn accept(&mut self, literal: &mut String, valid: &str) -> bool {
+        match self.peek_char() {
+            Some(ch) if valid.contains(ch) => {
+                literal.push(ch);
+                self.bump_char();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn accept_run(&mut self, literal: &mut String, valid: &str) {
+        while self.accept(literal, valid) {}
+    }
+
     fn read_raw_string(&mut self, start: usize) -> Result<String, Error> {
         let mut literal = String::new();
         while let Some(ch) = self.bump_char() {
@@ -380,6 +559,111 @@ impl<'a> Lexer<'a> {
     }
 }
 
+/// Decodes a character constant such as `'a'` or `'\n'` with Go's
+/// `strconv.UnquoteChar` rules.
+pub(crate) fn unquote_char(literal: &str) -> Result<char, Error> {
+    let malformed = || Error::parse(format!("malformed character constant: {literal}"), None);
+    let body = literal
+        .strip_prefix('\'')
+        .and_then(|rest| rest.strip_suffix('\''))
+        .ok_or_else(malformed)?;
+    let mut chars = body.chars();
+    let value = match chars.next() {
+        Some('\\') => match decode_escape(&mut chars, '\'').ok_or_else(malformed)? {
+            Escaped::Char(ch) => ch,
+            Escaped::Byte(byte) => char::from(byte),
+        },
+        Some('\'') | None => return Err(malformed()),
+        Some(ch) => ch,
+    };
+    if chars.next().is_some() {
+        return Err(malformed());
+    }
+    Ok(value)
+}
+
+/// Result of decoding one escape sequence: `\x` and octal escapes denote a
+/// single byte, everything else a Unicode character.
+enum Escaped {
+    Char(char),
+    Byte(u8),
+}
+
+/// Decodes the escape sequence following a backslash, as Go's
+/// `strconv.UnquoteChar` does. Only the enclosing `quote` character may be
+/// escaped, and `\u`/`\U` must name a valid code point.
+fn decode_escape(chars: &mut Chars<'_>, quote: char) -> Option<Escaped> {
+    let escaped = match chars.clone().next()? {
+        'x' => {
+            chars.next();
+            let value = read_digits(chars, 2, 16)?;
+            return u8::try_from(value).ok().map(Escaped::Byte);
+        }
+        'u' | 'U' => {
+            let len = if chars.next()? == 'u' { 4 } else { 8 };
+            return read_digits(chars, len, 16)
+                .and_then(char::from_u32)
+                .map(Escaped::Char);
+        }
+        '0'..='7' => {
+            let value = read_digits(chars, 3, 8)?;
+            return u8::try_from(value).ok().map(Escaped::Byte);
+        }
+        _ => match chars.next()? {
+            'a' => '\u{07}',
+            'b' => '\u{08}',
+            'f' => '\u{0C}',
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            'v' => '\u{0B}',
+            '\\' => '\\',
+            ch @ ('\'' | '"') if ch == quote => ch,
+            _ => return None,
+        },
+    };
+    Some(Escaped::Char(escaped))
+}
+
+/// Quotes `value` as a Go interpreted string literal, escaping it the way
+/// `strconv.Quote` does so that it lexes back to the same text.
+pub(crate) fn quote_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for ch in value.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\u{07}' => out.push_str("\\a"),
+            '\u{08}' => out.push_str("\\b"),
+            '\u{0C}' => out.push_str("\\f"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{0B}' => out.push_str("\\v"),
+            ch if ch.is_control() && u32::from(ch) < 0x80 => {
+                out.push_str(&format!("\\x{:02x}", u32::from(ch)));
+            }
+            ch if ch.is_control() && u32::from(ch) <= 0xFFFF => {
+                out.push_str(&format!("\\u{:04x}", u32::from(ch)));
+            }
+            ch if ch.is_control() => out.push_str(&format!("\\U{:08x}", u32::from(ch))),
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Consumes exactly `len` digits of the given radix.
+fn read_digits(chars: &mut Chars<'_>, len: usize, radix: u32) -> Option<u32> {
+    let mut value = 0;
+    for _ in 0..len {
+        value = value * radix + chars.next()?.to_digit(radix)?;
+    }
+    Some(value)
+}
+
 fn is_identifier_start(ch: char) -> bool {
     ch.is_ascii_alphabetic() || ch == '_' || ch == '$'
 }
@@ -433,4 +717,128 @@ mod tests {
             other => panic!("unexpected error: {other:?}"),
         }
     }
+
+    #[test]
+    fn lexes_go_number_literals() {
+        let tokens = lex_action("0x1F -1.5e-3 +7 'x' '\\'' 1_000 .items.0", 0).unwrap();
+        assert_eq!(
+            kinds(&tokens),
+            vec![
+                TokenKind::NumberLiteral("0x1F".into()),
+                TokenKind::NumberLiteral("-1.5e-3".into()),
+                TokenKind::NumberLiteral("+7".into()),
+                TokenKind::NumberLiteral("'x'".into()),
+                TokenKind::NumberLiteral("'\\''".into()),
+                TokenKind::NumberLiteral("1_000".into()),
+                TokenKind::Dot,
+                TokenKind::Identifier("items".into()),
+                TokenKind::Dot,
+                TokenKind::NumberLiteral("0".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_number_literals_with_span() {
+        for (source, message, span) in [
+            (
+                "print 12ab",
+                "bad number syntax: \"12a\"",
+                Span::new(16, 19),
+            ),
+            ("print 0x_", "illegal number syntax", Span::new(16, 19)),
+            (
+                "print 99999999999999999999",
+                "integer overflow",
+                Span::new(16, 36),
+            ),
+            (
+                "print 'ab",
+                "unterminated character constant",
+                Span::new(16, 19),
+            ),
+            (
+                "print ''",
+                "malformed character constant",
+                Span::new(16, 18),
+            ),
+            (
+                "print 2i",
+                "complex number literals are not supported",
+                Span::new(16, 18),
+            ),
+        ] {
+            let err = lex_action(source, 10).expect_err(source);
+            assert!(err.to_string().contains(message), "{source}: {err}");
+            let Error::Parse { span: found, .. } = err else {
+                panic!("expected parse error");
+            };
+            assert_eq!(found, Some(span), "{source}");
+        }
+    }
+
+    #[test]
+    fn decodes_go_string_escapes() {
+        let tokens = lex_action(
+            r#""\a\b\f\n\r\t\v\\\"" "\x41\101\u00e9\U0001F600" "\xc3\xa9" `\n`"#,
+            0,
+        )
+        .unwrap();
+        assert_eq!(
+            kinds(&tokens),
+            vec![
+                TokenKind::StringLiteral("\u{07}\u{08}\u{0C}\n\r\t\u{0B}\\\"".into()),
+                TokenKind::StringLiteral("AAé😀".into()),
+                TokenKind::StringLiteral("é".into()),
+                TokenKind::StringLiteral("\\n".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_string_escapes_with_span() {
+        for (source, message, span) in [
+            (
+                r#"print "a\qb""#,
+                "invalid escape sequence",
+                Span::new(8, 10),
+            ),
+            (r#"print "\'""#, "invalid escape sequence", Span::new(7, 9)),
+            (
+                r#"print "\x4""#,
+                "invalid escape sequence",
+                Span::new(7, 11),
+            ),
+            (
+                r#"print "\400""#,
+                "invalid escape sequence",
+                Span::new(7, 11),
+            ),
+            (
+                r#"print "\uD800""#,
+                "invalid escape sequence",
+                Span::new(7, 13),
+            ),
+            (r#"print "\xff""#, "not valid UTF-8", Span::new(6, 12)),
+            (
+                "print \"a\nb\"",
+                "unterminated string literal",
+                Span::new(6, 9),
+            ),
+        ] {
+            let err = lex_action(source, 0).expect_err(source);
+            assert!(err.to_string().contains(message), "{source}: {err}");
+            let Error::Parse { span: found, .. } = err else {
+                panic!("expected parse error");
+            };
+            assert_eq!(found, Some(span), "{source}");
+        }
+    }
+
+    #[test]
+    fn quoted_strings_lex_back_to_the_same_text() {
+        let value = "tab\tquote\"slash\\bell\u{07}nul\u{0}é";
+        let tokens = lex_action(&quote_string(value), 0).unwrap();
+        assert_eq!(kinds(&tokens), vec![TokenKind::StringLiteral(value.into())]);
+    }
 }
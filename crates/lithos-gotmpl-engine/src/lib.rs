@@ -5,44 +5,76 @@
 //! placeholder renderer while the full evaluator is implemented.
 
 pub mod analyze;
+#[cfg(feature = "artifact")]
+mod artifact;
 pub mod ast;
+mod dialect;
+#[cfg(feature = "serde")]
+mod document;
 mod error;
+mod format;
 pub mod lexer;
+mod options;
+mod output;
 mod parser;
 mod runtime;
+mod set;
+mod source_map;
 
 pub use analyze::{
-    analyze_template, AnalysisIssue, Certainty, ControlKind, ControlUsage, FunctionCall,
-    FunctionSource, Precision, TemplateAnalysis, TemplateCall, VariableAccess, VariableKind,
+    analyze_template, analyze_with_templates, AnalysisIssue, Certainty, ControlKind, ControlUsage,
+    FunctionCall, FunctionSource, Precision, TemplateAnalysis, TemplateCall, VariableAccess,
+    VariableKind,
 };
 pub use ast::{
-    ActionNode, Ast, BindingKind, Block, Command, CommentNode, ElseIfBranch, Expression, IfNode,
-    Node, Pipeline, PipelineDeclarations, RangeNode, Span, TextNode, WithNode,
+    ActionNode, ActionSyntax, Ast, BindingKind, Block, BlockNode, BreakNode, Command, CommentNode,
+    ContinueNode, ControlSyntax, DefineNode, Delimiters, ElseIfBranch, Expression, IfNode, Node,
+    Pipeline, PipelineDeclarations, RangeNode, Span, TemplateNode, TextNode, WithNode,
 };
+pub use dialect::{Dialect, GoVersion};
+#[cfg(feature = "serde")]
+pub use document::{AnalysisDocument, TemplateDocument, SCHEMA_VERSION};
 pub use error::Error;
+pub use format::{format_template, is_formatted, ActionSpacing, FormatOptions};
 pub use lexer::{Keyword, Operator, Token, TokenKind};
+pub use options::{CancelReason, CancellationToken, Limit, MissingKey, RenderOptions};
+pub use parser::{parse_template_recovering, ParseOptions, RecoveredParse};
 pub use runtime::{
-    coerce_number, is_empty, is_truthy, value_to_string, EvalContext, Function, FunctionRegistry,
-    FunctionRegistryBuilder,
+    coerce_number, is_empty, is_truthy, value_to_string, AsyncFunction, EvalContext, Function,
+    FunctionRegistry, FunctionRegistryBuilder, HelperFuture, LazyArgs, LazyFunction, Method,
 };
+pub use set::TemplateSet;
+pub use source_map::{LineColumn, SourceMap, SourceRange};
 
+use output::{FmtOutput, IoOutput, Output};
+use runtime::{AsyncCalls, TemplateMap};
 use serde_json::{Number, Value};
-use std::fmt;
+use set::SourceMaps;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::{fmt, io};
 
 /// Parsed template with associated AST and original source.
+///
+/// A template also carries the named templates it is associated with: the
+/// `{{define}}`/`{{block}}` definitions from its own source, or every template
+/// of the [`TemplateSet`] it was looked up from.
 #[derive(Clone)]
 pub struct Template {
     name: String,
-    source: String,
-    ast: Ast,
+    source: Arc<SourceMap>,
+    ast: Arc<Ast>,
     functions: FunctionRegistry,
+    templates: TemplateMap,
+    sources: SourceMaps,
 }
 
 impl fmt::Debug for Template {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Template")
             .field("name", &self.name)
-            .field("source", &self.source)
+            .field("source", &self.source())
             .finish()
     }
 }
@@ -59,13 +91,50 @@ impl Template {
         source: &str,
         functions: FunctionRegistry,
     ) -> Result<Self, Error> {
-        let ast = parser::parse_template(name, source)?;
-        Ok(Self {
+        Self::parse_with_options(name, source, functions, &ParseOptions::default())
+    }
+
+    /// Parses template source with custom [`ParseOptions`], such as action
+    /// delimiters, and associates it with a registry of functions.
+    pub fn parse_with_options(
+        name: &str,
+        source: &str,
+        functions: FunctionRegistry,
+        options: &ParseOptions,
+    ) -> Result<Self, Error> {
+        let ast = Arc::new(parse_ast(name, source, &functions, options)?);
+        let source = Arc::new(SourceMap::new(source));
+        let mut templates = HashMap::new();
+        let sources = set::associate_all(&mut templates, ast.clone())?
+            .into_iter()
+            .map(|installed| (installed, source.clone()))
+            .collect();
+        Ok(Self::from_parts(
+            name,
+            source,
+            ast,
+            functions,
+            Arc::new(templates),
+            Arc::new(sources),
+        ))
+    }
+
+    pub(crate) fn from_parts(
+        name: &str,
+        source: Arc<SourceMap>,
+        ast: Arc<Ast>,
+        functions: FunctionRegistry,
+        templates: TemplateMap,
+        sources: SourceMaps,
+    ) -> Self {
+        Self {
             name: name.to_string(),
-            source: source.to_string(),
+            source,
             ast,
             functions,
-        })
+            templates,
+            sources,
+        }
     }
 
     /// Returns a clone of the function registry in use.
@@ -84,6 +153,20 @@ impl Template {
         self
     }
 
+    /// Formats an error raised by this template in Go's
+    /// `template: name:line:col: ...` style; see [`Error::to_go_string`].
+    /// Render errors raised inside another associated template are resolved
+    /// against the source that template was parsed from.
+    pub fn format_error(&self, err: &Error) -> String {
+        let executing = err
+            .template()
+            .and_then(|name| Some((self.templates.get(name)?, self.sources.get(name)?)));
+        match executing {
+            Some((tree, source)) => err.to_go_string(&tree.parse_name, source),
+            None => err.to_go_string(&self.name, &self.source),
+        }
+    }
+
     /// Returns the original template name.
     pub fn name(&self) -> &str {
         &self.name
@@ -91,6 +174,12 @@ impl Template {
 
     /// Returns the original template source.
     pub fn source(&self) -> &str {
+        self.source.source()
+    }
+
+    /// Returns the line index of the template source, for turning the spans
+    /// of errors and analysis results into line/column positions.
+    pub fn source_map(&self) -> &SourceMap {
         &self.source
     }
 
@@ -99,196 +188,674 @@ impl Template {
         &self.ast
     }
 
+    /// Returns the sorted names of the templates this template can invoke.
+    pub fn template_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.templates.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
     /// Runs structural analysis over the template and returns helper usage metadata.
+    ///
+    /// Template invocations are resolved against the associated templates.
     pub fn analyze(&self) -> TemplateAnalysis {
-        analyze::analyze_template(&self.ast, Some(&self.functions))
+        analyze::analyze_with_templates(
+            &self.ast,
+            Some(&self.functions),
+            self.templates.keys().map(String::as_str),
+        )
     }
 
     /// Returns a canonical string representation of the parsed template, similar to Go's
     /// `parse.Tree.Root.String()` output.
+    ///
+    /// Actions are printed with the delimiters the template was parsed with.
     pub fn to_template_string(&self) -> String {
         let mut out = String::new();
-        Self::write_block(&mut out, &self.ast.root);
+        Self::write_block(&mut out, &self.ast.root, &self.ast.delimiters);
         out
     }
 
-    fn write_block(out: &mut String, block: &Block) {
+    /// Captures the template, its AST and its associated templates in a
+    /// [`TemplateDocument`] for serialization.
+    #[cfg(feature = "serde")]
+    pub fn to_document(&self) -> TemplateDocument {
+        let mut templates: Vec<Ast> = self
+            .templates
+            .values()
+            .filter(|tree| !Arc::ptr_eq(tree, &self.ast))
+            .map(|tree| Ast::clone(tree))
+            .collect();
+        templates.sort_by(|a, b| a.name.cmp(&b.name));
+        TemplateDocument {
+            schema_version: SCHEMA_VERSION,
+            name: self.name.clone(),
+            source: self.source().to_string(),
+            ast: Ast::clone(&self.ast),
+            templates,
+        }
+    }
+
+    /// Rebuilds a renderable template from a deserialized [`TemplateDocument`],
+    /// associating it with `functions`. Go-dialect templates are checked for
+    /// undefined functions, as when parsing.
+    #[cfg(feature = "serde")]
+    pub fn from_document(
+        document: TemplateDocument,
+        functions: FunctionRegistry,
+    ) -> Result<Self, Error> {
+        check_functions(&document.ast, &functions)?;
+        for tree in &document.templates {
+            check_functions(tree, &functions)?;
+        }
+        Ok(Self::from_document_unchecked(document, functions))
+    }
+
+    #[cfg(feature = "serde")]
+    pub(crate) fn from_document_unchecked(
+        document: TemplateDocument,
+        functions: FunctionRegistry,
+    ) -> Self {
+        let ast = Arc::new(document.ast);
+        let source = Arc::new(SourceMap::new(document.source));
+        let mut templates = HashMap::new();
+        for tree in document.templates {
+            templates.insert(tree.name.clone(), Arc::new(tree));
+        }
+        templates
+            .entry(document.name.clone())
+            .or_insert_with(|| ast.clone());
+        // Only trees parsed from this document's source can be located in it.
+        let sources = templates
+            .iter()
+            .filter(|(_, tree)| tree.parse_name == ast.parse_name)
+            .map(|(name, _)| (name.clone(), source.clone()))
+            .collect();
+        Self::from_parts(
+            &document.name,
+            source,
+            ast,
+            functions,
+            Arc::new(templates),
+            Arc::new(sources),
+        )
+    }
+
+    /// Returns the template source in canonical form; see [`format_template`].
+    /// Unlike [`Template::to_template_string`], text, trim markers and literal
+    /// spelling are kept, so the result renders exactly like the original.
+    pub fn format(&self, options: &FormatOptions) -> Result<String, Error> {
+        format::format_source(self.source(), &self.ast.delimiters, options)
+    }
+
+    /// Reports whether the template source is already in canonical form.
+    pub fn is_formatted(&self, options: &FormatOptions) -> Result<bool, Error> {
+        Ok(self.format(options)? == self.source())
+    }
+
+    fn write_block(out: &mut String, block: &Block, delims: &Delimiters) {
+        let open = |out: &mut String, keyword: &str| {
+            out.push_str(&delims.left);
+            out.push_str(keyword);
+        };
+        let close = |out: &mut String| out.push_str(&delims.right);
+        let tag = |out: &mut String, keyword: &str| {
+            open(out, keyword);
+            close(out);
+        };
         for node in &block.nodes {
             match node {
                 Node::Text(text) => out.push_str(&text.text),
-                Node::Comment(comment) => out.push_str(&comment.to_template_fragment()),
-                Node::Action(action) => out.push_str(&action.to_template_fragment()),
+                Node::Comment(comment) => out.push_str(&comment.to_template_fragment_with(delims)),
+                Node::Action(action) => out.push_str(&action.to_template_fragment_with(delims)),
                 Node::If(if_node) => {
-                    out.push_str("{{if ");
+                    open(out, "if ");
                     out.push_str(&pipeline_to_string(&if_node.pipeline));
-                    out.push_str("}}");
-                    Self::write_block(out, &if_node.then_block);
+                    close(out);
+                    Self::write_block(out, &if_node.then_block, delims);
                     for branch in &if_node.else_if_branches {
-                        out.push_str("{{else if ");
+                        open(out, "else if ");
                         out.push_str(&pipeline_to_string(&branch.pipeline));
-                        out.push_str("}}");
-                        Self::write_block(out, &branch.block);
+                        close(out);
+                        Self::write_block(out, &branch.block, delims);
                     }
                     if let Some(else_block) = &if_node.else_block {
-                        out.push_str("{{else}}");
-                        Self::write_block(out, else_block);
+                        tag(out, "else");
+                        Self::write_block(out, else_block, delims);
                     }
-                    out.push_str("{{end}}");
+                    tag(out, "end");
                 }
                 Node::Range(range_node) => {
-                    out.push_str("{{range ");
+                    open(out, "range ");
                     out.push_str(&pipeline_to_string(&range_node.pipeline));
-                    out.push_str("}}");
-                    Self::write_block(out, &range_node.then_block);
+                    close(out);
+                    Self::write_block(out, &range_node.then_block, delims);
                     if let Some(else_block) = &range_node.else_block {
-                        out.push_str("{{else}}");
-                        Self::write_block(out, else_block);
+                        tag(out, "else");
+                        Self::write_block(out, else_block, delims);
                     }
-                    out.push_str("{{end}}");
+                    tag(out, "end");
                 }
                 Node::With(with_node) => {
-                    out.push_str("{{with ");
+                    open(out, "with ");
                     out.push_str(&pipeline_to_string(&with_node.pipeline));
-                    out.push_str("}}");
-                    Self::write_block(out, &with_node.then_block);
+                    close(out);
+                    Self::write_block(out, &with_node.then_block, delims);
                     if let Some(else_block) = &with_node.else_block {
-                        out.push_str("{{else}}");
-                        Self::write_block(out, else_block);
+                        tag(out, "else");
+                        Self::write_block(out, else_block, delims);
                     }
-                    out.push_str("{{end}}");
+                    tag(out, "end");
                 }
+                Node::Define(define) => {
+                    open(out, "define ");
+                    out.push_str(&expression_to_string(&Expression::StringLiteral(
+                        define.name.clone(),
+                    )));
+                    close(out);
+                    Self::write_block(out, &define.block, delims);
+                    tag(out, "end");
+                }
+                Node::Template(template) => {
+                    open(out, "template ");
+                    out.push_str(&expression_to_string(&Expression::StringLiteral(
+                        template.name.clone(),
+                    )));
+                    if let Some(pipeline) = &template.pipeline {
+                        out.push(' ');
+                        out.push_str(&pipeline_to_string(pipeline));
+                    }
+                    close(out);
+                }
+                Node::Block(block) => {
+                    open(out, "block ");
+                    out.push_str(&expression_to_string(&Expression::StringLiteral(
+                        block.name.clone(),
+                    )));
+                    out.push(' ');
+                    out.push_str(&pipeline_to_string(&block.pipeline));
+                    close(out);
+                    Self::write_block(out, &block.block, delims);
+                    tag(out, "end");
+                }
+                Node::Break(_) => tag(out, "break"),
+                Node::Continue(_) => tag(out, "continue"),
             }
         }
     }
 
     /// Renders the template against the provided data.
     pub fn render(&self, data: &Value) -> Result<String, Error> {
-        let mut ctx = runtime::EvalContext::new(data.clone(), self.functions.clone());
+        self.render_with_options(data, &RenderOptions::default())
+    }
+
+    /// Renders the template with execution options such as `missingkey`.
+    pub fn render_with_options(
+        &self,
+        data: &Value,
+        options: &RenderOptions,
+    ) -> Result<String, Error> {
         let mut output = String::new();
-        Self::render_block(&mut ctx, &self.ast.root, &mut output)?;
+        self.execute(data, options, &mut output)?;
         Ok(output)
     }
 
+    /// Renders the template against any [`serde::Serialize`] value, such as a
+    /// typed struct, without the caller building a [`Value`] first. The data is
+    /// converted the way [`serde_json::to_value`] does: `#[serde(rename)]` and
+    /// other attributes apply, integers keep their `i64`/`u64` values, and
+    /// maps need string keys. Rendering then behaves exactly like
+    /// [`Template::render`].
+    #[cfg(feature = "serde")]
+    pub fn render_serialize<T: serde::Serialize + ?Sized>(
+        &self,
+        data: &T,
+    ) -> Result<String, Error> {
+        self.render_serialize_with_options(data, &RenderOptions::default())
+    }
+
+    /// Variant of [`Template::render_serialize`] with execution options.
+    #[cfg(feature = "serde")]
+    pub fn render_serialize_with_options<T: serde::Serialize + ?Sized>(
+        &self,
+        data: &T,
+        options: &RenderOptions,
+    ) -> Result<String, Error> {
+        let data = serde_json::to_value(data).map_err(|err| Error::Render {
+            message: format!("cannot serialize template data: {err}"),
+            source: Some(Box::new(err)),
+            span: None,
+            argument: None,
+            template: None,
+        })?;
+        // The converted value moves into the context instead of being borrowed
+        // and copied again.
+        let mut output = String::new();
+        self.run(&mut self.context(Cow::Owned(data), options), &mut output)?;
+        Ok(output)
+    }
+
+    /// Renders the template into `writer`, writing text and action output as
+    /// it is produced instead of collecting it in a `String`. Output written
+    /// before an error stays in the writer, as with Go's `Execute`; failures of
+    /// the writer itself are reported as [`Error::Write`]. Wrap unbuffered
+    /// writers such as files or sockets in an [`io::BufWriter`]. `data` is read
+    /// in place rather than copied up front.
+    pub fn render_to<W: io::Write>(&self, data: &Value, writer: W) -> Result<(), Error> {
+        self.render_to_with_options(data, writer, &RenderOptions::default())
+    }
+
+    /// Variant of [`Template::render_to`] with execution options.
+    pub fn render_to_with_options<W: io::Write>(
+        &self,
+        data: &Value,
+        writer: W,
+        options: &RenderOptions,
+    ) -> Result<(), Error> {
+        self.execute(data, options, &mut IoOutput(writer))
+    }
+
+    /// Renders the template into a [`fmt::Write`] such as a `String` or a
+    /// `fmt::Formatter`; see [`Template::render_to`].
+    pub fn render_fmt<W: fmt::Write>(&self, data: &Value, writer: W) -> Result<(), Error> {
+        self.render_fmt_with_options(data, writer, &RenderOptions::default())
+    }
+
+    /// Variant of [`Template::render_fmt`] with execution options.
+    pub fn render_fmt_with_options<W: fmt::Write>(
+        &self,
+        data: &Value,
+        writer: W,
+        options: &RenderOptions,
+    ) -> Result<(), Error> {
+        self.execute(data, options, &mut FmtOutput(writer))
+    }
+
+    /// Renders the template with helpers registered through
+    /// [`FunctionRegistryBuilder::register_async`] awaited as needed; see there
+    /// for how passes are repeated. The returned future does not depend on a
+    /// particular async runtime. Templates without async helpers render in a
+    /// single pass, exactly like [`Template::render`].
+    pub async fn render_async(&self, data: &Value) -> Result<String, Error> {
+        self.render_async_with_options(data, &RenderOptions::default())
+            .await
+    }
+
+    /// Variant of [`Template::render_async`] with execution options. Limits
+    /// apply to each pass as to a synchronous render, and cancellation is
+    /// checked again after every awaited helper.
+    pub async fn render_async_with_options(
+        &self,
+        data: &Value,
+        options: &RenderOptions,
+    ) -> Result<String, Error> {
+        let mut calls = AsyncCalls::default();
+        loop {
+            let mut output = String::new();
+            let mut ctx = self
+                .context(Cow::Borrowed(data), options)
+                .with_async_calls(calls);
+            let result = self.run(&mut ctx, &mut output);
+            calls = ctx.into_async_calls().unwrap_or_default();
+            if !calls.resolve_pending(&self.functions).await {
+                return result.map(|()| output);
+            }
+        }
+    }
+
+    fn execute(
+        &self,
+        data: &Value,
+        options: &RenderOptions,
+        output: &mut dyn Output,
+    ) -> Result<(), Error> {
+        self.run(&mut self.context(Cow::Borrowed(data), options), output)
+    }
+
+    fn context<'data>(
+        &self,
+        data: Cow<'data, Value>,
+        options: &RenderOptions,
+    ) -> runtime::EvalContext<'data> {
+        runtime::EvalContext::with_data(data, self.functions.clone())
+            .with_templates(self.templates.clone())
+            .with_options(options.clone())
+            .with_dialect(self.ast.dialect)
+    }
+
+    fn run(&self, ctx: &mut runtime::EvalContext, output: &mut dyn Output) -> Result<(), Error> {
+        // A definition may replace the body associated with this template's own name.
+        let tree = self
+            .templates
+            .get(&self.name)
+            .cloned()
+            .unwrap_or_else(|| self.ast.clone());
+        Self::render_block(ctx, &tree.root, output).map_err(|err| err.in_template(&self.name))?;
+        Ok(())
+    }
+
+    /// Executes the named template with `data` as dot, appending to `output`.
+    pub(crate) fn render_template_call(
+        ctx: &mut runtime::EvalContext,
+        name: &str,
+        data: Value,
+        span: Option<Span>,
+        output: &mut dyn Output,
+    ) -> Result<(), Error> {
+        let tree = ctx
+            .template(name)
+            .ok_or_else(|| Error::render(format!("template {name:?} not defined"), span))?;
+        let saved = ctx.enter_template(data, span)?;
+        let render_result = Self::render_block(ctx, &tree.root, output);
+        ctx.exit_template(saved);
+        // The parser keeps `break`/`continue` from escaping a template body.
+        // Errors without a location are placed by the caller, in its own tree.
+        render_result.map(|_| ()).map_err(|err| match err.span() {
+            Some(_) => err.in_template(name),
+            None => err,
+        })
+    }
+
     fn render_block(
         ctx: &mut runtime::EvalContext,
         block: &Block,
-        output: &mut String,
-    ) -> Result<(), Error> {
+        output: &mut dyn Output,
+    ) -> Result<Flow, Error> {
         for node in &block.nodes {
-            match node {
-                Node::Text(text) => output.push_str(&text.text),
-                Node::Comment(_) => {}
+            ctx.set_span(node_span(node));
+            ctx.check_cancelled()?;
+            let flow = match node {
+                Node::Text(text) => {
+                    ctx.charge_output(text.text.len(), text.span)?;
+                    output
+                        .write_str(&text.text)
+                        .map_err(|err| err.with_span(text.span))?;
+                    Flow::Next
+                }
+                Node::Comment(_) | Node::Define(_) => Flow::Next,
                 Node::Action(action) => {
                     let value = ctx.eval_pipeline(&action.pipeline)?;
                     ctx.apply_bindings(&action.pipeline, &value)?;
                     if action.pipeline.declarations.is_none() {
-                        output.push_str(&runtime::value_to_string(&value));
+                        let text = ctx.format_value(&value);
+                        ctx.charge_output(text.len(), action.span)?;
+                        output
+                            .write_str(&text)
+                            .map_err(|err| err.with_span(action.span))?;
                     }
+                    Flow::Next
                 }
                 Node::If(if_node) => Self::render_if(ctx, if_node, output)?,
                 Node::Range(range_node) => Self::render_range(ctx, range_node, output)?,
                 Node::With(with_node) => Self::render_with(ctx, with_node, output)?,
+                Node::Template(template) => {
+                    let data = match &template.pipeline {
+                        Some(pipeline) => ctx.eval_pipeline(pipeline)?,
+                        None => Value::Null,
+                    };
+                    Self::render_template_call(
+                        ctx,
+                        &template.name,
+                        data,
+                        Some(node_span(node)),
+                        output,
+                    )?;
+                    Flow::Next
+                }
+                Node::Block(block) => {
+                    let data = ctx.eval_pipeline(&block.pipeline)?;
+                    Self::render_template_call(
+                        ctx,
+                        &block.name,
+                        data,
+                        Some(node_span(node)),
+                        output,
+                    )?;
+                    Flow::Next
+                }
+                Node::Break(_) => Flow::Break,
+                Node::Continue(_) => Flow::Continue,
+            };
+            if !matches!(flow, Flow::Next) {
+                return Ok(flow);
             }
         }
-        Ok(())
+        Ok(Flow::Next)
     }
 
     fn render_if(
         ctx: &mut runtime::EvalContext,
         node: &crate::ast::IfNode,
-        output: &mut String,
-    ) -> Result<(), Error> {
+        output: &mut dyn Output,
+    ) -> Result<Flow, Error> {
         let value = ctx.eval_pipeline(&node.pipeline)?;
         ctx.apply_bindings(&node.pipeline, &value)?;
         if runtime::is_truthy(&value) {
-            Self::render_block(ctx, &node.then_block, output)?;
-        } else {
-            for branch in &node.else_if_branches {
-                let branch_value = ctx.eval_pipeline(&branch.pipeline)?;
-                ctx.apply_bindings(&branch.pipeline, &branch_value)?;
-                if runtime::is_truthy(&branch_value) {
-                    Self::render_block(ctx, &branch.block, output)?;
-                    return Ok(());
-                }
-            }
-            if let Some(else_block) = &node.else_block {
-                Self::render_block(ctx, else_block, output)?;
+            return Self::render_block(ctx, &node.then_block, output);
+        }
+        for branch in &node.else_if_branches {
+            let branch_value = ctx.eval_pipeline(&branch.pipeline)?;
+            ctx.apply_bindings(&branch.pipeline, &branch_value)?;
+            if runtime::is_truthy(&branch_value) {
+                return Self::render_block(ctx, &branch.block, output);
             }
         }
-        Ok(())
+        if let Some(else_block) = &node.else_block {
+            return Self::render_block(ctx, else_block, output);
+        }
+        Ok(Flow::Next)
     }
 
-    fn render_range(
-        ctx: &mut runtime::EvalContext,
+    fn render_range<'data>(
+        ctx: &mut runtime::EvalContext<'data>,
         node: &crate::ast::RangeNode,
-        output: &mut String,
-    ) -> Result<(), Error> {
+        output: &mut dyn Output,
+    ) -> Result<Flow, Error> {
         ctx.predeclare_bindings(&node.pipeline);
-        let value = ctx.eval_pipeline(&node.pipeline)?;
-
-        let mut iterated = false;
+        let value = ctx.eval_pipeline_cow(&node.pipeline)?;
+        let span = tokens_span(&node.tokens).unwrap_or(node.span);
 
-        match value {
-            Value::Array(items) => {
-                if items.is_empty() {
-                    // handled later for else
-                } else {
-                    for (index, item) in items.iter().enumerate() {
-                        let key_value = Value::Number(Number::from(index as u64));
-                        ctx.assign_range_bindings(&node.pipeline, Some(key_value), item.clone())?;
-                        ctx.push_scope(item.clone());
-                        let render_result = Self::render_block(ctx, &node.then_block, output);
-                        ctx.pop_scope();
-                        render_result?;
-                        iterated = true;
+        // Elements of borrowed data stay borrowed; owned collections are
+        // taken apart rather than copied.
+        type Entries<'a> = Box<dyn Iterator<Item = (Value, Cow<'a, Value>)> + 'a>;
+        let index_key = |index: usize| Value::Number(Number::from(index as u64));
+        let entries: Entries<'data> = match value {
+            Cow::Borrowed(Value::Array(items)) => Box::new(
+                items
+                    .iter()
+                    .enumerate()
+                    .map(move |(index, item)| (index_key(index), Cow::Borrowed(item))),
+            ),
+            Cow::Owned(Value::Array(items)) => Box::new(
+                items
+                    .into_iter()
+                    .enumerate()
+                    .map(move |(index, item)| (index_key(index), Cow::Owned(item))),
+            ),
+            Cow::Borrowed(Value::Object(map)) => Box::new(
+                map.iter()
+                    .map(|(key, item)| (Value::String(key.clone()), Cow::Borrowed(item))),
+            ),
+            Cow::Owned(Value::Object(map)) => Box::new(
+                map.into_iter()
+                    .map(|(key, item)| (Value::String(key), Cow::Owned(item))),
+            ),
+            value => match &*value {
+                Value::Number(number) => {
+                    let count = range_count(number)
+                        .filter(|_| ctx.dialect().supports(GoVersion::GO1_22))
+                        .ok_or_else(|| {
+                            Error::render_with_span(
+                                format!(
+                                    "range can't iterate over {}",
+                                    runtime::value_to_string(&value)
+                                ),
+                                span,
+                            )
+                        })?;
+                    let declared = node
+                        .pipeline
+                        .declarations
+                        .as_ref()
+                        .map_or(0, |decls| decls.variables.len());
+                    if declared > 1 {
+                        return Err(Error::render_with_span(
+                            format!(
+                                "can't use {} to iterate over more than one variable",
+                                runtime::value_to_string(&value)
+                            ),
+                            span,
+                        ));
                     }
+                    Box::new((0..count).map(|index| {
+                        let item = Value::Number(Number::from(index));
+                        (item.clone(), Cow::Owned(item))
+                    }))
                 }
-            }
-            Value::Object(map) => {
-                if map.is_empty() {
-                    // handled later
-                } else {
-                    for (key, val) in map.iter() {
-                        let key_value = Value::String(key.clone());
-                        ctx.assign_range_bindings(&node.pipeline, Some(key_value), val.clone())?;
-                        ctx.push_scope(val.clone());
-                        let render_result = Self::render_block(ctx, &node.then_block, output);
-                        ctx.pop_scope();
-                        render_result?;
-                        iterated = true;
-                    }
+                // Like Go, a nil value ranges zero times and falls through to `else`.
+                Value::Null => Box::new(std::iter::empty()),
+                _ => {
+                    return Err(Error::render_with_span(
+                        format!(
+                            "range can't iterate over {}",
+                            runtime::value_to_string(&value)
+                        ),
+                        span,
+                    ));
                 }
+            },
+        };
+
+        let mut iterated = false;
+        for (key, item) in entries {
+            ctx.assign_range_bindings(&node.pipeline, Some(key), &item)?;
+            iterated = true;
+            if Self::render_iteration(ctx, node, item, output)? {
+                break;
             }
-            _ => {}
         }
 
         if !iterated {
-            ctx.assign_range_bindings(&node.pipeline, None, Value::Null)?;
+            ctx.assign_range_bindings(&node.pipeline, None, &Value::Null)?;
             if let Some(else_block) = &node.else_block {
-                Self::render_block(ctx, else_block, output)?;
+                // `break`/`continue` in the else branch target an enclosing range.
+                return Self::render_block(ctx, else_block, output);
             }
         }
 
-        Ok(())
+        Ok(Flow::Next)
+    }
+
+    /// Renders one range iteration and reports whether the loop should stop.
+    fn render_iteration<'data>(
+        ctx: &mut runtime::EvalContext<'data>,
+        node: &crate::ast::RangeNode,
+        item: Cow<'data, Value>,
+        output: &mut dyn Output,
+    ) -> Result<bool, Error> {
+        ctx.charge_iteration(tokens_span(&node.tokens).unwrap_or(node.span))?;
+        ctx.push_scope_cow(item);
+        let render_result = Self::render_block(ctx, &node.then_block, output);
+        ctx.pop_scope();
+        Ok(matches!(render_result?, Flow::Break))
     }
 
     fn render_with(
         ctx: &mut runtime::EvalContext,
         node: &crate::ast::WithNode,
-        output: &mut String,
-    ) -> Result<(), Error> {
-        let value = ctx.eval_pipeline(&node.pipeline)?;
+        output: &mut dyn Output,
+    ) -> Result<Flow, Error> {
+        let value = ctx.eval_pipeline_cow(&node.pipeline)?;
         ctx.apply_bindings(&node.pipeline, &value)?;
         if runtime::is_truthy(&value) {
-            ctx.push_scope(value.clone());
+            ctx.push_scope_cow(value);
             let render_result = Self::render_block(ctx, &node.then_block, output);
             ctx.pop_scope();
-            render_result?;
-        } else if let Some(else_block) = &node.else_block {
-            Self::render_block(ctx, else_block, output)?;
+            return render_result;
+        }
+        if let Some(else_block) = &node.else_block {
+            return Self::render_block(ctx, else_block, output);
+        }
+        Ok(Flow::Next)
+    }
+}
+
+/// Parses `source` like Go's `Template.Parse` with `functions` installed:
+/// registered `break`/`continue` helpers shadow the keywords, and the Go
+/// dialect rejects calls to functions that are not registered.
+pub(crate) fn parse_ast(
+    name: &str,
+    source: &str,
+    functions: &FunctionRegistry,
+    options: &ParseOptions,
+) -> Result<Ast, Error> {
+    let mut options = options.clone();
+    for keyword in [Keyword::Break, Keyword::Continue] {
+        if functions.get(keyword.as_str()).is_some() {
+            options = options.with_function_keyword(keyword);
         }
-        Ok(())
     }
+    let ast = parser::parse_template_with_options(name, source, &options)?;
+    check_functions(&ast, functions)?;
+    Ok(ast)
+}
+
+/// Go resolves functions at parse time, so Go-dialect templates may only call
+/// functions that are registered.
+fn check_functions(ast: &Ast, functions: &FunctionRegistry) -> Result<(), Error> {
+    if ast.dialect.is_go() {
+        let analysis = analyze::analyze_template(ast, Some(functions));
+        if let Some(call) = analysis.unknown_functions.first() {
+            return Err(Error::parse_with_span(
+                format!("function {:?} not defined", call.name),
+                call.span,
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Span reported for evaluation errors raised while executing `node`: the
+/// action itself, or the pipeline of a control structure.
+fn node_span(node: &Node) -> Span {
+    let tokens = match node {
+        Node::If(node) => &node.tokens,
+        Node::Range(node) => &node.tokens,
+        Node::With(node) => &node.tokens,
+        Node::Block(node) => &node.tokens,
+        Node::Template(node) => &node.tokens,
+        _ => return node.span(),
+    };
+    tokens_span(tokens).unwrap_or_else(|| node.span())
+}
+
+/// Span covering a control action's pipeline tokens.
+fn tokens_span(tokens: &[Token]) -> Option<Span> {
+    Some(Span::new(
+        tokens.first()?.span.start,
+        tokens.last()?.span.end,
+    ))
+}
+
+/// Number of iterations for ranging over an integer (Go 1.22+). Non-positive
+/// integers range zero times; non-integral numbers cannot be ranged over.
+fn range_count(number: &Number) -> Option<u64> {
+    if let Some(count) = number.as_u64() {
+        Some(count)
+    } else if number.as_i64().is_some() {
+        Some(0)
+    } else {
+        None
+    }
+}
+
+/// How rendering continues after a block, letting `{{break}}` and
+/// `{{continue}}` unwind through nested `if`/`with` bodies to their range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Flow {
+    Next,
+    Break,
+    Continue,
 }
 
 fn pipeline_to_string(pipeline: &Pipeline) -> String {
@@ -317,7 +884,7 @@ fn pipeline_to_string(pipeline: &Pipeline) -> String {
     out
 }
 
-fn expression_to_string(expr: &Expression) -> String {
+pub(crate) fn expression_to_string(expr: &Expression) -> String {
     match expr {
         Expression::Identifier(name) => name.clone(),
         Expression::Field(parts) => {
@@ -331,7 +898,10 @@ fn expression_to_string(expr: &Expression) -> String {
         Expression::PipelineExpr(pipeline) => {
             format!("({})", pipeline_to_string(pipeline))
         }
-        Expression::StringLiteral(value) => format!("\"{}\"", value),
+        Expression::Chain { target, fields } => {
+            format!("{}.{}", expression_to_string(target), fields.join("."))
+        }
+        Expression::StringLiteral(value) => lexer::quote_string(value),
         Expression::NumberLiteral(value) => value.clone(),
         Expression::BoolLiteral(flag) => flag.to_string(),
         Expression::Nil => "nil".to_string(),
@@ -441,16 +1011,16 @@ mod tests {
     fn comment_trimming_matches_go() {
         let left = Template::parse_str("comment-left", "x \r\n\t{{- /* hi */}}").unwrap();
         assert_eq!(left.render(&json!({})).unwrap(), "x");
-        assert_eq!(left.to_template_string(), "x{{-/*hi*/}}");
+        assert_eq!(left.to_template_string(), "x{{- /*hi*/}}");
 
         let right = Template::parse_str("comment-right", "{{/* hi */ -}}\n\n\ty").unwrap();
         assert_eq!(right.render(&json!({})).unwrap(), "y");
-        assert_eq!(right.to_template_string(), "{{/*hi*/-}}y");
+        assert_eq!(right.to_template_string(), "{{/*hi*/ -}}y");
 
         let both =
             Template::parse_str("comment-both", "left \n{{- /* trim */ -}}\n right").unwrap();
         assert_eq!(both.render(&json!({})).unwrap(), "leftright");
-        assert_eq!(both.to_template_string(), "left{{-/*trim*/-}}right");
+        assert_eq!(both.to_template_string(), "left{{- /*trim*/ -}}right");
     }
 
     #[test]
@@ -501,6 +1071,235 @@ mod tests {
         assert!(err.to_string().contains("variable $v not defined"));
     }
 
+    #[test]
+    fn template_invocation_rebinds_dot_and_root() {
+        let tmpl = Template::parse_str(
+            "define",
+            r#"{{define "item"}}{{.name}}/{{$.name}}{{end}}{{template "item" .inner}}|{{$.name}}"#,
+        )
+        .unwrap();
+        let rendered = tmpl
+            .render(&json!({"name": "outer", "inner": {"name": "inner"}}))
+            .unwrap();
+        assert_eq!(rendered, "inner/inner|outer");
+        assert_eq!(tmpl.template_names(), vec!["define", "item"]);
+    }
+
+    #[test]
+    fn block_renders_default_body() {
+        let tmpl = Template::parse_str(
+            "block",
+            "{{block \"list\" .items}}{{range .}}[{{.}}]{{end}}{{end}}",
+        )
+        .unwrap();
+        let rendered = tmpl.render(&json!({"items": ["a", "b"]})).unwrap();
+        assert_eq!(rendered, "[a][b]");
+        assert_eq!(
+            tmpl.to_template_string(),
+            "{{block \"list\" .items}}{{range .}}[{{.}}]{{end}}{{end}}"
+        );
+    }
+
+    #[test]
+    fn helpers_can_render_named_templates() {
+        let mut builder = FunctionRegistry::builder();
+        builder.register("include", |ctx, args| {
+            let name = args.first().and_then(Value::as_str).unwrap_or_default();
+            let data = args.get(1).cloned().unwrap_or(Value::Null);
+            ctx.execute_template(name, data).map(Value::String)
+        });
+        let tmpl = Template::parse_with_functions(
+            "include",
+            r#"{{define "label"}}<{{.}}>{{end}}{{$x := include "label" .value}}{{$x}}{{$x}}"#,
+            builder.build(),
+        )
+        .unwrap();
+        assert_eq!(tmpl.render(&json!({"value": "v"})).unwrap(), "<v><v>");
+    }
+
+    #[test]
+    fn recursive_templates_hit_depth_limit() {
+        let tmpl = Template::parse_str(
+            "loop",
+            r#"{{define "r"}}{{template "r" .}}{{end}}{{template "r"}}"#,
+        )
+        .unwrap();
+        let err = tmpl.render(&json!({})).unwrap_err();
+        assert_eq!(err.limit(), Some(Limit::Depth));
+        assert!(err
+            .to_string()
+            .contains("exceeded maximum template depth (100)"));
+    }
+
+    #[test]
+    fn custom_delimiters_render_and_print() {
+        let options = ParseOptions::new().with_delimiters("[[", "]]");
+        let tmpl = Template::parse_with_options(
+            "delims",
+            "{{ literal }} [[- if .on ]]on[[else]]off[[end]] [[/* c */]][[.name -]] !",
+            FunctionRegistry::empty(),
+            &options,
+        )
+        .unwrap();
+        assert_eq!(
+            tmpl.render(&json!({"on": true, "name": "x"})).unwrap(),
+            "{{ literal }}on x!"
+        );
+        assert_eq!(
+            tmpl.to_template_string(),
+            "{{ literal }}[[if .on]]on[[else]]off[[end]] [[/*c*/]][[.name -]]!"
+        );
+    }
+
+    #[test]
+    fn chain_accesses_fields_of_computed_values() {
+        let mut builder = FunctionRegistry::builder();
+        builder.register("first", |_ctx, args| {
+            Ok(args
+                .first()
+                .and_then(|list| list.get(0))
+                .cloned()
+                .unwrap_or(Value::Null))
+        });
+        let tmpl = Template::parse_with_functions(
+            "chain",
+            "{{(first .items).name}} {{with $x := .items}}{{(first $x).tags.1}}{{end}}",
+            builder.build(),
+        )
+        .unwrap();
+        let data = json!({"items": [{"name": "a", "tags": ["t0", "t1"]}]});
+        assert_eq!(tmpl.render(&data).unwrap(), "a t1");
+        assert_eq!(
+            tmpl.to_template_string(),
+            "{{(first .items).name}} {{with $x := .items}}{{(first $x).tags.1}}{{end}}"
+        );
+
+        let err = Template::parse_str("bad", "{{(.n).x}}")
+            .unwrap()
+            .render(&json!({"n": 1}))
+            .unwrap_err();
+        assert!(err.to_string().contains("cannot access field x"));
+    }
+
+    #[test]
+    fn missing_keys_follow_missingkey_option() {
+        let tmpl =
+            Template::parse_str("missing", "[{{.name}}] [{{.user.nmae}}] [{{.none.x}}]").unwrap();
+        let data = json!({"name": null, "user": {"name": "x"}});
+
+        assert_eq!(
+            tmpl.render(&data).unwrap(),
+            "[<no value>] [<no value>] [<no value>]"
+        );
+        let zero = RenderOptions::new().with_missing_key(MissingKey::Zero);
+        assert_eq!(tmpl.render_with_options(&data, &zero).unwrap(), "[] [] []");
+
+        let strict = RenderOptions::new().with_missing_key(MissingKey::Error);
+        let err = tmpl.render_with_options(&data, &strict).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("map has no entry for key \"nmae\" in .user.nmae"));
+        let Error::Render { span, .. } = err else {
+            panic!("expected render error");
+        };
+        assert_eq!(span, Some(Span::new(15, 25)));
+    }
+
+    #[test]
+    fn missingkey_error_covers_nil_data_and_control_pipelines() {
+        let strict = RenderOptions::new().with_missing_key(MissingKey::Error);
+        let tmpl = Template::parse_str("nil", "{{if .a}}{{end}}").unwrap();
+        let err = tmpl
+            .render_with_options(&json!({"b": 1}), &strict)
+            .unwrap_err();
+        let Error::Render { span, message, .. } = err else {
+            panic!("expected render error");
+        };
+        assert_eq!(message, "map has no entry for key \"a\" in .a");
+        assert_eq!(span, Some(Span::new(5, 7)));
+
+        let tmpl = Template::parse_str("nil", "{{.a.b}}").unwrap();
+        let err = tmpl
+            .render_with_options(&json!({"a": null}), &strict)
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("nil data; no entry for key \"b\" in .a.b"));
+        assert_eq!(
+            tmpl.render_with_options(&json!({"a": {"b": 1}}), &strict)
+                .unwrap(),
+            "1"
+        );
+    }
+
+    #[test]
+    fn range_over_integers() {
+        let tmpl = Template::parse_str(
+            "ints",
+            "{{range 3}}{{.}}{{end}}|{{range $i := .count}}{{$i}}{{end}}|{{range .none}}x{{else}}empty{{end}}",
+        )
+        .unwrap();
+        assert_eq!(
+            tmpl.render(&json!({"count": 2, "none": -1})).unwrap(),
+            "012|01|empty"
+        );
+
+        let two_vars = Template::parse_str("two", "{{range $i, $v := 3}}{{end}}").unwrap();
+        let err = two_vars.render(&json!({})).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("can't use 3 to iterate over more than one variable"));
+    }
+
+    #[test]
+    fn range_over_unsupported_kind_is_error() {
+        let tmpl = Template::parse_str("bad", "ok {{range .v}}x{{end}}").unwrap();
+        for value in [json!("abc"), json!(true), json!(1.5)] {
+            let err = tmpl.render(&json!({ "v": value })).unwrap_err();
+            assert!(err.to_string().contains("range can't iterate over"));
+            let Error::Render { span, .. } = err else {
+                panic!("expected render error");
+            };
+            assert_eq!(span, Some(Span::new(11, 13)));
+        }
+        assert_eq!(tmpl.render(&json!({})).unwrap(), "ok ");
+    }
+
+    #[test]
+    fn break_and_continue_unwind_through_if_and_with() {
+        let tmpl = Template::parse_str(
+            "loop",
+            "{{range .items}}{{with .skip}}{{continue}}{{end}}{{if .stop}}{{break}}{{end}}{{.v}}{{end}}",
+        )
+        .unwrap();
+        let data = json!({"items": [
+            {"v": "a"},
+            {"v": "b", "skip": true},
+            {"v": "c"},
+            {"v": "d", "stop": true},
+            {"v": "e"}
+        ]});
+        assert_eq!(tmpl.render(&data).unwrap(), "ac");
+    }
+
+    #[test]
+    fn break_targets_innermost_range() {
+        let tmpl = Template::parse_str(
+            "nested",
+            "{{range .outer}}[{{range .inner}}{{if .stop}}{{break}}{{end}}{{.v}}{{end}}]{{end}}",
+        )
+        .unwrap();
+        let data = json!({"outer": [
+            {"inner": [{"v": 1}, {"v": 2, "stop": true}, {"v": 3}]},
+            {"inner": [{"v": 4}]}
+        ]});
+        assert_eq!(tmpl.render(&data).unwrap(), "[1][4]");
+        assert_eq!(
+            tmpl.to_template_string(),
+            "{{range .outer}}[{{range .inner}}{{if .stop}}{{break}}{{end}}{{.v}}{{end}}]{{end}}"
+        );
+    }
+
     #[test]
     fn pipeline_expression_inside_if() {
         let mut builder = FunctionRegistry::builder();
@@ -537,4 +1336,413 @@ mod tests {
             .to_template_string()
             .contains("{{if ge (.x | default 1) 1}}"));
     }
+
+    #[test]
+    fn go_dialect_treats_identifiers_as_functions() {
+        let go = ParseOptions::new().with_dialect(Dialect::go());
+        let mut builder = FunctionRegistryBuilder::new();
+        builder.register("greeting", |_ctx, _args| Ok(json!("hi")));
+        builder.register("shout", |_ctx, args| {
+            Ok(json!(runtime::value_to_string(&args[0]).to_uppercase()))
+        });
+        let functions = builder.build();
+
+        let tmpl = Template::parse_with_options(
+            "go",
+            "{{greeting}} {{shout greeting}}",
+            functions.clone(),
+            &go,
+        )
+        .unwrap();
+        assert_eq!(tmpl.render(&json!({"greeting": "data"})).unwrap(), "hi HI");
+
+        let err = Template::parse_with_options("go", "{{name}}", functions.clone(), &go)
+            .expect_err("unknown function");
+        assert!(err.to_string().contains(r#"function "name" not defined"#));
+
+        let lithos = Template::parse_str("lithos", "{{name}}").unwrap();
+        assert_eq!(lithos.render(&json!({"name": "data"})).unwrap(), "data");
+    }
+
+    #[test]
+    fn go_dialect_ranges_over_integers_from_go1_22() {
+        let source = "{{range 2}}{{.}}{{end}}";
+        let old = ParseOptions::new().with_dialect(Dialect::Go {
+            version: GoVersion::new(1, 21),
+        });
+        let tmpl =
+            Template::parse_with_options("old", source, FunctionRegistry::empty(), &old).unwrap();
+        let err = tmpl.render(&json!({})).unwrap_err();
+        assert!(err.to_string().contains("range can't iterate over 2"));
+
+        let new = ParseOptions::new().with_dialect(Dialect::go());
+        let tmpl =
+            Template::parse_with_options("new", source, FunctionRegistry::empty(), &new).unwrap();
+        assert_eq!(tmpl.render(&json!({})).unwrap(), "01");
+    }
+
+    #[test]
+    fn helper_errors_point_at_call_site_and_argument() {
+        let mut builder = FunctionRegistryBuilder::new();
+        builder
+            .register("toJson", |_ctx, args| {
+                Ok(Value::String(serde_json::to_string(&args[0]).unwrap()))
+            })
+            .register("indent", |_ctx, args| {
+                if args[0].is_number() {
+                    Err(Error::render("input too long", None))
+                } else {
+                    Err(Error::render("width must be a number", None).with_argument(0))
+                }
+            });
+        let functions = builder.build();
+        let span_of = |source: &str, err: &Error| {
+            let span = err.span().expect("render errors carry a span");
+            source[span.start..span.end].to_string()
+        };
+
+        let source = "line one\n{{ toJson .x | indent 4 }}";
+        let tmpl = Template::parse_with_functions("call", source, functions.clone()).unwrap();
+        let err = tmpl.render(&json!({"x": 1})).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("error calling indent: input too long"));
+        assert_eq!(span_of(source, &err), "indent 4");
+        assert_eq!(
+            tmpl.format_error(&err),
+            r#"template: call:2:15: executing "call" at <indent 4>: error calling indent: input too long"#
+        );
+
+        let source = r#"{{ toJson .x | indent "four" }}"#;
+        let tmpl = Template::parse_with_functions("arg", source, functions.clone()).unwrap();
+        let err = tmpl.render(&json!({"x": 1})).unwrap_err();
+        assert_eq!(span_of(source, &err), r#""four""#);
+
+        let source = "{{ indent 2 .a.b }}";
+        let tmpl = Template::parse_with_functions("eval", source, functions).unwrap();
+        let strict = RenderOptions::new().with_missing_key(MissingKey::Error);
+        let err = tmpl
+            .render_with_options(&json!({"a": {}}), &strict)
+            .unwrap_err();
+        assert!(err.to_string().contains("map has no entry for key \"b\""));
+        assert_eq!(span_of(source, &err), ".a.b");
+    }
+
+    #[test]
+    fn streams_output_and_keeps_it_on_errors() {
+        let tmpl = Template::parse_str("stream", "{{ range . }}<{{ . }}>{{ end }}{{ index . 9 }}")
+            .unwrap();
+        let data = json!([1, 2]);
+
+        let mut bytes = Vec::new();
+        let err = tmpl.render_to(&data, &mut bytes).unwrap_err();
+        assert!(matches!(err, Error::Render { .. }), "{err:?}");
+        assert_eq!(bytes, b"<1><2>");
+
+        let mut text = String::new();
+        tmpl.render_fmt(&data, &mut text).unwrap_err();
+        assert_eq!(text, "<1><2>");
+
+        let ok = Template::parse_str("ok", "a{{ . }}b").unwrap();
+        let mut text = String::from(">");
+        ok.render_fmt(&json!(1), &mut text).unwrap();
+        assert_eq!(text, ">a1b");
+    }
+
+    #[test]
+    fn writer_failures_are_write_errors() {
+        /// Accepts `room` bytes, then fails.
+        struct Full {
+            written: Vec<u8>,
+            room: usize,
+        }
+        impl io::Write for Full {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                if self.written.len() + buf.len() > self.room {
+                    return Err(io::Error::new(io::ErrorKind::WriteZero, "disk full"));
+                }
+                self.written.extend_from_slice(buf);
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let tmpl = Template::parse_str("full", "head\n{{ .name }}\ntail").unwrap();
+        let mut writer = Full {
+            written: Vec::new(),
+            room: 8,
+        };
+        let err = tmpl
+            .render_to(&json!({"name": "value"}), &mut writer)
+            .unwrap_err();
+        assert!(matches!(err, Error::Write { .. }), "{err:?}");
+        assert_eq!(err.message(), "disk full");
+        assert_eq!(err.span(), Some(Span::new(5, 16)));
+        assert_eq!(tmpl.format_error(&err), "disk full");
+        let source = std::error::Error::source(&err).unwrap();
+        assert!(source.downcast_ref::<io::Error>().is_some());
+        assert_eq!(writer.written, b"head\n");
+    }
+
+    #[test]
+    fn limits_stop_renders_with_typed_errors() {
+        let mut builder = FunctionRegistry::builder();
+        builder.register("budget", |ctx, _args| {
+            Ok(json!(ctx.remaining(Limit::HelperCalls)))
+        });
+        builder.register("big", |_ctx, args| Ok(json!("x".repeat(args.len() * 10))));
+        let functions = builder.build();
+        let render = |source: &str, limit: Limit, max: usize| {
+            let tmpl = Template::parse_with_functions("limits", source, functions.clone()).unwrap();
+            let options = RenderOptions::new().with_limit(limit, max);
+            let mut output = String::new();
+            let result =
+                tmpl.render_fmt_with_options(&json!({"items": [1, 2, 3]}), &mut output, &options);
+            (result, output)
+        };
+
+        let (result, output) = render(
+            "abc{{ range .items }}{{ . }}{{ end }}",
+            Limit::OutputBytes,
+            4,
+        );
+        let err = result.unwrap_err();
+        assert_eq!(err.limit(), Some(Limit::OutputBytes));
+        assert_eq!(err.message(), "exceeded maximum output size (4 bytes)");
+        assert_eq!(err.span(), Some(Span::new(21, 28)));
+        assert_eq!(output, "abc1");
+
+        let (result, output) = render("{{ range .items }}{{ . }}{{ end }}", Limit::Iterations, 2);
+        let err = result.unwrap_err();
+        assert_eq!(err.limit(), Some(Limit::Iterations));
+        assert_eq!(
+            err.to_string(),
+            "render error: exceeded maximum loop iterations (2)"
+        );
+        assert_eq!(output, "12");
+
+        let (result, output) = render(
+            "{{ budget }} {{ budget }} {{ budget }}",
+            Limit::HelperCalls,
+            2,
+        );
+        assert_eq!(result.unwrap_err().limit(), Some(Limit::HelperCalls));
+        assert_eq!(output, "1 0 ");
+
+        let (result, _) = render("{{ big 1 }}{{ big 1 2 }}", Limit::ValueSize, 15);
+        let err = result.unwrap_err();
+        assert_eq!(err.limit(), Some(Limit::ValueSize));
+        assert_eq!(err.span(), Some(Span::new(14, 21)));
+
+        let source =
+            r#"{{ define "loop" }}{{ template "loop" . }}{{ end }}{{ template "loop" . }}"#;
+        let (result, _) = render(source, Limit::Depth, 5);
+        let err = result.unwrap_err();
+        assert_eq!(err.limit(), Some(Limit::Depth));
+        assert_eq!(err.message(), "exceeded maximum template depth (5)");
+
+        // Renders within every limit are unaffected.
+        let (result, output) = render("{{ range .items }}{{ . }}{{ end }}", Limit::Iterations, 3);
+        result.unwrap();
+        assert_eq!(output, "123");
+    }
+
+    #[test]
+    fn cancellation_and_deadlines_stop_renders() {
+        let token = CancellationToken::new();
+        let mut builder = FunctionRegistry::builder();
+        let stop = token.clone();
+        builder.register("stop", move |_ctx, args| {
+            if args.first() == Some(&json!(2)) {
+                stop.cancel();
+            }
+            Ok(json!(""))
+        });
+        builder.register("poll", |ctx, _args| {
+            ctx.check_cancelled()?;
+            Ok(json!("polled"))
+        });
+        let functions = builder.build();
+        let source = "a{{ range .items }}{{ stop . }}{{ . }}{{ end }}z";
+        let tmpl = Template::parse_with_functions("cancel", source, functions.clone()).unwrap();
+        let data = json!({"items": [1, 2, 3]});
+
+        let options = RenderOptions::new().with_cancellation(token.clone());
+        let mut output = Vec::new();
+        let err = tmpl
+            .render_to_with_options(&data, &mut output, &options)
+            .unwrap_err();
+        assert_eq!(err.cancel_reason(), Some(CancelReason::Cancelled));
+        assert_eq!(err.to_string(), "render error: render cancelled");
+        // `{{ stop . }}` finished; the next node saw the cancellation.
+        assert_eq!(err.span(), Some(Span::new(31, 38)));
+        assert_eq!(String::from_utf8(output).unwrap(), "a1");
+        assert!(token.is_cancelled());
+
+        // A cancelled token stops the render before any output, including
+        // from within helpers that check it themselves.
+        let err = tmpl.render_with_options(&data, &options).unwrap_err();
+        assert_eq!(err.span(), Some(Span::new(0, 1)));
+        let polled = Template::parse_with_functions("poll", "{{ poll }}", functions).unwrap();
+        let err = polled
+            .render_with_options(&Value::Null, &options)
+            .unwrap_err();
+        assert_eq!(err.cancel_reason(), Some(CancelReason::Cancelled));
+
+        let past = std::time::Instant::now();
+        let options = RenderOptions::new().with_deadline(past);
+        assert_eq!(options.deadline(), Some(past));
+        let err = tmpl.render_with_options(&data, &options).unwrap_err();
+        assert_eq!(err.cancel_reason(), Some(CancelReason::DeadlineExceeded));
+        assert_eq!(err.message(), "render deadline exceeded");
+
+        let later = past + std::time::Duration::from_secs(3600);
+        let options = RenderOptions::new()
+            .with_deadline(later)
+            .with_cancellation(CancellationToken::new());
+        let plain = Template::parse_str("plain", "{{ range .items }}{{ . }}{{ end }}").unwrap();
+        assert_eq!(plain.render_with_options(&data, &options).unwrap(), "123");
+    }
+
+    #[test]
+    fn registered_methods_are_called_on_matching_data() {
+        let mut builder = FunctionRegistry::builder();
+        builder
+            .register_method("User", "FullName", |_ctx, user, _args| {
+                Ok(json!(format!(
+                    "{} {}",
+                    user["first"].as_str().unwrap(),
+                    user["last"].as_str().unwrap()
+                )))
+            })
+            .register_method("User", "Greet", |_ctx, user, args| {
+                Ok(json!(format!(
+                    "{} {}",
+                    args[0].as_str().unwrap(),
+                    user["first"].as_str().unwrap()
+                )))
+            })
+            .register_method_when(
+                |value| value.get("entries").is_some_and(Value::is_object),
+                "Get",
+                |_ctx, labels, args| {
+                    let key = args[0].as_str().ok_or_else(|| {
+                        Error::render("label key must be a string", None).with_argument(0)
+                    })?;
+                    Ok(labels["entries"].get(key).cloned().unwrap_or(Value::Null))
+                },
+            );
+        let functions = builder.build();
+        let data = json!({
+            "user": {"$type": "User", "first": "Ada", "last": "Lovelace", "FullName": "shadowed"},
+            "pod": {"Labels": {"entries": {"app": "web"}}},
+        });
+        let render = |source: &str| {
+            Template::parse_with_functions("methods", source, functions.clone())
+                .and_then(|tmpl| tmpl.render(&data))
+        };
+
+        assert_eq!(render("{{ .user.FullName }}").unwrap(), "Ada Lovelace");
+        assert_eq!(render(r#"{{ .pod.Labels.Get "app" }}"#).unwrap(), "web");
+        assert_eq!(render(r#"{{ "app" | .pod.Labels.Get }}"#).unwrap(), "web");
+        assert_eq!(
+            render(r#"{{ with .user }}{{ .Greet "hi" }}, {{ (.).Greet "bye" }}{{ end }}"#).unwrap(),
+            "hi Ada, bye Ada"
+        );
+        assert_eq!(
+            render(r#"{{ $u := .user }}{{ $u.Greet "yo" }}"#).unwrap(),
+            "yo Ada"
+        );
+        assert_eq!(render("{{ .user.first }}").unwrap(), "Ada");
+        let go = ParseOptions::new().with_dialect(Dialect::go());
+        let tmpl = Template::parse_with_options(
+            "go",
+            r#"{{ .pod.Labels.Get "app" }} {{ .user.FullName }}"#,
+            functions.clone(),
+            &go,
+        )
+        .unwrap();
+        assert_eq!(tmpl.render(&data).unwrap(), "web Ada Lovelace");
+
+        let source = r#"{{ .pod.Labels.Get 1 }}"#;
+        let err = render(source).unwrap_err();
+        assert_eq!(
+            err.message(),
+            "error calling Get: label key must be a string"
+        );
+        assert_eq!(err.span(), Some(Span::new(19, 20)));
+
+        // Data without a matching method keeps Go's field semantics.
+        let err = render(r#"{{ .pod.Get "app" }}"#).unwrap_err();
+        assert_eq!(
+            err.message(),
+            "arguments supplied to non-function expression"
+        );
+        assert_eq!(render("{{ .pod.FullName }}").unwrap(), "<no value>");
+
+        // Later registrations win.
+        let mut builder = FunctionRegistry::builder();
+        builder.extend(&functions);
+        builder.register_method("User", "FullName", |_ctx, _user, _args| {
+            Ok(json!("override"))
+        });
+        let tmpl =
+            Template::parse_with_functions("methods", "{{ .user.FullName }}", builder.build())
+                .unwrap();
+        assert_eq!(tmpl.render(&data).unwrap(), "override");
+    }
+
+    #[test]
+    fn method_receivers_are_evaluated_once_and_only_for_method_names() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let mut builder = FunctionRegistry::builder();
+        builder
+            .register("user", move |_ctx, _args| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Ok(json!({"$type": "User", "first": "Ada"}))
+            })
+            .register_method("User", "Greet", |_ctx, user, args| {
+                Ok(json!(format!(
+                    "{} {}",
+                    args[0].as_str().unwrap(),
+                    user["first"].as_str().unwrap()
+                )))
+            });
+        let functions = builder.build();
+        let render = |source: &str| {
+            Template::parse_with_functions("methods", source, functions.clone())
+                .and_then(|tmpl| tmpl.render(&json!({})))
+        };
+
+        assert_eq!(render(r#"{{ (user).Greet "hi" }}"#).unwrap(), "hi Ada");
+        assert_eq!(calls.swap(0, Ordering::SeqCst), 1);
+
+        // Names no method is registered under do not evaluate the receiver, so
+        // neither its helpers nor missing keys on the way run before the error.
+        let err = render(r#"{{ (user).Wave "hi" }}"#).unwrap_err();
+        assert_eq!(
+            err.message(),
+            "arguments supplied to non-function expression"
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+        let strict = Template::parse_with_functions(
+            "methods",
+            r#"{{ .missing.Wave "hi" }}"#,
+            functions.clone(),
+        )
+        .unwrap();
+        let options = RenderOptions::new().with_missing_key(MissingKey::Error);
+        let err = strict
+            .render_with_options(&json!({}), &options)
+            .unwrap_err();
+        assert_eq!(
+            err.message(),
+            "arguments supplied to non-function expression"
+        );
+    }
 }
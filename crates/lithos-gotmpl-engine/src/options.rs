@@ -0,0 +1,232 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::error::Error;
+
+/// Controls what happens when a template reads a key that the data does not
+/// contain (Go's `missingkey` option).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MissingKey {
+    /// Go's default: the lookup yields no value, which prints as `<no value>`.
+    #[default]
+    Default,
+    /// The lookup yields the zero value; missing and `null` values print as an
+    /// empty string.
+    Zero,
+    /// Rendering stops with an error naming the missing key.
+    Error,
+}
+
+/// Resources a render can be limited in, for templates from untrusted sources.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Limit {
+    /// Bytes written by text and action nodes, including output that helpers
+    /// capture with [`EvalContext::execute_template`](crate::EvalContext::execute_template).
+    OutputBytes,
+    /// `range` iterations over the whole render.
+    Iterations,
+    /// Nested `template`/`block` invocations. Without a limit, renders stop
+    /// at a depth of 100, far below Go's 100000, because every level uses
+    /// native stack; renders on threads with larger stacks can raise it.
+    Depth,
+    /// Helper function calls over the whole render.
+    HelperCalls,
+    /// Size of a single value returned by a helper: the bytes of its strings
+    /// and object keys plus one unit per other scalar, summed over nested
+    /// arrays and objects.
+    ValueSize,
+    /// Async helper calls awaited by
+    /// [`Template::render_async`](crate::Template::render_async), each of
+    /// which costs another pass over the template. Without a limit, renders
+    /// stop after 1000.
+    AsyncCalls,
+}
+
+impl Limit {
+    const COUNT: usize = 6;
+
+    fn index(self) -> usize {
+        match self {
+            Limit::OutputBytes => 0,
+            Limit::Iterations => 1,
+            Limit::Depth => 2,
+            Limit::HelperCalls => 3,
+            Limit::ValueSize => 4,
+            Limit::AsyncCalls => 5,
+        }
+    }
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Limit::OutputBytes => "output size",
+            Limit::Iterations => "loop iterations",
+            Limit::Depth => "template depth",
+            Limit::HelperCalls => "helper calls",
+            Limit::ValueSize => "value size",
+            Limit::AsyncCalls => "async helper calls",
+        })
+    }
+}
+
+/// Why a render was stopped before it finished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CancelReason {
+    /// The render's [`CancellationToken`] was cancelled.
+    Cancelled,
+    /// The deadline set with [`RenderOptions::with_deadline`] passed.
+    DeadlineExceeded,
+}
+
+impl fmt::Display for CancelReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CancelReason::Cancelled => "render cancelled",
+            CancelReason::DeadlineExceeded => "render deadline exceeded",
+        })
+    }
+}
+
+/// Shared flag that stops renders from another thread. Clones observe the
+/// same flag; once cancelled, a token stays cancelled.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Creates a token that is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Asks every render holding this token to stop at its next check.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Reports whether [`CancellationToken::cancel`] was called.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Execution options applied when rendering a template.
+#[derive(Debug, Clone, Default)]
+pub struct RenderOptions {
+    missing_key: MissingKey,
+    limits: [Option<usize>; Limit::COUNT],
+    cancellation: Option<CancellationToken>,
+    deadline: Option<Instant>,
+}
+
+impl RenderOptions {
+    /// Creates options matching Go's defaults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the behaviour for missing keys.
+    pub fn with_missing_key(mut self, missing_key: MissingKey) -> Self {
+        self.missing_key = missing_key;
+        self
+    }
+
+    /// Applies a Go-style option string such as `missingkey=error`, as accepted
+    /// by `Template.Option`.
+    pub fn with_option(mut self, option: &str) -> Result<Self, Error> {
+        self.missing_key = match option.split_once('=') {
+            Some(("missingkey", "invalid" | "default")) => MissingKey::Default,
+            Some(("missingkey", "zero")) => MissingKey::Zero,
+            Some(("missingkey", "error")) => MissingKey::Error,
+            _ => {
+                return Err(Error::render(
+                    format!("unrecognized option: {option}"),
+                    None,
+                ))
+            }
+        };
+        Ok(self)
+    }
+
+    /// Returns the configured behaviour for missing keys.
+    pub fn missing_key(&self) -> MissingKey {
+        self.missing_key
+    }
+
+    /// Caps `limit` at `max`; exceeding it stops the render with
+    /// [`Error::LimitExceeded`].
+    pub fn with_limit(mut self, limit: Limit, max: usize) -> Self {
+        self.limits[limit.index()] = Some(max);
+        self
+    }
+
+    /// Returns the configured maximum for `limit`, if any.
+    pub fn limit(&self, limit: Limit) -> Option<usize> {
+        self.limits[limit.index()]
+    }
+
+    /// Stops the render with [`Error::Cancelled`] once `token` is cancelled.
+    /// The evaluator checks before each node, loop iteration and helper call.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Returns the cancellation token, if any.
+    pub fn cancellation(&self) -> Option<&CancellationToken> {
+        self.cancellation.as_ref()
+    }
+
+    /// Stops the render with [`Error::Cancelled`] once `deadline` has passed,
+    /// checked at the same points as the cancellation token.
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Returns the deadline, if any.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Reports why a render with these options must stop now, if it must.
+    pub(crate) fn cancel_reason(&self) -> Option<CancelReason> {
+        if self
+            .cancellation
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+        {
+            return Some(CancelReason::Cancelled);
+        }
+        match self.deadline {
+            Some(deadline) if Instant::now() >= deadline => Some(CancelReason::DeadlineExceeded),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_go_option_strings() {
+        let options = RenderOptions::new()
+            .with_option("missingkey=error")
+            .unwrap();
+        assert_eq!(options.missing_key(), MissingKey::Error);
+        let options = options.with_option("missingkey=invalid").unwrap();
+        assert_eq!(options.missing_key(), MissingKey::Default);
+        let options = options.with_option("missingkey=zero").unwrap();
+        assert_eq!(options.missing_key(), MissingKey::Zero);
+
+        for bad in ["missingkey=bogus", "missingkey", "other=1"] {
+            let err = RenderOptions::new().with_option(bad).unwrap_err();
+            assert!(err.to_string().contains("unrecognized option"), "{bad}");
+        }
+    }
+}
@@ -0,0 +1,38 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+use std::{fmt, io};
+
+use crate::error::Error;
+
+/// Destination the renderer streams text and action output into.
+pub(crate) trait Output {
+    fn write_str(&mut self, text: &str) -> Result<(), Error>;
+}
+
+impl Output for String {
+    fn write_str(&mut self, text: &str) -> Result<(), Error> {
+        self.push_str(text);
+        Ok(())
+    }
+}
+
+/// Adapts an [`io::Write`]; every fragment is written as soon as it is produced.
+pub(crate) struct IoOutput<W>(pub(crate) W);
+
+impl<W: io::Write> Output for IoOutput<W> {
+    fn write_str(&mut self, text: &str) -> Result<(), Error> {
+        self.0
+            .write_all(text.as_bytes())
+            .map_err(|err| Error::write(err, None))
+    }
+}
+
+/// Adapts a [`fmt::Write`].
+pub(crate) struct FmtOutput<W>(pub(crate) W);
+
+impl<W: fmt::Write> Output for FmtOutput<W> {
+    fn write_str(&mut self, text: &str) -> Result<(), Error> {
+        self.0
+            .write_str(text)
+            .map_err(|err| Error::write(err, None))
+    }
+}
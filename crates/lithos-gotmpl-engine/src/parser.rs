@@ -1,25 +1,163 @@
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 use crate::ast::{
-    ActionNode, Ast, BindingKind, Block, Command, CommentNode, Expression, IfNode, Node, Pipeline,
-    PipelineDeclarations, RangeNode, Span, TextNode, WithNode,
+    ActionNode, ActionSyntax, Ast, BindingKind, Block, BlockNode, BreakNode, Command, CommentNode,
+    ContinueNode, ControlSyntax, DefineNode, Delimiters, Expression, IfNode, Node, Pipeline,
+    PipelineDeclarations, RangeNode, Span, TemplateNode, TextNode, WithNode,
 };
+use crate::dialect::{Dialect, GoVersion};
 use crate::error::Error;
 use crate::lexer;
 use crate::lexer::{Keyword, Operator, Token, TokenKind};
 
+/// Parses template source with Go's default options.
+#[cfg(test)]
+pub fn parse_template(name: &str, source: &str) -> Result<Ast, Error> {
+    parse_template_with_options(name, source, &ParseOptions::default())
+}
+
+/// Settings that influence how template source is parsed.
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    delimiters: Delimiters,
+    dialect: Dialect,
+    function_keywords: Vec<Keyword>,
+}
+
+impl ParseOptions {
+    /// Creates options matching Go's defaults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Uses `left` and `right` as action delimiters, like Go's `Delims`. Empty
+    /// strings select the default `{{`/`}}`.
+    pub fn with_delimiters(mut self, left: impl Into<String>, right: impl Into<String>) -> Self {
+        self.delimiters = Delimiters::new(left, right);
+        self
+    }
+
+    /// Returns the configured action delimiters.
+    pub fn delimiters(&self) -> &Delimiters {
+        &self.delimiters
+    }
+
+    /// Selects the syntax profile, e.g. [`Dialect::Go`] to reject Lithos extensions.
+    pub fn with_dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Returns the configured dialect.
+    pub fn dialect(&self) -> Dialect {
+        self.dialect
+    }
+
+    /// Treats `keyword` as a function name. Like Go, `break` and `continue`
+    /// lose their keyword meaning when a function of that name is registered.
+    pub(crate) fn with_function_keyword(mut self, keyword: Keyword) -> Self {
+        self.function_keywords.push(keyword);
+        self
+    }
+}
+
 /// Primary entry point for parsing template sources.
 ///
 /// The parser walks the input once, splitting it into literal text and action
 /// blocks. To mimic Go's `text/template` behaviour we keep two stacks:
 ///
-/// - `control_stack` stores the open `if`/`range`/`with` frames so we can
-///   populate their bodies when a matching `{{end}}` is seen.
+/// - `control_stack` stores the open `if`/`range`/`with`/`define`/`block`
+///   frames so we can populate their bodies when a matching `{{end}}` is seen.
 /// - `target_stack` tracks where the next node should be appended (root block,
 ///   current `then` block, or current `else` block).
 ///
 /// Keeping the structure explicit helps when trimming whitespace and recording
 /// byte spans.
-pub fn parse_template(name: &str, source: &str) -> Result<Ast, Error> {
+pub fn parse_template_with_options(
+    name: &str,
+    source: &str,
+    options: &ParseOptions,
+) -> Result<Ast, Error> {
+    parse_source(name, source, options, &mut Diagnostics::Fail)
+}
+
+/// Result of [`parse_template_recovering`]: the tree built from every action
+/// that parsed, plus one diagnostic per syntax error.
+#[derive(Debug)]
+pub struct RecoveredParse {
+    pub ast: Ast,
+    pub diagnostics: Vec<Error>,
+}
+
+impl RecoveredParse {
+    /// Reports whether the source parsed without errors.
+    pub fn is_ok(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+}
+
+/// Parses like [`Template::parse_with_options`](crate::Template::parse_with_options)
+/// but keeps going after syntax errors so lint tools can report every problem
+/// in one run.
+///
+/// A malformed action is reported and skipped, resuming at the next action.
+/// Control structures whose clause fails to parse are still opened so their
+/// `{{end}}` does not cascade into further errors, and structures left open at
+/// the end of the source are reported and closed there. The resulting tree is
+/// partial whenever diagnostics were reported and should not be rendered.
+pub fn parse_template_recovering(
+    name: &str,
+    source: &str,
+    options: &ParseOptions,
+) -> RecoveredParse {
+    let mut diagnostics = Diagnostics::Collect(Vec::new());
+    let ast = parse_source(name, source, options, &mut diagnostics)
+        .expect("recovering parse reports errors as diagnostics");
+    let Diagnostics::Collect(diagnostics) = diagnostics else {
+        unreachable!("diagnostics mode does not change");
+    };
+    RecoveredParse { ast, diagnostics }
+}
+
+/// Where syntax errors go: the strict parser stops at the first one, the
+/// recovering parser collects them all.
+enum Diagnostics {
+    Fail,
+    Collect(Vec<Error>),
+}
+
+impl Diagnostics {
+    fn report(&mut self, err: Error) -> Result<(), Error> {
+        match self {
+            Diagnostics::Fail => Err(err),
+            Diagnostics::Collect(errors) => {
+                errors.push(err);
+                Ok(())
+            }
+        }
+    }
+
+    /// Reports a failed `result` and continues with `fallback`.
+    fn recover<T>(
+        &mut self,
+        result: Result<T, Error>,
+        fallback: impl FnOnce() -> T,
+    ) -> Result<T, Error> {
+        result.or_else(|err| self.report(err).map(|()| fallback()))
+    }
+}
+
+fn empty_pipeline() -> Pipeline {
+    Pipeline::new(None, Vec::new())
+}
+
+fn parse_source(
+    name: &str,
+    source: &str,
+    options: &ParseOptions,
+    diagnostics: &mut Diagnostics,
+) -> Result<Ast, Error> {
+    let left = options.delimiters.left.as_bytes();
+    let right = options.delimiters.right.as_bytes();
     let mut root = Block::default();
     let mut cursor = 0usize;
     let bytes = source.as_bytes();
@@ -27,7 +165,7 @@ pub fn parse_template(name: &str, source: &str) -> Result<Ast, Error> {
     let mut target_stack: Vec<AppendTarget> = vec![AppendTarget::Root];
 
     while cursor < bytes.len() {
-        let Some(open) = find_action_start(bytes, cursor) else {
+        let Some(open) = find_action_start(bytes, cursor, left) else {
             let text = &source[cursor..];
             if !text.is_empty() {
                 push_node(
@@ -55,133 +193,83 @@ pub fn parse_template(name: &str, source: &str) -> Result<Ast, Error> {
             }
         }
 
-        match find_action_end(bytes, open + 2) {
+        match find_action_end(bytes, open + left.len(), right) {
             Some(close) => {
-                let window = trim_action_delimiters(source, bytes, open, close);
+                let window =
+                    trim_action_delimiters(source, bytes, (open, left.len()), (close, right.len()));
 
-                if window.trim_left {
+                let trimmed_before = if window.trim_left {
                     let block = current_block_mut(&mut root, &mut control_stack, &target_stack);
-                    trim_trailing_whitespace(block);
-                }
+                    trim_trailing_whitespace(block)
+                } else {
+                    String::new()
+                };
+                let next = close + right.len();
+                let resume = if window.trim_right {
+                    skip_leading_whitespace(bytes, next)
+                } else {
+                    next
+                };
+                let syntax = ActionSyntax {
+                    trim_left: window.trim_left,
+                    trim_right: window.trim_right,
+                    leading: source
+                        [open + left.len() + usize::from(window.trim_left)..window.body_start]
+                        .to_string(),
+                    body: window.body.to_string(),
+                    trailing: source[window.body_start + window.body.len()
+                        ..close - usize::from(window.trim_right)]
+                        .to_string(),
+                    trimmed_before,
+                    trimmed_after: source[next..resume].to_string(),
+                };
 
                 if is_potential_comment(window.body) && !window.body.ends_with("*/") {
-                    return Err(Error::parse_with_span("unclosed comment", window.span));
-                }
-
-                if is_comment(window.body) {
+                    diagnostics.report(Error::parse_with_span("unclosed comment", window.span))?;
+                } else if is_comment(window.body) {
                     push_node(
                         &mut root,
                         &mut control_stack,
                         &target_stack,
-                        Node::Comment(CommentNode::new(
-                            window.span,
-                            strip_comment(window.body),
-                            window.trim_left,
-                            window.trim_right,
-                        )),
-                    );
-                } else {
-                    let tokens = lexer::lex_action(window.body, window.body_start)?;
-
-                    if tokens.is_empty() {
-                        return Err(Error::parse_with_span("empty action", window.span));
-                    }
-
-                    match classify_action(&tokens)? {
-                        ActionKind::If => {
-                            let condition_tokens: Vec<_> = tokens[1..].to_vec();
-                            let condition_pipeline = parse_action_pipeline(&condition_tokens)?;
-                            let frame = ControlFrame::new(
-                                ControlKind::If,
+                        Node::Comment(
+                            CommentNode::new(
                                 window.span,
-                                condition_tokens,
-                                condition_pipeline,
-                            );
-                            push_control_frame(&mut control_stack, &mut target_stack, frame);
-                        }
-                        ActionKind::Range => {
-                            let condition_tokens: Vec<_> = tokens[1..].to_vec();
-                            let condition_pipeline = parse_action_pipeline(&condition_tokens)?;
-                            let frame = ControlFrame::new(
-                                ControlKind::Range,
-                                window.span,
-                                condition_tokens,
-                                condition_pipeline,
-                            );
-                            push_control_frame(&mut control_stack, &mut target_stack, frame);
-                        }
-                        ActionKind::With => {
-                            let condition_tokens: Vec<_> = tokens[1..].to_vec();
-                            let condition_pipeline = parse_action_pipeline(&condition_tokens)?;
-                            let frame = ControlFrame::new(
-                                ControlKind::With,
-                                window.span,
-                                condition_tokens,
-                                condition_pipeline,
-                            );
-                            push_control_frame(&mut control_stack, &mut target_stack, frame);
-                        }
-                        ActionKind::Else => {
-                            handle_else(&mut control_stack, &mut target_stack, window.span)?;
-                        }
-                        ActionKind::ElseIf => {
-                            if tokens.len() < 3 {
-                                return Err(Error::parse_with_span(
-                                    "else-if requires a condition",
-                                    window.span,
-                                ));
-                            }
-                            let condition_tokens: Vec<_> = tokens[2..].to_vec();
-                            let condition_pipeline = parse_action_pipeline(&condition_tokens)?;
-                            handle_else_if(
-                                &mut control_stack,
-                                &mut target_stack,
-                                window.span,
-                                condition_tokens,
-                                condition_pipeline,
-                            )?;
-                        }
-                        ActionKind::End => {
-                            close_control_frame(
-                                &mut root,
-                                &mut control_stack,
-                                &mut target_stack,
-                                window.span,
-                            )?;
-                        }
-                        ActionKind::Regular => {
-                            let pipeline = parse_action_pipeline(&tokens)?;
-                            let node = build_action_node(
-                                window.span,
-                                window.body,
-                                tokens,
-                                pipeline,
+                                strip_comment(window.body),
                                 window.trim_left,
                                 window.trim_right,
-                            );
-                            push_node(&mut root, &mut control_stack, &target_stack, node);
-                        }
-                    }
+                            )
+                            .with_syntax(syntax),
+                        ),
+                    );
+                } else if let Err(err) = parse_action(
+                    &window,
+                    syntax,
+                    options,
+                    &mut root,
+                    &mut control_stack,
+                    &mut target_stack,
+                    diagnostics,
+                ) {
+                    diagnostics.report(err)?;
                 }
 
-                cursor = close + 2;
-                if window.trim_right {
-                    cursor = skip_leading_whitespace(bytes, cursor);
-                }
+                cursor = resume;
             }
             None => {
-                let mut remainder = &source[open + 2..];
+                let mut remainder = &source[open + left.len()..];
                 remainder = remainder.trim_start();
                 if let Some(rest) = remainder.strip_prefix('-') {
                     remainder = rest.trim_start();
                 }
 
                 let span = Span::new(open, source.len());
-                if remainder.starts_with("/*") {
-                    return Err(Error::parse_with_span("unclosed comment", span));
-                }
-
-                return Err(Error::parse_with_span("unclosed action", span));
+                let message = if remainder.starts_with("/*") {
+                    "unclosed comment"
+                } else {
+                    "unclosed action"
+                };
+                diagnostics.report(Error::parse_with_span(message, span))?;
+                break;
             }
         }
     }
@@ -195,46 +283,213 @@ pub fn parse_template(name: &str, source: &str) -> Result<Ast, Error> {
         );
     }
 
-    if let Some(frame) = control_stack.last() {
-        return Err(Error::parse(
+    while let Some(frame) = control_stack.last() {
+        diagnostics.report(Error::parse(
             "unterminated control structure",
             Some(frame.start_span),
-        ));
+        ))?;
+        let eof = Span::new(source.len(), source.len());
+        let end = ActionSyntax::new("end");
+        close_control_frame(&mut root, &mut control_stack, &mut target_stack, eof, end)?;
+    }
+
+    Ok(Ast::new(name, root)
+        .with_delimiters(options.delimiters.clone())
+        .with_dialect(options.dialect))
+}
+
+/// Parses one non-comment action and applies it to the tree under
+/// construction. Errors that leave the action unusable are returned; errors in
+/// a control clause are reported and the structure is opened regardless.
+fn parse_action(
+    window: &ActionWindow<'_>,
+    syntax: ActionSyntax,
+    options: &ParseOptions,
+    root: &mut Block,
+    control_stack: &mut Vec<ControlFrame>,
+    target_stack: &mut Vec<AppendTarget>,
+    diagnostics: &mut Diagnostics,
+) -> Result<(), Error> {
+    let tokens = lexer::lex_action(window.body, window.body_start)?;
+
+    if tokens.is_empty() {
+        return Err(Error::parse_with_span("empty action", window.span));
+    }
+
+    if options.dialect.is_go() {
+        check_go_tokens(&tokens, options)?;
+    }
+
+    match classify_action(&tokens, options)? {
+        kind @ (ActionKind::If | ActionKind::Range | ActionKind::With) => {
+            let (kind, keyword) = match kind {
+                ActionKind::If => (ControlKind::If, "if"),
+                ActionKind::Range => (ControlKind::Range, "range"),
+                _ => (ControlKind::With, "with"),
+            };
+            let condition_tokens: Vec<_> = tokens[1..].to_vec();
+            let condition_pipeline = if condition_tokens.is_empty() {
+                diagnostics.report(Error::parse_with_span(
+                    format!("{keyword} requires a pipeline"),
+                    tokens[0].span,
+                ))?;
+                empty_pipeline()
+            } else {
+                diagnostics.recover(parse_action_pipeline(&condition_tokens), empty_pipeline)?
+            };
+            let frame = ControlFrame::new(kind, window.span, condition_tokens, condition_pipeline)
+                .with_open(syntax);
+            push_control_frame(control_stack, target_stack, frame);
+        }
+        ActionKind::Define => {
+            if !control_stack.is_empty() {
+                diagnostics.report(Error::parse_with_span(
+                    "define is only allowed at the top level",
+                    window.span,
+                ))?;
+            }
+            let name = diagnostics
+                .recover(parse_definition_name(&tokens, "define clause"), String::new)?;
+            if let Some(extra) = tokens.get(2) {
+                diagnostics.report(Error::parse(
+                    format!("unexpected {:?} in define clause", extra.kind),
+                    Some(extra.span),
+                ))?;
+            }
+            let frame = ControlFrame::definition(
+                ControlKind::Define(name),
+                window.span,
+                tokens[1..].to_vec(),
+                None,
+            )
+            .with_open(syntax);
+            push_control_frame(control_stack, target_stack, frame);
+        }
+        ActionKind::Block => {
+            let name =
+                diagnostics.recover(parse_definition_name(&tokens, "block clause"), String::new)?;
+            let pipeline = if tokens.len() < 3 {
+                diagnostics.report(Error::parse_with_span(
+                    "missing value for block clause",
+                    window.span,
+                ))?;
+                empty_pipeline()
+            } else {
+                diagnostics.recover(parse_action_pipeline(&tokens[2..]), empty_pipeline)?
+            };
+            let frame = ControlFrame::definition(
+                ControlKind::Block(name),
+                window.span,
+                tokens[1..].to_vec(),
+                Some(pipeline),
+            )
+            .with_open(syntax);
+            push_control_frame(control_stack, target_stack, frame);
+        }
+        ActionKind::Template => {
+            let name = parse_definition_name(&tokens, "template clause")?;
+            let pipeline = match tokens.get(2..) {
+                Some(rest) if !rest.is_empty() => Some(parse_action_pipeline(rest)?),
+                _ => None,
+            };
+            let invocation_tokens: Vec<_> = tokens[1..].to_vec();
+            let node = Node::Template(
+                TemplateNode::new(window.span, invocation_tokens, name, pipeline)
+                    .with_syntax(syntax),
+            );
+            push_node(root, control_stack, target_stack, node);
+        }
+        ActionKind::Else => {
+            handle_else(control_stack, target_stack, window.span, syntax)?;
+        }
+        ActionKind::ElseIf => {
+            if tokens.len() < 3 {
+                return Err(Error::parse_with_span(
+                    "else-if requires a condition",
+                    window.span,
+                ));
+            }
+            let condition_tokens: Vec<_> = tokens[2..].to_vec();
+            let condition_pipeline = parse_action_pipeline(&condition_tokens)?;
+            handle_else_if(
+                control_stack,
+                target_stack,
+                window.span,
+                condition_tokens,
+                condition_pipeline,
+                syntax,
+            )?;
+        }
+        ActionKind::Break => {
+            ensure_inside_range(control_stack, target_stack, "{{break}}", window.span)?;
+            let node = Node::Break(BreakNode::new(window.span).with_syntax(syntax));
+            push_node(root, control_stack, target_stack, node);
+        }
+        ActionKind::Continue => {
+            ensure_inside_range(control_stack, target_stack, "{{continue}}", window.span)?;
+            let node = Node::Continue(ContinueNode::new(window.span).with_syntax(syntax));
+            push_node(root, control_stack, target_stack, node);
+        }
+        ActionKind::End => {
+            if let Some(extra) = tokens.get(1) {
+                diagnostics.report(Error::parse(
+                    "unexpected tokens after end",
+                    Some(extra.span),
+                ))?;
+            }
+            close_control_frame(root, control_stack, target_stack, window.span, syntax)?;
+        }
+        ActionKind::Regular => {
+            let pipeline = parse_action_pipeline(&tokens)?;
+            let node = build_action_node(window, tokens, pipeline, syntax);
+            push_node(root, control_stack, target_stack, node);
+        }
     }
 
-    Ok(Ast::new(name, root))
+    Ok(())
 }
 
 #[derive(Debug, Clone, Copy)]
-struct ActionWindow<'a> {
-    span: Span,
-    body_start: usize,
-    body: &'a str,
-    trim_left: bool,
-    trim_right: bool,
+pub(crate) struct ActionWindow<'a> {
+    pub(crate) span: Span,
+    pub(crate) body_start: usize,
+    pub(crate) body: &'a str,
+    pub(crate) trim_left: bool,
+    pub(crate) trim_right: bool,
 }
 
-fn trim_action_delimiters<'a>(
+/// Splits an action into its body and trim flags. `open` and `close` are the
+/// offsets and lengths of the left and right delimiters.
+pub(crate) fn trim_action_delimiters<'a>(
     source: &'a str,
     bytes: &[u8],
-    open: usize,
-    close: usize,
+    (open, left_len): (usize, usize),
+    (close, right_len): (usize, usize),
 ) -> ActionWindow<'a> {
-    let mut body_start = open + 2;
+    let mut body_start = open + left_len;
     let mut body_end = close;
     let mut trim_left = false;
     let mut trim_right = false;
 
-    if body_start < close && bytes[body_start] == b'-' {
+    // As in Go, a trim marker must be separated from the body by whitespace:
+    // `{{-3}}` is a negative number and `{{ 3-}}` is not trimmed.
+    let is_space = |byte: &u8| matches!(byte, b' ' | b'\t' | b'\r' | b'\n');
+    if body_start + 1 < close
+        && bytes[body_start] == b'-'
+        && bytes.get(body_start + 1).is_some_and(is_space)
+    {
         trim_left = true;
         body_start += 1;
     }
-    if body_start < body_end && bytes[body_end - 1] == b'-' {
+    if body_start + 1 < body_end
+        && bytes[body_end - 1] == b'-'
+        && bytes.get(body_end - 2).is_some_and(is_space)
+    {
         trim_right = true;
         body_end -= 1;
     }
 
-    let span = Span::new(open, close + 2);
+    let span = Span::new(open, close + right_len);
     let raw = &source[body_start..body_end];
     let trimmed_start = raw.trim_start();
     let prefix_len = raw.len() - trimmed_start.len();
@@ -250,45 +505,85 @@ fn trim_action_delimiters<'a>(
     }
 }
 
+/// Rejects the Lithos syntax extensions Go does not accept: infix comparison
+/// operators, keywords in operand position, and punctuation Go's lexer refuses.
+fn check_go_tokens(tokens: &[Token], options: &ParseOptions) -> Result<(), Error> {
+    let else_if = matches!(
+        tokens.first().map(|token| &token.kind),
+        Some(TokenKind::Keyword(Keyword::Else))
+    );
+    // Commas only separate the variables of a range declaration.
+    let declaration = tokens
+        .iter()
+        .position(|token| matches!(token.kind, TokenKind::Declare | TokenKind::Assign));
+    for (idx, token) in tokens.iter().enumerate() {
+        match &token.kind {
+            TokenKind::Operator(op) => {
+                return Err(Error::parse_with_span(
+                    format!("unexpected {:?} in operand", op.as_str()),
+                    token.span,
+                ));
+            }
+            TokenKind::Comma if declaration.is_some_and(|end| idx < end) => {}
+            TokenKind::Comma
+            | TokenKind::Colon
+            | TokenKind::LeftBracket
+            | TokenKind::RightBracket => {
+                return Err(Error::parse_with_span(
+                    "unexpected character in action",
+                    token.span,
+                ));
+            }
+            TokenKind::Keyword(Keyword::Nil | Keyword::True | Keyword::False) => {}
+            TokenKind::Keyword(keyword) => {
+                let leading = idx == 0 || (idx == 1 && else_if && *keyword == Keyword::If);
+                if !leading && !options.function_keywords.contains(keyword) {
+                    return Err(Error::parse_with_span(
+                        format!("unexpected <{}> in operand", keyword.as_str()),
+                        token.span,
+                    ));
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum ActionKind {
     If,
     Range,
     With,
+    Define,
+    Block,
+    Template,
+    Break,
+    Continue,
     Else,
     ElseIf,
     End,
     Regular,
 }
 
-fn classify_action(tokens: &[Token]) -> Result<ActionKind, Error> {
+fn classify_action(tokens: &[Token], options: &ParseOptions) -> Result<ActionKind, Error> {
     let first = tokens
         .first()
         .ok_or_else(|| Error::parse("empty action", None))?;
     match &first.kind {
-        TokenKind::Keyword(Keyword::If) => {
-            if tokens.len() < 2 {
-                return Err(Error::parse_with_span("if requires a pipeline", first.span));
-            }
-            Ok(ActionKind::If)
-        }
-        TokenKind::Keyword(Keyword::Range) => {
-            if tokens.len() < 2 {
-                return Err(Error::parse_with_span(
-                    "range requires a pipeline",
-                    first.span,
-                ));
-            }
-            Ok(ActionKind::Range)
-        }
-        TokenKind::Keyword(Keyword::With) => {
+        TokenKind::Keyword(Keyword::If) => Ok(ActionKind::If),
+        TokenKind::Keyword(Keyword::Range) => Ok(ActionKind::Range),
+        TokenKind::Keyword(Keyword::With) => Ok(ActionKind::With),
+        TokenKind::Keyword(Keyword::Define) => Ok(ActionKind::Define),
+        TokenKind::Keyword(Keyword::Block) => Ok(ActionKind::Block),
+        TokenKind::Keyword(Keyword::Template) => {
             if tokens.len() < 2 {
                 return Err(Error::parse_with_span(
-                    "with requires a pipeline",
+                    "template requires a name",
                     first.span,
                 ));
             }
-            Ok(ActionKind::With)
+            Ok(ActionKind::Template)
         }
         TokenKind::Keyword(Keyword::Else) => {
             if tokens.len() == 1 {
@@ -305,15 +600,32 @@ fn classify_action(tokens: &[Token]) -> Result<ActionKind, Error> {
                 ))
             }
         }
-        TokenKind::Keyword(Keyword::End) => {
-            if tokens.len() > 1 {
+        TokenKind::Keyword(keyword @ (Keyword::Break | Keyword::Continue)) => {
+            if options.function_keywords.contains(keyword) {
+                return Ok(ActionKind::Regular);
+            }
+            if !options.dialect.supports(GoVersion::GO1_18) {
+                return Err(Error::parse_with_span(
+                    format!("function {:?} not defined", keyword.as_str()),
+                    first.span,
+                ));
+            }
+            if let Some(extra) = tokens.get(1) {
                 return Err(Error::parse(
-                    "unexpected tokens after end",
-                    Some(tokens[1].span),
+                    format!(
+                        "unexpected {:?} in {{{{{}}}}}",
+                        extra.kind,
+                        keyword.as_str()
+                    ),
+                    Some(extra.span),
                 ));
             }
-            Ok(ActionKind::End)
+            Ok(match keyword {
+                Keyword::Break => ActionKind::Break,
+                _ => ActionKind::Continue,
+            })
         }
+        TokenKind::Keyword(Keyword::End) => Ok(ActionKind::End),
         _ => Ok(ActionKind::Regular),
     }
 }
@@ -331,14 +643,24 @@ struct ControlFrame {
     kind: ControlKind,
     start_span: Span,
     tokens: Vec<Token>,
-    pipeline: Pipeline,
+    pipeline: Option<Pipeline>,
     then_block: Block,
     else_if_branches: Vec<ElseIfData>,
     else_block: Option<Block>,
+    syntax: ControlSyntax,
 }
 
 impl ControlFrame {
     fn new(kind: ControlKind, span: Span, tokens: Vec<Token>, pipeline: Pipeline) -> Self {
+        Self::definition(kind, span, tokens, Some(pipeline))
+    }
+
+    fn definition(
+        kind: ControlKind,
+        span: Span,
+        tokens: Vec<Token>,
+        pipeline: Option<Pipeline>,
+    ) -> Self {
         Self {
             kind,
             start_span: span,
@@ -347,8 +669,21 @@ impl ControlFrame {
             then_block: Block::default(),
             else_if_branches: Vec::new(),
             else_block: None,
+            syntax: ControlSyntax::default(),
         }
     }
+
+    fn with_open(mut self, syntax: ActionSyntax) -> Self {
+        self.syntax.open = syntax;
+        self
+    }
+
+    fn accepts_else(&self) -> bool {
+        matches!(
+            self.kind,
+            ControlKind::If | ControlKind::Range | ControlKind::With
+        )
+    }
 }
 
 #[derive(Debug)]
@@ -357,6 +692,7 @@ struct ElseIfData {
     tokens: Vec<Token>,
     pipeline: Pipeline,
     block: Block,
+    syntax: ActionSyntax,
 }
 
 #[derive(Debug)]
@@ -364,6 +700,8 @@ enum ControlKind {
     If,
     Range,
     With,
+    Define(String),
+    Block(String),
 }
 
 fn current_block_mut<'a>(
@@ -386,18 +724,41 @@ fn current_block_mut<'a>(
     }
 }
 
-fn trim_trailing_whitespace(block: &mut Block) {
-    if let Some(Node::Text(text)) = block.nodes.last_mut() {
-        while text
-            .text
-            .chars()
-            .last()
-            .map(|ch| matches!(ch, ' ' | '\t' | '\n' | '\r'))
-            .unwrap_or(false)
-        {
-            text.text.pop();
+/// Rejects `{{break}}`/`{{continue}}` unless the current append target sits in
+/// a `range` body. Like Go, the `else` branch of a range does not count, and
+/// definitions start a fresh context.
+fn ensure_inside_range(
+    controls: &[ControlFrame],
+    targets: &[AppendTarget],
+    keyword: &str,
+    span: Span,
+) -> Result<(), Error> {
+    for target in targets.iter().rev() {
+        let (idx, in_then) = match *target {
+            AppendTarget::Root => break,
+            AppendTarget::Then(idx) => (idx, true),
+            AppendTarget::Else(idx) | AppendTarget::ElseIf(idx, _) => (idx, false),
+        };
+        match controls[idx].kind {
+            ControlKind::Range if in_then => return Ok(()),
+            ControlKind::Define(_) | ControlKind::Block(_) => break,
+            _ => {}
         }
     }
+    Err(Error::parse_with_span(
+        format!("{keyword} outside {{{{range}}}}"),
+        span,
+    ))
+}
+
+/// Strips trailing whitespace from the block's last text node and returns
+/// what was removed.
+fn trim_trailing_whitespace(block: &mut Block) -> String {
+    let Some(Node::Text(text)) = block.nodes.last_mut() else {
+        return String::new();
+    };
+    let kept = text.text.trim_end_matches([' ', '\t', '\n', '\r']).len();
+    text.text.split_off(kept)
 }
 
 fn skip_leading_whitespace(bytes: &[u8], mut index: usize) -> usize {
@@ -418,21 +779,22 @@ fn push_node(
 }
 
 fn build_action_node(
-    span: Span,
-    body: &str,
+    window: &ActionWindow<'_>,
     tokens: Vec<Token>,
     pipeline: Pipeline,
-    trim_left: bool,
-    trim_right: bool,
+    syntax: ActionSyntax,
 ) -> Node {
-    Node::Action(ActionNode::new(
-        span,
-        body.to_string(),
-        tokens,
-        pipeline,
-        trim_left,
-        trim_right,
-    ))
+    Node::Action(
+        ActionNode::new(
+            window.span,
+            window.body,
+            tokens,
+            pipeline,
+            window.trim_left,
+            window.trim_right,
+        )
+        .with_syntax(syntax),
+    )
 }
 
 fn push_control_frame(
@@ -449,6 +811,7 @@ fn handle_else(
     controls: &mut [ControlFrame],
     targets: &mut [AppendTarget],
     span: Span,
+    syntax: ActionSyntax,
 ) -> Result<(), Error> {
     if targets.len() <= 1 {
         return Err(Error::parse_with_span("unexpected else", span));
@@ -471,11 +834,16 @@ fn handle_else(
         .get_mut(idx)
         .ok_or_else(|| Error::parse_with_span("mismatched else", span))?;
 
+    if !frame.accepts_else() {
+        return Err(Error::parse_with_span("unexpected else", span));
+    }
+
     if frame.else_block.is_some() {
         return Err(Error::parse_with_span("multiple else blocks", span));
     }
 
     frame.else_block = Some(Block::default());
+    frame.syntax.else_action = Some(syntax);
     *current = AppendTarget::Else(idx);
     Ok(())
 }
@@ -486,6 +854,7 @@ fn handle_else_if(
     span: Span,
     tokens: Vec<Token>,
     pipeline: Pipeline,
+    syntax: ActionSyntax,
 ) -> Result<(), Error> {
     if targets.len() <= 1 {
         return Err(Error::parse_with_span("unexpected else-if", span));
@@ -525,6 +894,7 @@ fn handle_else_if(
         tokens,
         pipeline,
         block: Block::default(),
+        syntax,
     });
 
     *current = AppendTarget::ElseIf(frame_idx, branch_idx);
@@ -537,9 +907,11 @@ fn close_control_frame(
     controls: &mut Vec<ControlFrame>,
     targets: &mut Vec<AppendTarget>,
     span: Span,
+    end: ActionSyntax,
 ) -> Result<(), Error> {
     let top = targets
-        .pop()
+        .last()
+        .copied()
         .ok_or_else(|| Error::parse_with_span("unexpected end", span))?;
 
     let idx = match top {
@@ -559,6 +931,7 @@ fn close_control_frame(
         ));
     }
 
+    targets.pop();
     let frame = controls
         .pop()
         .ok_or_else(|| Error::parse_with_span("unexpected end", span))?;
@@ -570,31 +943,47 @@ fn close_control_frame(
         then_block,
         else_if_branches,
         else_block,
+        mut syntax,
         ..
     } = frame;
+    syntax.end = end;
+    let expect_pipeline = || pipeline.expect("control frame pipeline should be initialised");
 
     let if_branches = else_if_branches
         .into_iter()
         .map(|branch| {
             crate::ast::ElseIfBranch::new(branch.span, branch.tokens, branch.pipeline, branch.block)
+                .with_syntax(branch.syntax)
         })
         .collect::<Vec<_>>();
 
     let node = match kind {
-        ControlKind::If => Node::If(IfNode::new(
-            full_span,
-            tokens,
-            pipeline,
-            then_block,
-            if_branches,
-            else_block,
-        )),
-        ControlKind::Range => Node::Range(RangeNode::new(
-            full_span, tokens, pipeline, then_block, else_block,
-        )),
-        ControlKind::With => Node::With(WithNode::new(
-            full_span, tokens, pipeline, then_block, else_block,
-        )),
+        ControlKind::If => Node::If(
+            IfNode::new(
+                full_span,
+                tokens,
+                expect_pipeline(),
+                then_block,
+                if_branches,
+                else_block,
+            )
+            .with_syntax(syntax),
+        ),
+        ControlKind::Range => Node::Range(
+            RangeNode::new(full_span, tokens, expect_pipeline(), then_block, else_block)
+                .with_syntax(syntax),
+        ),
+        ControlKind::With => Node::With(
+            WithNode::new(full_span, tokens, expect_pipeline(), then_block, else_block)
+                .with_syntax(syntax),
+        ),
+        ControlKind::Define(name) => {
+            Node::Define(DefineNode::new(full_span, tokens, name, then_block).with_syntax(syntax))
+        }
+        ControlKind::Block(name) => Node::Block(
+            BlockNode::new(full_span, tokens, name, expect_pipeline(), then_block)
+                .with_syntax(syntax),
+        ),
     };
 
     push_node(root, controls, targets.as_slice(), node);
@@ -602,7 +991,7 @@ fn close_control_frame(
     Ok(())
 }
 
-fn is_comment(body: &str) -> bool {
+pub(crate) fn is_comment(body: &str) -> bool {
     let trimmed = body.trim();
     trimmed.starts_with("/*") && trimmed.ends_with("*/")
 }
@@ -626,6 +1015,24 @@ fn parse_action_pipeline(tokens: &[crate::lexer::Token]) -> Result<Pipeline, Err
     parser.parse_pipeline()
 }
 
+/// Extracts the quoted template name following `define`, `block` or `template`.
+fn parse_definition_name(tokens: &[Token], context: &str) -> Result<String, Error> {
+    match tokens.get(1) {
+        Some(Token {
+            kind: TokenKind::StringLiteral(name),
+            ..
+        }) => Ok(name.clone()),
+        Some(token) => Err(Error::parse(
+            format!("unexpected {:?} in {context}", token.kind),
+            Some(token.span),
+        )),
+        None => Err(Error::parse(
+            format!("missing template name in {context}"),
+            tokens.first().map(|token| token.span),
+        )),
+    }
+}
+
 struct ActionParser<'a> {
     tokens: &'a [crate::lexer::Token],
     index: usize,
@@ -711,10 +1118,14 @@ impl<'a> ActionParser<'a> {
     }
 
     fn parse_command(&mut self) -> Result<Command, Error> {
+        let start = self.index;
         let first_expr = self.parse_expression()?;
+        let first_span = self.span_since(start);
 
         if let Some(operator) = self.consume_operator()? {
+            let rhs_start = self.index;
             let rhs = self.parse_expression()?;
+            let rhs_span = self.span_since(rhs_start);
             let op_name = match operator {
                 Operator::Equal => "eq",
                 Operator::NotEqual => "ne",
@@ -726,20 +1137,31 @@ impl<'a> ActionParser<'a> {
             return Ok(Command::new(
                 Expression::Identifier(op_name.to_string()),
                 vec![first_expr, rhs],
-            ));
+            )
+            .with_spans(self.span_since(start), vec![first_span, rhs_span]));
         }
 
         let mut args = Vec::new();
+        let mut arg_spans = Vec::new();
 
         loop {
             self.skip_commas();
             if self.peek_pipe() || self.is_eof() {
                 break;
             }
+            let arg_start = self.index;
             args.push(self.parse_expression()?);
+            arg_spans.push(self.span_since(arg_start));
         }
 
-        Ok(Command::new(first_expr, args))
+        Ok(Command::new(first_expr, args).with_spans(self.span_since(start), arg_spans))
+    }
+
+    /// Span from the token at `start` through the last consumed token.
+    fn span_since(&self, start: usize) -> Span {
+        let first = self.tokens[start].span;
+        let last = self.tokens[self.index.max(start + 1) - 1].span;
+        Span::new(first.start, last.end)
     }
 
     fn parse_expression(&mut self) -> Result<Expression, Error> {
@@ -767,7 +1189,17 @@ impl<'a> ActionParser<'a> {
             TokenKind::Keyword(Keyword::True) => Expression::BoolLiteral(true),
             TokenKind::Keyword(Keyword::False) => Expression::BoolLiteral(false),
             TokenKind::Keyword(keyword) => Expression::Identifier(keyword.as_str().to_string()),
-            TokenKind::LeftParen => self.parse_parenthesized_pipeline()?,
+            TokenKind::LeftParen => {
+                let pipeline = self.parse_parenthesized_pipeline()?;
+                let close_span = self.tokens[self.index - 1].span;
+                let mut fields = Vec::new();
+                self.extend_field_segments(&mut fields, close_span);
+                if fields.is_empty() {
+                    pipeline
+                } else {
+                    Expression::chain(pipeline, fields)
+                }
+            }
             other => {
                 return Err(Error::parse(
                     format!("unexpected token in expression: {:?}", other),
@@ -939,10 +1371,10 @@ impl<'a> ActionParser<'a> {
     }
 }
 
-fn find_action_start(bytes: &[u8], from: usize) -> Option<usize> {
+pub(crate) fn find_action_start(bytes: &[u8], from: usize, left: &[u8]) -> Option<usize> {
     let mut i = from;
-    while i + 1 < bytes.len() {
-        if bytes[i] == b'{' && bytes[i + 1] == b'{' {
+    while i + left.len() <= bytes.len() {
+        if bytes[i..].starts_with(left) {
             return Some(i);
         }
         i += 1;
@@ -950,16 +1382,17 @@ fn find_action_start(bytes: &[u8], from: usize) -> Option<usize> {
     None
 }
 
-fn find_action_end(bytes: &[u8], from: usize) -> Option<usize> {
+pub(crate) fn find_action_end(bytes: &[u8], from: usize, right: &[u8]) -> Option<usize> {
     let mut i = from;
     let mut in_raw = false;
     let mut in_string = false;
     let mut in_comment = false;
-    while i + 1 < bytes.len() {
+    while i < bytes.len() {
         let current = bytes[i];
+        let next = bytes.get(i + 1).copied();
 
         if in_comment {
-            if current == b'*' && bytes[i + 1] == b'/' {
+            if current == b'*' && next == Some(b'/') {
                 in_comment = false;
                 i += 2;
             } else {
@@ -988,7 +1421,7 @@ fn find_action_end(bytes: &[u8], from: usize) -> Option<usize> {
             continue;
         }
 
-        if current == b'/' && bytes[i + 1] == b'*' {
+        if current == b'/' && next == Some(b'*') {
             in_comment = true;
             i += 2;
             continue;
@@ -1008,7 +1441,7 @@ fn find_action_end(bytes: &[u8], from: usize) -> Option<usize> {
             _ => {}
         }
 
-        if current == b'}' && bytes[i + 1] == b'}' {
+        if bytes[i..].starts_with(right) {
             return Some(i);
         }
         i += 1;
@@ -1024,15 +1457,23 @@ mod tests {
     fn trim_action_delimiters_reports_flags() {
         let source = "{{- foo -}}";
         let bytes = source.as_bytes();
-        let open = find_action_start(bytes, 0).expect("missing action start");
-        let close = find_action_end(bytes, open + 2).expect("missing action end");
-        let window = trim_action_delimiters(source, bytes, open, close);
+        let open = find_action_start(bytes, 0, b"{{").expect("missing action start");
+        let close = find_action_end(bytes, open + 2, b"}}").expect("missing action end");
+        let window = trim_action_delimiters(source, bytes, (open, 2), (close, 2));
 
         assert!(window.trim_left);
         assert!(window.trim_right);
         assert_eq!(window.body, "foo");
         assert_eq!(window.body_start, 4);
         assert_eq!(window.span, Span::new(0, source.len()));
+
+        // Without whitespace next to the body, `-` belongs to the body.
+        for source in ["{{-3}}", "{{-.5}}", "{{ 3-}}"] {
+            let bytes = source.as_bytes();
+            let close = find_action_end(bytes, 2, b"}}").expect("missing action end");
+            let window = trim_action_delimiters(source, bytes, (0, 2), (close, 2));
+            assert!(!window.trim_left && !window.trim_right, "{source}");
+        }
     }
 
     #[test]
@@ -1054,8 +1495,15 @@ mod tests {
             .then_block
             .push(Node::Text(TextNode::new(Span::new(10, 12), "ok")));
 
-        close_control_frame(&mut root, &mut controls, &mut targets, Span::new(12, 20))
-            .expect("closing control frame should succeed");
+        let end = ActionSyntax::new("end");
+        close_control_frame(
+            &mut root,
+            &mut controls,
+            &mut targets,
+            Span::new(12, 20),
+            end,
+        )
+        .expect("closing control frame should succeed");
 
         assert!(controls.is_empty());
         assert!(matches!(targets.as_slice(), [AppendTarget::Root]));
@@ -1065,16 +1513,16 @@ mod tests {
     #[test]
     fn find_action_end_handles_comment_with_quotes() {
         let input = b"{{/* comment with \" unmatched */}} tail";
-        let start = find_action_start(input, 0).expect("missing action start");
-        let end = find_action_end(input, start + 2).expect("should find closing braces");
+        let start = find_action_start(input, 0, b"{{").expect("missing action start");
+        let end = find_action_end(input, start + 2, b"}}").expect("should find closing braces");
         assert_eq!(&input[end..end + 2], b"}}");
     }
 
     #[test]
     fn find_action_end_handles_comment_with_backticks() {
         let input = b"{{/* comment with ` unmatched */}} tail";
-        let start = find_action_start(input, 0).expect("missing action start");
-        let end = find_action_end(input, start + 2).expect("should find closing braces");
+        let start = find_action_start(input, 0, b"{{").expect("missing action start");
+        let end = find_action_end(input, start + 2, b"}}").expect("should find closing braces");
         assert_eq!(&input[end..end + 2], b"}}");
     }
 
@@ -1252,4 +1700,247 @@ mod tests {
             Expression::Field(_)
         ));
     }
+
+    #[test]
+    fn parses_define_and_template_nodes() {
+        let src = r#"{{define "item"}}{{.}}{{end}}{{template "item" .name}}"#;
+        let ast = parse_template("define", src).unwrap();
+        match &ast.root.nodes[0] {
+            Node::Define(node) => {
+                assert_eq!(node.name, "item");
+                assert_eq!(node.block.nodes.len(), 1);
+            }
+            other => panic!("expected Define node, got {other:?}"),
+        }
+        match &ast.root.nodes[1] {
+            Node::Template(node) => {
+                assert_eq!(node.name, "item");
+                assert!(node.pipeline.is_some());
+            }
+            other => panic!("expected Template node, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn define_must_be_top_level_and_reject_else() {
+        let nested = parse_template("nested", r#"{{if .x}}{{define "a"}}{{end}}{{end}}"#)
+            .expect_err("define inside if should fail");
+        assert!(nested
+            .to_string()
+            .contains("define is only allowed at the top level"));
+
+        let with_else = parse_template("else", r#"{{define "a"}}x{{else}}y{{end}}"#)
+            .expect_err("else inside define should fail");
+        assert!(with_else.to_string().contains("unexpected else"));
+
+        let block = parse_template("block", r#"{{block "a"}}x{{end}}"#)
+            .expect_err("block without pipeline should fail");
+        assert!(block.to_string().contains("missing value for block clause"));
+    }
+
+    #[test]
+    fn parses_field_chain_on_parenthesized_pipeline() {
+        let ast = parse_template("chain", "{{ (index .items 0).name.first (.x) .y }}")
+            .expect("chain should parse");
+        let Node::Action(action) = &ast.root.nodes[0] else {
+            panic!("expected action node");
+        };
+        let command = &action.pipeline.commands[0];
+        let Expression::Chain { target, fields } = &command.target else {
+            panic!("expected chain target, got {:?}", command.target);
+        };
+        assert!(matches!(**target, Expression::PipelineExpr(_)));
+        assert_eq!(fields, &vec!["name".to_string(), "first".to_string()]);
+        // Fields separated by whitespace remain separate arguments.
+        assert!(matches!(command.args[0], Expression::PipelineExpr(_)));
+        assert!(matches!(&command.args[1], Expression::Field(parts) if parts == &["y"]));
+    }
+
+    #[test]
+    fn custom_delimiters_of_any_length() {
+        let options = ParseOptions::new().with_delimiters("<%=", "%>");
+        let src = "{{keep}} <%= .name %> <%=- /* note */ -%> <%= \"%>\" %>";
+        let ast = parse_template_with_options("custom", src, &options)
+            .expect("custom delimiters should parse");
+        assert_eq!(ast.delimiters, Delimiters::new("<%=", "%>"));
+
+        let Node::Text(text) = &ast.root.nodes[0] else {
+            panic!("default delimiters should be plain text");
+        };
+        assert_eq!(text.text, "{{keep}} ");
+        assert!(matches!(ast.root.nodes[3], Node::Comment(_)));
+        let Node::Action(last) = &ast.root.nodes[4] else {
+            panic!("expected trailing action");
+        };
+        let start = src.rfind("<%=").unwrap();
+        assert_eq!(last.span, Span::new(start, src.len()));
+        assert_eq!(last.source, "\"%>\"");
+
+        let brackets = options.with_delimiters("[[", "]]");
+        let err = parse_template_with_options("open", "[[ .x ", &brackets)
+            .expect_err("missing right delimiter");
+        assert!(err.to_string().contains("unclosed action"));
+    }
+
+    #[test]
+    fn break_and_continue_require_enclosing_range() {
+        let ast = parse_template(
+            "ok",
+            "{{range .}}{{if .}}{{break}}{{end}}{{continue}}{{end}}",
+        )
+        .expect("break inside range should parse");
+        let Node::Range(range) = &ast.root.nodes[0] else {
+            panic!("expected range node");
+        };
+        assert!(matches!(range.then_block.nodes[1], Node::Continue(_)));
+
+        let outside = parse_template("outside", "{{if .}}{{break}}{{end}}")
+            .expect_err("break outside range should fail");
+        assert!(outside.to_string().contains("{{break}} outside {{range}}"));
+
+        let in_else = parse_template("else", "{{range .}}x{{else}}{{continue}}{{end}}")
+            .expect_err("continue in range else should fail");
+        assert!(in_else
+            .to_string()
+            .contains("{{continue}} outside {{range}}"));
+
+        let in_define = parse_template(
+            "define",
+            r#"{{range .}}{{end}}{{define "a"}}{{break}}{{end}}"#,
+        )
+        .expect_err("break inside define should fail");
+        assert!(in_define
+            .to_string()
+            .contains("{{break}} outside {{range}}"));
+
+        let extra = parse_template("extra", "{{range .}}{{break 1}}{{end}}")
+            .expect_err("break with arguments should fail");
+        assert!(extra.to_string().contains("{{break}}"));
+    }
+
+    #[test]
+    fn go_dialect_rejects_lithos_extensions() {
+        let go = ParseOptions::new().with_dialect(Dialect::go());
+        let parse = |source: &str| parse_template_with_options("go", source, &go);
+
+        let err = parse("{{if .a == .b}}x{{end}}").expect_err("operators are Lithos-only");
+        assert!(err.to_string().contains(r#"unexpected "==" in operand"#));
+        let err = parse("{{printf \"%s\", .a}}").expect_err("commas separate range variables only");
+        assert!(err.to_string().contains("unexpected character in action"));
+        let err = parse("{{print if}}").expect_err("keywords are not operands");
+        assert!(err.to_string().contains("unexpected <if> in operand"));
+
+        parse("{{range $i, $v := .}}{{if eq $i 0}}{{else if not $v}}{{end}}{{end}}")
+            .expect("plain Go template parses");
+        parse_template_with_options("lithos", "{{if .a == .b}}x{{end}}", &ParseOptions::new())
+            .expect("Lithos keeps infix operators");
+    }
+
+    #[test]
+    fn go_dialect_gates_break_on_version() {
+        let old = ParseOptions::new().with_dialect(Dialect::Go {
+            version: GoVersion::new(1, 17),
+        });
+        let err = parse_template_with_options("old", "{{range .}}{{break}}{{end}}", &old)
+            .expect_err("break predates go1.18");
+        assert!(err.to_string().contains(r#"function "break" not defined"#));
+
+        let shadowed = old.with_function_keyword(Keyword::Break);
+        let ast = parse_template_with_options("old", "{{range .}}{{break 1}}{{end}}", &shadowed)
+            .expect("registered break is a function");
+        let Node::Range(range) = &ast.root.nodes[0] else {
+            panic!("expected range node");
+        };
+        assert!(matches!(range.then_block.nodes[0], Node::Action(_)));
+    }
+
+    #[test]
+    fn recovering_parse_reports_every_error() {
+        let src =
+            "{{if .a}}{{ .b | }}{{else}}{{else}}x{{end}}{{end}}{{range .c}}{{ 1 +* }}{{with}}y";
+        let parsed = parse_template_recovering("lint", src, &ParseOptions::new());
+        let messages: Vec<_> = parsed.diagnostics.iter().map(Error::message).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "unexpected end of action",
+                "duplicate else block",
+                "unexpected end",
+                "unexpected character '+'",
+                "with requires a pipeline",
+                "unterminated control structure",
+                "unterminated control structure",
+            ]
+        );
+        assert!(!parsed.is_ok());
+
+        // The partial tree keeps what parsed, including unterminated bodies.
+        let nodes = &parsed.ast.root.nodes;
+        let Node::If(if_node) = &nodes[0] else {
+            panic!("expected if node");
+        };
+        assert_eq!(if_node.else_block.as_ref().unwrap().nodes.len(), 1);
+        let Node::Range(range) = &nodes[1] else {
+            panic!("expected range node");
+        };
+        assert_eq!(
+            range.span,
+            Span::new(src.find("{{range").unwrap(), src.len())
+        );
+        let Node::With(with) = &range.then_block.nodes[0] else {
+            panic!("expected with node");
+        };
+        assert!(matches!(&with.then_block.nodes[0], Node::Text(text) if text.text == "y"));
+    }
+
+    #[test]
+    fn recovering_parse_matches_strict_parse() {
+        let options = ParseOptions::new();
+        let ok = parse_template_recovering("ok", "{{define \"x\"}}{{.}}{{end}}", &options);
+        assert!(ok.is_ok());
+
+        for src in ["{{if}}{{end}}", "{{end}}", "a {{ .b", "{{/* c", "{{if .a}}"] {
+            let strict = parse_template_with_options("src", src, &options).unwrap_err();
+            let parsed = parse_template_recovering("src", src, &options);
+            assert_eq!(parsed.diagnostics.len(), 1, "{src}");
+            assert_eq!(parsed.diagnostics[0].message(), strict.message(), "{src}");
+            assert_eq!(parsed.diagnostics[0].span(), strict.span(), "{src}");
+        }
+    }
+
+    #[test]
+    fn records_concrete_syntax_of_actions() {
+        let src = "a \n{{- if  .x -}}\n  b{{ else }}c{{end}}";
+        let mut ast = parse_template("cst", src).unwrap();
+        assert_eq!(ast.to_source(), src);
+
+        let Node::If(if_node) = &mut ast.root.nodes[1] else {
+            panic!("expected if node");
+        };
+        let open = &if_node.syntax.open;
+        assert!(open.trim_left && open.trim_right);
+        assert_eq!(
+            (
+                open.leading.as_str(),
+                open.body.as_str(),
+                open.trailing.as_str()
+            ),
+            (" ", "if  .x", " ")
+        );
+        assert_eq!(open.trimmed_before, " \n");
+        assert_eq!(open.trimmed_after, "\n  ");
+        assert_eq!(
+            if_node.syntax.else_action.as_ref().map(|s| s.body.as_str()),
+            Some("else")
+        );
+
+        // Codemods edit the recorded syntax and print the rest untouched.
+        if_node.syntax.open.body = "if .y".into();
+        if_node.syntax.end.trim_left = true;
+        if_node.syntax.end.leading = " ".into();
+        assert_eq!(
+            ast.to_source(),
+            "a \n{{- if .y -}}\n  b{{ else }}c{{- end}}"
+        );
+    }
 }
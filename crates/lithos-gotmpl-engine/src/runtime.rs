@@ -1,27 +1,152 @@
 // SPDX-License-Identifier: Apache-2.0 OR MIT
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::future::Future;
+use std::mem;
+use std::pin::Pin;
 use std::sync::Arc;
 
 use serde_json::{Number, Value};
 
-use crate::ast::{BindingKind, Command, Expression, Pipeline};
+use crate::ast::{Ast, BindingKind, Command, Expression, Pipeline, Span};
+use crate::dialect::Dialect;
 use crate::error::Error;
+use crate::options::{Limit, MissingKey, RenderOptions};
+
+/// Named template trees reachable from an execution, keyed by template name.
+pub(crate) type TemplateMap = Arc<HashMap<String, Arc<Ast>>>;
+
+/// Upper bound on nested `template`/`block` invocations, guarding against
+/// unbounded recursion between templates. Go allows 100000 (`maxExecDepth`),
+/// but each level here takes native stack, so the default stays well within
+/// a thread's default stack.
+const MAX_TEMPLATE_DEPTH: usize = 100;
+
+/// Upper bound on async helper calls awaited by one render, each of which
+/// costs another pass over the template.
+const MAX_ASYNC_CALLS: usize = 1000;
 
 /// Signature implemented by helper functions invoked from templates.
 pub type Function = dyn Fn(&mut EvalContext, &[Value]) -> Result<Value, Error> + Send + Sync;
 
+/// Signature implemented by helpers that evaluate their arguments on demand,
+/// such as the short-circuiting `and`/`or`.
+pub type LazyFunction =
+    dyn for<'a> Fn(&mut EvalContext, LazyArgs<'a>) -> Result<Value, Error> + Send + Sync;
+
+/// Signature implemented by methods registered for data shapes. A method
+/// receives the object it was called on and its evaluated arguments.
+pub type Method = dyn Fn(&mut EvalContext, &Value, &[Value]) -> Result<Value, Error> + Send + Sync;
+
+/// Key naming an object's type for [`FunctionRegistryBuilder::register_method`].
+const TYPE_KEY: &str = "$type";
+
+/// Objects a method applies to.
+#[derive(Clone)]
+enum Receiver {
+    Type(String),
+    Predicate(Arc<dyn Fn(&Value) -> bool + Send + Sync>),
+}
+
+impl Receiver {
+    fn matches(&self, value: &Value) -> bool {
+        match self {
+            Receiver::Type(name) => {
+                value.get(TYPE_KEY).and_then(Value::as_str) == Some(name.as_str())
+            }
+            Receiver::Predicate(matches) => matches(value),
+        }
+    }
+}
+
+/// Methods sharing a name, in registration order.
+type MethodMap = HashMap<String, Vec<(Receiver, Arc<Method>)>>;
+
+/// Future returned by an [`AsyncFunction`].
+pub type HelperFuture = Pin<Box<dyn Future<Output = Result<Value, Error>> + Send>>;
+
+/// Signature implemented by helpers that do asynchronous work such as I/O. They
+/// receive their evaluated arguments and are only available to
+/// [`Template::render_async`](crate::Template::render_async).
+pub type AsyncFunction = dyn Fn(Vec<Value>) -> HelperFuture + Send + Sync;
+
+/// Arguments handed to a [`LazyFunction`]; each one is evaluated only when
+/// requested through [`LazyArgs::eval`].
+#[derive(Clone, Copy)]
+pub struct LazyArgs<'a> {
+    inner: LazyArgsInner<'a>,
+}
+
+#[derive(Clone, Copy)]
+enum LazyArgsInner<'a> {
+    Expressions {
+        command: &'a Command,
+        input: Option<&'a Value>,
+    },
+    Values(&'a [Value]),
+}
+
+impl<'a> LazyArgs<'a> {
+    /// Wraps already evaluated values, e.g. when a lazy helper is invoked through `call`.
+    pub fn from_values(values: &'a [Value]) -> Self {
+        Self {
+            inner: LazyArgsInner::Values(values),
+        }
+    }
+
+    fn from_command(command: &'a Command, input: Option<&'a Value>) -> Self {
+        Self {
+            inner: LazyArgsInner::Expressions { command, input },
+        }
+    }
+
+    /// Returns the number of arguments, including a piped-in value.
+    pub fn len(&self) -> usize {
+        match self.inner {
+            LazyArgsInner::Expressions { command, input } => {
+                command.args.len() + usize::from(input.is_some())
+            }
+            LazyArgsInner::Values(values) => values.len(),
+        }
+    }
+
+    /// Reports whether the helper was called without arguments.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Evaluates the argument at `index`. A piped-in value is the last argument
+    /// and was already evaluated by the preceding command.
+    pub fn eval(&self, ctx: &mut EvalContext, index: usize) -> Result<Value, Error> {
+        match self.inner {
+            LazyArgsInner::Expressions { command, input } => match command.args.get(index) {
+                Some(expr) => ctx.at(command.arg_span(index), |ctx| ctx.eval_expression(expr)),
+                None if index == command.args.len() => input
+                    .cloned()
+                    .ok_or_else(|| Error::render("argument index out of range", ctx.span)),
+                None => Err(Error::render("argument index out of range", ctx.span)),
+            },
+            LazyArgsInner::Values(values) => values
+                .get(index)
+                .cloned()
+                .ok_or_else(|| Error::render("argument index out of range", ctx.span)),
+        }
+    }
+}
+
 /// Registry that maps helper names to callable functions.
 #[derive(Clone, Default)]
 pub struct FunctionRegistry {
     map: Arc<HashMap<String, Arc<Function>>>,
+    lazy: Arc<HashMap<String, Arc<LazyFunction>>>,
+    asynchronous: Arc<HashMap<String, Arc<AsyncFunction>>>,
+    methods: Arc<MethodMap>,
 }
 
 impl FunctionRegistry {
     /// Creates an empty registry.
     pub fn empty() -> Self {
-        Self {
-            map: Arc::new(HashMap::new()),
-        }
+        Self::default()
     }
 
     /// Returns a new builder for constructing registries.
@@ -35,10 +160,36 @@ impl FunctionRegistry {
     }
 
     /// Fetches a helper function by name.
+    ///
+    /// Lazy helpers are returned as adapters that receive evaluated arguments.
     pub fn get(&self, name: &str) -> Option<Arc<Function>> {
         self.map.get(name).cloned()
     }
 
+    /// Fetches a helper registered with [`FunctionRegistryBuilder::register_lazy`].
+    pub fn get_lazy(&self, name: &str) -> Option<Arc<LazyFunction>> {
+        self.lazy.get(name).cloned()
+    }
+
+    /// Fetches a helper registered with [`FunctionRegistryBuilder::register_async`].
+    pub fn get_async(&self, name: &str) -> Option<Arc<AsyncFunction>> {
+        self.asynchronous.get(name).cloned()
+    }
+
+    /// Finds the method `name` for `receiver`. When several registered methods
+    /// match, the one registered last wins.
+    pub fn method(&self, receiver: &Value, name: &str) -> Option<Arc<Method>> {
+        if self.methods.is_empty() {
+            return None;
+        }
+        self.methods
+            .get(name)?
+            .iter()
+            .rev()
+            .find(|(matcher, _)| matcher.matches(receiver))
+            .map(|(_, method)| method.clone())
+    }
+
     /// Reports whether the registry contains no helper functions.
     pub fn is_empty(&self) -> bool {
         self.map.is_empty()
@@ -56,14 +207,15 @@ impl FunctionRegistry {
 #[derive(Default)]
 pub struct FunctionRegistryBuilder {
     map: HashMap<String, Arc<Function>>,
+    lazy: HashMap<String, Arc<LazyFunction>>,
+    asynchronous: HashMap<String, Arc<AsyncFunction>>,
+    methods: MethodMap,
 }
 
 impl FunctionRegistryBuilder {
     /// Creates a new, empty builder.
     pub fn new() -> Self {
-        Self {
-            map: HashMap::new(),
-        }
+        Self::default()
     }
 
     /// Registers a helper function under the provided name.
@@ -71,15 +223,134 @@ impl FunctionRegistryBuilder {
     where
         F: Fn(&mut EvalContext, &[Value]) -> Result<Value, Error> + Send + Sync + 'static,
     {
-        self.map.insert(name.into(), Arc::new(func));
+        let name = name.into();
+        self.lazy.remove(&name);
+        self.asynchronous.remove(&name);
+        self.map.insert(name, Arc::new(func));
+        self
+    }
+
+    /// Registers a helper that receives its arguments unevaluated and decides
+    /// which of them to evaluate, allowing short-circuit semantics.
+    pub fn register_lazy<F>(&mut self, name: impl Into<String>, func: F) -> &mut Self
+    where
+        F: for<'a> Fn(&mut EvalContext, LazyArgs<'a>) -> Result<Value, Error>
+            + Send
+            + Sync
+            + 'static,
+    {
+        let name = name.into();
+        self.asynchronous.remove(&name);
+        let func: Arc<LazyFunction> = Arc::new(func);
+        let eager = func.clone();
+        self.map.insert(
+            name.clone(),
+            Arc::new(move |ctx: &mut EvalContext, args: &[Value]| {
+                eager(ctx, LazyArgs::from_values(args))
+            }),
+        );
+        self.lazy.insert(name, func);
+        self
+    }
+
+    /// Registers a helper that returns a future, for work such as looking up
+    /// secrets or reading configuration.
+    ///
+    /// [`Template::render_async`](crate::Template::render_async) evaluates the
+    /// template synchronously; when it reaches an async helper whose result is
+    /// not known yet, it awaits the helper and evaluates the template again.
+    /// Later passes replay the helper calls earlier passes completed, sync and
+    /// async alike, so each helper call runs once per render; only a helper
+    /// whose own arguments were still waiting on an async helper runs again.
+    /// Each awaited call still costs a pass over the template up to that call,
+    /// and [`Limit::AsyncCalls`] caps how many one render awaits. Every pass
+    /// starts with fresh budgets, so other limits apply as they do to a
+    /// synchronous render. A pass that reaches an earlier async call with
+    /// different arguments fails with [`Error::AsyncArgumentsChanged`].
+    /// Synchronous renders fail when they reach an async helper.
+    pub fn register_async<F, Fut>(&mut self, name: impl Into<String>, func: F) -> &mut Self
+    where
+        F: Fn(Vec<Value>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Value, Error>> + Send + 'static,
+    {
+        let name = name.into();
+        self.lazy.remove(&name);
+        let key = name.clone();
+        self.map.insert(
+            name.clone(),
+            Arc::new(move |ctx: &mut EvalContext, args: &[Value]| ctx.async_result(&key, args)),
+        );
+        self.asynchronous.insert(
+            name,
+            Arc::new(move |args: Vec<Value>| -> HelperFuture { Box::pin(func(args)) }),
+        );
+        self
+    }
+
+    /// Registers method `name` for objects whose `"$type"` key is `type_name`,
+    /// so that `{{ .user.FullName }}` calls it with `.user` as the receiver and
+    /// `{{ .pod.Labels.Get "app" }}` passes `"app"` as its argument. As in Go,
+    /// a method takes precedence over a key of the same name, and a piped value
+    /// becomes the last argument.
+    pub fn register_method<F>(
+        &mut self,
+        type_name: impl Into<String>,
+        name: impl Into<String>,
+        func: F,
+    ) -> &mut Self
+    where
+        F: Fn(&mut EvalContext, &Value, &[Value]) -> Result<Value, Error> + Send + Sync + 'static,
+    {
+        self.add_method(
+            Receiver::Type(type_name.into()),
+            name.into(),
+            Arc::new(func),
+        )
+    }
+
+    /// Registers method `name` for every value `matches` accepts, for data
+    /// that carries no `"$type"` key; see [`FunctionRegistryBuilder::register_method`].
+    pub fn register_method_when<P, F>(
+        &mut self,
+        matches: P,
+        name: impl Into<String>,
+        func: F,
+    ) -> &mut Self
+    where
+        P: Fn(&Value) -> bool + Send + Sync + 'static,
+        F: Fn(&mut EvalContext, &Value, &[Value]) -> Result<Value, Error> + Send + Sync + 'static,
+    {
+        self.add_method(
+            Receiver::Predicate(Arc::new(matches)),
+            name.into(),
+            Arc::new(func),
+        )
+    }
+
+    fn add_method(&mut self, receiver: Receiver, name: String, func: Arc<Method>) -> &mut Self {
+        self.methods.entry(name).or_default().push((receiver, func));
         self
     }
 
     /// Extends the builder with all helpers from another registry.
     pub fn extend(&mut self, other: &FunctionRegistry) -> &mut Self {
         for (key, value) in other.map.iter() {
+            self.lazy.remove(key);
+            self.asynchronous.remove(key);
             self.map.insert(key.clone(), value.clone());
         }
+        for (key, value) in other.lazy.iter() {
+            self.lazy.insert(key.clone(), value.clone());
+        }
+        for (key, value) in other.asynchronous.iter() {
+            self.asynchronous.insert(key.clone(), value.clone());
+        }
+        for (key, methods) in other.methods.iter() {
+            self.methods
+                .entry(key.clone())
+                .or_default()
+                .extend(methods.iter().cloned());
+        }
         self
     }
 
@@ -87,16 +358,128 @@ impl FunctionRegistryBuilder {
     pub fn build(self) -> FunctionRegistry {
         FunctionRegistry {
             map: Arc::new(self.map),
+            lazy: Arc::new(self.lazy),
+            asynchronous: Arc::new(self.asynchronous),
+            methods: Arc::new(self.methods),
         }
     }
 }
 
+/// State [`Template::render_async`](crate::Template::render_async) carries
+/// from one pass to the next: the results of awaited async helpers, keyed by
+/// helper name and JSON-encoded arguments, and the helper calls earlier passes
+/// completed, so that later passes replay them instead of calling again.
+#[derive(Default)]
+pub(crate) struct AsyncCalls {
+    results: HashMap<(String, String), Result<Value, Error>>,
+    /// Completed helper calls, indexed by the position they started at.
+    completed: Vec<Option<CompletedCall>>,
+    /// The first call of the current pass whose result is not known yet, and
+    /// its position.
+    pending: Option<(String, Vec<Value>, usize)>,
+    /// Position of the next helper call in the current pass.
+    position: usize,
+    /// Position of the call the previous pass stopped at. Every async call up
+    /// to it must find its result, or its arguments changed between passes.
+    awaited: Option<usize>,
+}
+
+struct CompletedCall {
+    name: String,
+    result: Result<Value, Error>,
+    /// Position after the calls the helper made itself, where a replay resumes.
+    next: usize,
+}
+
+enum HelperCall {
+    /// An earlier pass completed the call with this result.
+    Replayed(Result<Value, Error>),
+    /// The call runs, starting at this position.
+    Started(usize),
+}
+
+impl AsyncCalls {
+    /// Awaits the call the last pass stopped at, returning `false` when the
+    /// pass needed nothing more.
+    pub(crate) async fn resolve_pending(&mut self, functions: &FunctionRegistry) -> bool {
+        let Some((name, args, position)) = self.pending.take() else {
+            return false;
+        };
+        self.awaited = Some(position);
+        let key = (name, Value::Array(args.clone()).to_string());
+        let result = match functions.get_async(&key.0) {
+            Some(func) => func(args).await,
+            None => Err(Error::render(
+                format!("async helper {:?} is not registered", key.0),
+                None,
+            )),
+        };
+        self.results.insert(key, result);
+        true
+    }
+
+    fn start_call(&mut self, name: &str) -> HelperCall {
+        let position = self.position;
+        if let Some(Some(call)) = self.completed.get(position) {
+            if call.name == name {
+                self.position = call.next;
+                return HelperCall::Replayed(replay(&call.result));
+            }
+            // The pass took another path, so what follows was recorded for
+            // calls that no longer happen.
+            self.completed.truncate(position);
+        }
+        self.position += 1;
+        HelperCall::Started(position)
+    }
+
+    fn finish_call(&mut self, position: usize, name: &str, result: &Result<Value, Error>) {
+        // Calls cut short by a pending async helper did not complete.
+        if self.pending.is_some() {
+            return;
+        }
+        if self.completed.len() <= position {
+            self.completed.resize_with(position + 1, || None);
+        }
+        self.completed[position] = Some(CompletedCall {
+            name: name.to_string(),
+            result: replay(result),
+            next: self.position,
+        });
+    }
+}
+
+fn replay(result: &Result<Value, Error>) -> Result<Value, Error> {
+    match result {
+        Ok(value) => Ok(value.clone()),
+        Err(err) => Err(err.duplicate()),
+    }
+}
+
 /// Execution context threaded through template evaluation.
-pub struct EvalContext {
-    stack: Vec<Value>,
-    root: Value,
+///
+/// The render data is borrowed for `'data`, so renders do not copy their
+/// input; field lookups on it clone only the values they return.
+pub struct EvalContext<'data> {
+    /// Dot values, innermost last; the first entry is `$`.
+    stack: Vec<Cow<'data, Value>>,
     variables: Vec<HashMap<String, Value>>,
     functions: FunctionRegistry,
+    templates: TemplateMap,
+    depth: usize,
+    output_bytes: usize,
+    iterations: usize,
+    helper_calls: usize,
+    options: RenderOptions,
+    dialect: Dialect,
+    span: Option<Span>,
+    async_calls: Option<AsyncCalls>,
+}
+
+/// Caller state stashed while a named template executes with its own dot and `$`.
+pub(crate) struct SavedFrame<'data> {
+    stack: Vec<Cow<'data, Value>>,
+    variables: Vec<HashMap<String, Value>>,
 }
 
 enum CommandResolution {
@@ -105,31 +488,335 @@ enum CommandResolution {
     Expression,
 }
 
-impl EvalContext {
+impl<'data> EvalContext<'data> {
     /// Creates a new evaluation context seeded with the input data and helper registry.
     pub fn new(data: Value, functions: FunctionRegistry) -> Self {
-        let mut variables = Vec::new();
-        let mut scope = HashMap::new();
-        scope.insert("$".to_string(), data.clone());
-        variables.push(scope);
+        Self::with_data(Cow::Owned(data), functions)
+    }
 
+    /// Creates a context over owned or borrowed render data.
+    pub(crate) fn with_data(data: Cow<'data, Value>, functions: FunctionRegistry) -> Self {
         Self {
-            stack: vec![data.clone()],
-            root: data,
-            variables,
+            stack: vec![data],
+            variables: vec![HashMap::new()],
             functions,
+            templates: TemplateMap::default(),
+            depth: 0,
+            output_bytes: 0,
+            iterations: 0,
+            helper_calls: 0,
+            options: RenderOptions::default(),
+            dialect: Dialect::default(),
+            span: None,
+            async_calls: None,
+        }
+    }
+
+    pub(crate) fn with_templates(mut self, templates: TemplateMap) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    /// Applies execution options such as the `missingkey` behaviour.
+    pub fn with_options(mut self, options: RenderOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub(crate) fn with_dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Starts another pass of an async render. Budgets start afresh, so each
+    /// pass is limited like a synchronous render.
+    pub(crate) fn with_async_calls(mut self, mut calls: AsyncCalls) -> Self {
+        calls.position = 0;
+        self.async_calls = Some(calls);
+        self
+    }
+
+    pub(crate) fn into_async_calls(self) -> Option<AsyncCalls> {
+        self.async_calls
+    }
+
+    /// Looks up the result of async helper `name` for `args`. Unknown results
+    /// are recorded for [`Template::render_async`](crate::Template::render_async)
+    /// to await, and stop the current pass.
+    fn async_result(&mut self, name: &str, args: &[Value]) -> Result<Value, Error> {
+        let max = self
+            .options
+            .limit(Limit::AsyncCalls)
+            .unwrap_or(MAX_ASYNC_CALLS);
+        let Some(calls) = self.async_calls.as_mut() else {
+            return Err(Error::render(
+                "async helper requires Template::render_async",
+                None,
+            ));
+        };
+        // `call_helper` started this call last.
+        let position = calls.position - 1;
+        let resolved = calls.results.len();
+        let key = (name.to_string(), Value::Array(args.to_vec()).to_string());
+        match calls.results.get_mut(&key) {
+            Some(Ok(value)) => Ok(value.clone()),
+            // The first read takes the error with its source; should a helper
+            // swallow it and call again, later reads get a copy.
+            Some(result) => {
+                let copy = replay(result);
+                mem::replace(result, copy)
+            }
+            None if calls.awaited.is_some_and(|awaited| position <= awaited) => {
+                Err(Error::async_arguments_changed(name, None))
+            }
+            None if calls.pending.is_none() && resolved >= max => {
+                Err(Error::limit_exceeded(Limit::AsyncCalls, max, None))
+            }
+            None => {
+                calls
+                    .pending
+                    .get_or_insert_with(|| (name.to_string(), args.to_vec(), position));
+                Err(Error::render("waiting for async helper", None))
+            }
         }
     }
 
+    /// Returns the dialect of the template being executed.
+    pub fn dialect(&self) -> Dialect {
+        self.dialect
+    }
+
+    /// Returns the execution options of the current render.
+    pub fn options(&self) -> &RenderOptions {
+        &self.options
+    }
+
+    /// Records the span of the node being executed so evaluation errors can
+    /// point at it.
+    pub(crate) fn set_span(&mut self, span: Span) {
+        self.span = Some(span);
+    }
+
+    /// Runs `eval` with `span` as the current location (when known) and points
+    /// any error that lacks a location at it.
+    fn at<T>(
+        &mut self,
+        span: Option<Span>,
+        eval: impl FnOnce(&mut Self) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let saved = self.span;
+        if span.is_some() {
+            self.span = span;
+        }
+        let result = match self.span {
+            Some(current) => eval(self).map_err(|err| err.with_span(current)),
+            None => eval(self),
+        };
+        self.span = saved;
+        result
+    }
+
+    /// Calls helper `name` after charging it to the render's budgets. Passes
+    /// of an async render replay calls an earlier pass completed instead.
+    fn call_helper(
+        &mut self,
+        name: &str,
+        command: Option<&Command>,
+        call: impl FnOnce(&mut Self) -> Result<Value, Error>,
+    ) -> Result<Value, Error> {
+        self.check_cancelled()?;
+        self.helper_calls += 1;
+        self.check_limit(Limit::HelperCalls, self.helper_calls, self.span)?;
+        let Some(calls) = self.async_calls.as_mut() else {
+            return self.invoke_helper(name, command, call);
+        };
+        let position = match calls.start_call(name) {
+            HelperCall::Replayed(result) => return result,
+            HelperCall::Started(position) => position,
+        };
+        let result = self.invoke_helper(name, command, call);
+        if let Some(calls) = self.async_calls.as_mut() {
+            calls.finish_call(position, name, &result);
+        }
+        result
+    }
+
+    /// Runs helper `name` and wraps errors that carry no location the way Go
+    /// does, as `error calling name: ...` pointing at the command, or at the
+    /// argument named by [`Error::with_argument`].
+    fn invoke_helper(
+        &mut self,
+        name: &str,
+        command: Option<&Command>,
+        call: impl FnOnce(&mut Self) -> Result<Value, Error>,
+    ) -> Result<Value, Error> {
+        let value = call(self).map_err(|err| {
+            if err.span().is_some() {
+                return err;
+            }
+            let span = err
+                .argument()
+                .and_then(|index| command?.arg_span(index))
+                .or(self.span);
+            match err {
+                Error::Parse {
+                    message, source, ..
+                }
+                | Error::Render {
+                    message, source, ..
+                }
+                | Error::Write {
+                    message, source, ..
+                } => Error::Render {
+                    message: format!("error calling {name}: {message}"),
+                    source,
+                    span,
+                    argument: None,
+                    template: None,
+                },
+                // Limits and cancellation keep their type; the caller points
+                // them at the command.
+                Error::LimitExceeded { .. }
+                | Error::Cancelled { .. }
+                | Error::AsyncArgumentsChanged { .. } => err,
+            }
+        })?;
+        if let Some(max) = self.options.limit(Limit::ValueSize) {
+            if value_exceeds(&value, max) {
+                return Err(Error::limit_exceeded(Limit::ValueSize, max, self.span));
+            }
+        }
+        Ok(value)
+    }
+
+    /// Returns how much of `limit` the render may still use, or `None` when
+    /// it is unlimited. For [`Limit::ValueSize`] this is the largest value a
+    /// helper may return. Helpers that build large values, such as repeated
+    /// strings, check their budget before allocating.
+    pub fn remaining(&self, limit: Limit) -> Option<usize> {
+        let max = self.options.limit(limit)?;
+        let used = match limit {
+            Limit::OutputBytes => self.output_bytes,
+            Limit::Iterations => self.iterations,
+            Limit::Depth => self.depth,
+            Limit::HelperCalls => self.helper_calls,
+            Limit::ValueSize => 0,
+            Limit::AsyncCalls => self
+                .async_calls
+                .as_ref()
+                .map_or(0, |calls| calls.results.len()),
+        };
+        Some(max.saturating_sub(used))
+    }
+
+    /// Fails with [`Error::LimitExceeded`] when a value of `size` would exceed
+    /// [`Limit::ValueSize`].
+    pub fn ensure_value_size(&self, size: usize) -> Result<(), Error> {
+        self.check_limit(Limit::ValueSize, size, self.span)
+    }
+
+    /// Fails with [`Error::Cancelled`] once the render's cancellation token is
+    /// cancelled or its deadline has passed. The evaluator checks between
+    /// nodes, iterations and helper calls; long-running helpers can call this
+    /// to stop early as well.
+    pub fn check_cancelled(&self) -> Result<(), Error> {
+        match self.options.cancel_reason() {
+            Some(reason) => Err(Error::cancelled(reason, self.span)),
+            None => Ok(()),
+        }
+    }
+
+    /// Accounts for `bytes` of output about to be written by the node at `span`.
+    pub(crate) fn charge_output(&mut self, bytes: usize, span: Span) -> Result<(), Error> {
+        self.output_bytes = self.output_bytes.saturating_add(bytes);
+        self.check_limit(Limit::OutputBytes, self.output_bytes, Some(span))
+    }
+
+    /// Accounts for one iteration of the `range` at `span`.
+    pub(crate) fn charge_iteration(&mut self, span: Span) -> Result<(), Error> {
+        if let Some(reason) = self.options.cancel_reason() {
+            return Err(Error::cancelled(reason, Some(span)));
+        }
+        self.iterations += 1;
+        self.check_limit(Limit::Iterations, self.iterations, Some(span))
+    }
+
+    fn check_limit(&self, limit: Limit, used: usize, span: Option<Span>) -> Result<(), Error> {
+        match self.options.limit(limit) {
+            Some(max) if used > max => Err(Error::limit_exceeded(limit, max, span)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Formats an action's value for output. Like Go, a missing or `null` value
+    /// prints as `<no value>` unless `missingkey=zero` is in effect.
+    pub(crate) fn format_value(&self, value: &Value) -> String {
+        if value.is_null() && self.options.missing_key() != MissingKey::Zero {
+            return "<no value>".to_string();
+        }
+        value_to_string(value)
+    }
+
     /// Retrieves a helper function by name, if registered.
     pub fn function(&self, name: &str) -> Option<Arc<Function>> {
         self.functions.get(name)
     }
 
+    /// Reports whether a named template is associated with the current execution.
+    pub fn has_template(&self, name: &str) -> bool {
+        self.templates.contains_key(name)
+    }
+
+    /// Executes an associated named template with `data` as dot and returns its
+    /// output, which lets helpers such as `include` capture a partial as a string.
+    pub fn execute_template(&mut self, name: &str, data: Value) -> Result<String, Error> {
+        let mut output = String::new();
+        crate::Template::render_template_call(self, name, data, None, &mut output)?;
+        Ok(output)
+    }
+
+    pub(crate) fn template(&self, name: &str) -> Option<Arc<Ast>> {
+        self.templates.get(name).cloned()
+    }
+
+    /// Switches to a fresh variable scope where both dot and `$` refer to `data`,
+    /// mirroring how Go executes `{{template}}` invocations.
+    pub(crate) fn enter_template(
+        &mut self,
+        data: Value,
+        span: Option<Span>,
+    ) -> Result<SavedFrame<'data>, Error> {
+        let max = self
+            .options
+            .limit(Limit::Depth)
+            .unwrap_or(MAX_TEMPLATE_DEPTH);
+        if self.depth >= max {
+            return Err(Error::limit_exceeded(Limit::Depth, max, span));
+        }
+        self.depth += 1;
+
+        Ok(SavedFrame {
+            stack: mem::replace(&mut self.stack, vec![Cow::Owned(data)]),
+            variables: mem::replace(&mut self.variables, vec![HashMap::new()]),
+        })
+    }
+
+    /// Restores the caller state captured by [`EvalContext::enter_template`].
+    pub(crate) fn exit_template(&mut self, saved: SavedFrame<'data>) {
+        self.stack = saved.stack;
+        self.variables = saved.variables;
+        self.depth -= 1;
+    }
+
     /// Pushes a new scope with the provided value at the top of the stack.
     pub fn push_scope(&mut self, value: Value) {
+        self.push_scope_cow(Cow::Owned(value));
+    }
+
+    /// Pushes a scope whose dot may borrow from the render data.
+    pub(crate) fn push_scope_cow(&mut self, value: Cow<'data, Value>) {
         self.stack.push(value);
-        self.variables.push(self.new_scope());
+        self.variables.push(HashMap::new());
     }
 
     /// Pops the current scope, restoring the previous context.
@@ -142,18 +829,12 @@ impl EvalContext {
         }
     }
 
-    fn new_scope(&self) -> HashMap<String, Value> {
-        let mut scope = HashMap::new();
-        scope.insert("$".to_string(), self.root.clone());
-        scope
-    }
-
     /// Evaluates a pipeline in the context and returns the resulting value.
     pub fn eval_pipeline(&mut self, pipeline: &Pipeline) -> Result<Value, Error> {
         let mut iter = pipeline.commands.iter();
         let first = iter
             .next()
-            .ok_or_else(|| Error::render("empty pipeline", None))?;
+            .ok_or_else(|| Error::render("empty pipeline", self.span))?;
         let mut value = self.eval_command(first, None)?;
 
         for command in iter {
@@ -163,10 +844,42 @@ impl EvalContext {
         Ok(value)
     }
 
+    /// Evaluates `pipeline` like [`EvalContext::eval_pipeline`], but borrows
+    /// the result when the pipeline is just a path into the render data.
+    pub(crate) fn eval_pipeline_cow(
+        &mut self,
+        pipeline: &Pipeline,
+    ) -> Result<Cow<'data, Value>, Error> {
+        if let [command] = pipeline.commands.as_slice() {
+            if let (Expression::Field(parts), true) = (&command.target, command.args.is_empty()) {
+                return self.at(command.span, |ctx| ctx.resolve_field_cow(parts));
+            }
+        }
+        self.eval_pipeline(pipeline).map(Cow::Owned)
+    }
+
     fn eval_command(&mut self, command: &Command, input: Option<Value>) -> Result<Value, Error> {
-        let resolution = self.resolve_command_target(command);
-        let args = self.prepare_command_args(command, input, &resolution)?;
-        self.execute_prepared_command(command, resolution, args)
+        self.at(command.span, |ctx| {
+            if let Expression::Identifier(name) = &command.target {
+                if let Some(func) = ctx.functions.get_lazy(name) {
+                    return ctx.call_helper(name, Some(command), |ctx| {
+                        func(ctx, LazyArgs::from_command(command, input.as_ref()))
+                    });
+                }
+            }
+            if !command.args.is_empty() || input.is_some() {
+                if let Some((receiver, name)) = ctx.eval_method_receiver(&command.target)? {
+                    if let Some(method) = ctx.functions.method(&receiver, name) {
+                        let args = ctx.eval_command_args(command, input)?;
+                        return ctx
+                            .call_helper(name, Some(command), |ctx| method(ctx, &receiver, &args));
+                    }
+                }
+            }
+            let resolution = ctx.resolve_command_target(command);
+            let args = ctx.prepare_command_args(command, input, &resolution)?;
+            ctx.execute_prepared_command(command, resolution, args)
+        })
     }
 
     fn resolve_command_target(&self, command: &Command) -> CommandResolution {
@@ -188,20 +901,13 @@ impl EvalContext {
         resolution: &CommandResolution,
     ) -> Result<Vec<Value>, Error> {
         match resolution {
-            CommandResolution::Function(_) => {
-                let mut args =
-                    Vec::with_capacity(command.args.len() + usize::from(input.is_some()));
-                for expr in &command.args {
-                    args.push(self.eval_expression(expr)?);
-                }
-                if let Some(prev) = input {
-                    args.push(prev);
-                }
-                Ok(args)
-            }
+            CommandResolution::Function(_) => self.eval_command_args(command, input),
             CommandResolution::Identifier(name) => {
                 if !command.args.is_empty() || input.is_some() {
-                    return Err(Error::render(format!("unknown function \"{name}\""), None));
+                    return Err(Error::render(
+                        format!("unknown function \"{name}\""),
+                        self.span,
+                    ));
                 }
                 Ok(Vec::new())
             }
@@ -209,13 +915,13 @@ impl EvalContext {
                 if !command.args.is_empty() {
                     return Err(Error::render(
                         "arguments supplied to non-function expression",
-                        None,
+                        self.span,
                     ));
                 }
                 if input.is_some() {
                     return Err(Error::render(
                         "cannot pipe value into non-function expression",
-                        None,
+                        self.span,
                     ));
                 }
                 Ok(Vec::new())
@@ -223,6 +929,60 @@ impl EvalContext {
         }
     }
 
+    /// Evaluates the arguments of `command`, followed by the piped-in value.
+    fn eval_command_args(
+        &mut self,
+        command: &Command,
+        input: Option<Value>,
+    ) -> Result<Vec<Value>, Error> {
+        let mut args = Vec::with_capacity(command.args.len() + usize::from(input.is_some()));
+        for (index, expr) in command.args.iter().enumerate() {
+            let value = self.at(command.arg_span(index), |ctx| ctx.eval_expression(expr))?;
+            args.push(value);
+        }
+        if let Some(prev) = input {
+            args.push(prev);
+        }
+        Ok(args)
+    }
+
+    /// Splits a field path used as a command target into the value its last
+    /// segment is read from and that segment, which may name a method taking
+    /// the command's arguments. The receiver is only evaluated when a method
+    /// of that name is registered, so other targets keep their field semantics.
+    fn eval_method_receiver<'a>(
+        &mut self,
+        target: &'a Expression,
+    ) -> Result<Option<(Cow<'data, Value>, &'a str)>, Error> {
+        let is_method = |name: &str| self.functions.methods.contains_key(name);
+        match target {
+            Expression::Field(parts) => match parts.split_last() {
+                Some((name, prefix)) if !name.starts_with('$') && is_method(name) => {
+                    Ok(Some((self.resolve_field_cow(prefix)?, name)))
+                }
+                _ => Ok(None),
+            },
+            Expression::Chain { target, fields } => {
+                let Some((name, prefix)) = fields.split_last().filter(|(name, _)| is_method(name))
+                else {
+                    return Ok(None);
+                };
+                let mut value = Cow::Owned(self.eval_expression(target)?);
+                for (idx, field) in prefix.iter().enumerate() {
+                    value = self.project_field_segment(value, field, || {
+                        format!(
+                            "{}.{}",
+                            crate::expression_to_string(target),
+                            fields[..=idx].join(".")
+                        )
+                    })?;
+                }
+                Ok(Some((value, name)))
+            }
+            _ => Ok(None),
+        }
+    }
+
     fn execute_prepared_command(
         &mut self,
         command: &Command,
@@ -230,7 +990,13 @@ impl EvalContext {
         args: Vec<Value>,
     ) -> Result<Value, Error> {
         match resolution {
-            CommandResolution::Function(func) => func(self, &args),
+            CommandResolution::Function(func) => {
+                let name = match &command.target {
+                    Expression::Identifier(name) => name.as_str(),
+                    _ => "function",
+                };
+                self.call_helper(name, Some(command), |ctx| func(ctx, &args))
+            }
             CommandResolution::Identifier(_) | CommandResolution::Expression => {
                 debug_assert!(args.is_empty());
                 self.eval_expression(&command.target)
@@ -238,88 +1004,118 @@ impl EvalContext {
         }
     }
 
-    fn eval_expression(&mut self, expr: &Expression) -> Result<Value, Error> {
+    pub(crate) fn eval_expression(&mut self, expr: &Expression) -> Result<Value, Error> {
         match expr {
-            Expression::Identifier(name) => Ok(self.resolve_identifier(name)),
+            Expression::Identifier(name) if self.dialect.is_go() => {
+                // Go has no bare data lookups: identifiers always name functions.
+                let func = self.functions.get(name).ok_or_else(|| {
+                    Error::render(format!("function {name:?} not defined"), self.span)
+                })?;
+                func(self, &[])
+            }
+            Expression::Identifier(name) => self.resolve_identifier(name),
             Expression::Field(parts) => self.resolve_field(parts),
             Expression::Variable(name) => Ok(self.resolve_variable(name)),
             Expression::PipelineExpr(pipeline) => {
                 if pipeline.declarations.is_some() {
                     return Err(Error::render(
                         "pipeline declarations not allowed in expression",
-                        None,
+                        self.span,
                     ));
                 }
                 self.eval_pipeline(pipeline)
             }
+            Expression::Chain { target, fields } => {
+                let mut value = Cow::Owned(self.eval_expression(target)?);
+                for (idx, field) in fields.iter().enumerate() {
+                    value = self.project_field_segment(value, field, || {
+                        format!(
+                            "{}.{}",
+                            crate::expression_to_string(target),
+                            fields[..=idx].join(".")
+                        )
+                    })?;
+                }
+                Ok(value.into_owned())
+            }
             Expression::StringLiteral(value) => Ok(Value::String(value.clone())),
-            Expression::NumberLiteral(text) => parse_number(text)
-                .map(Value::Number)
-                .ok_or_else(|| Error::render(format!("invalid number literal {text}"), None)),
+            Expression::NumberLiteral(text) => parse_number(text).map(Value::Number),
             Expression::BoolLiteral(flag) => Ok(Value::Bool(*flag)),
             Expression::Nil => Ok(Value::Null),
         }
     }
 
-    fn resolve_identifier(&self, name: &str) -> Value {
+    fn resolve_identifier(&self, name: &str) -> Result<Value, Error> {
         for value in self.stack.iter().rev() {
-            if let Value::Object(map) = value {
+            if let Value::Object(map) = &**value {
                 if let Some(found) = map.get(name) {
-                    return found.clone();
+                    return Ok(found.clone());
                 }
             }
         }
-        Value::Null
+        self.missing_value(name, || name.to_string())
+    }
+
+    fn resolve_field(&mut self, parts: &[String]) -> Result<Value, Error> {
+        self.resolve_field_cow(parts).map(Cow::into_owned)
     }
 
-    fn resolve_field(&self, parts: &[String]) -> Result<Value, Error> {
+    fn resolve_field_cow(&mut self, parts: &[String]) -> Result<Cow<'data, Value>, Error> {
         if parts.is_empty() {
-            return self
-                .stack
-                .last()
-                .cloned()
-                .ok_or_else(|| Error::render("dot resolution failed", None));
+            return self.dot();
         }
         if let Some(first) = parts.first() {
             if first.starts_with('$') {
-                let mut value = self.resolve_variable(first);
-                for part in parts.iter().skip(1) {
-                    value = Self::project_field_segment(value, part)?;
+                let mut value = self.variable(first);
+                for (idx, part) in parts.iter().enumerate().skip(1) {
+                    value = self.project_field_segment(value, part, || parts[..=idx].join("."))?;
                 }
                 return Ok(value);
             }
         }
 
-        let mut value = self
-            .stack
-            .last()
-            .cloned()
-            .ok_or_else(|| Error::render("dot resolution failed", None))?;
-
-        for part in parts {
-            value = Self::project_field_segment(value, part)?;
+        let mut value = self.dot()?;
+        for (idx, part) in parts.iter().enumerate() {
+            value = self
+                .project_field_segment(value, part, || format!(".{}", parts[..=idx].join(".")))?;
         }
 
         Ok(value)
     }
 
+    /// Returns dot, borrowing it when it is (part of) the render data.
+    fn dot(&self) -> Result<Cow<'data, Value>, Error> {
+        self.stack
+            .last()
+            .cloned()
+            .ok_or_else(|| Error::render("dot resolution failed", self.span))
+    }
+
     fn resolve_variable(&self, name: &str) -> Value {
+        self.variable(name).into_owned()
+    }
+
+    fn variable(&self, name: &str) -> Cow<'data, Value> {
         if name == "$" {
-            return self.root.clone();
+            return self
+                .stack
+                .first()
+                .cloned()
+                .unwrap_or(Cow::Owned(Value::Null));
         }
 
         for scope in self.variables.iter().rev() {
             if let Some(value) = scope.get(name) {
-                return value.clone();
+                return Cow::Owned(value.clone());
             }
         }
 
-        Value::Null
+        Cow::Owned(Value::Null)
     }
 
     fn set_variable(&mut self, name: &str, kind: BindingKind, value: Value) -> Result<(), Error> {
         if name == "$" {
-            return Err(Error::render("cannot assign to root variable", None));
+            return Err(Error::render("cannot assign to root variable", self.span));
         }
 
         match kind {
@@ -337,23 +1133,65 @@ impl EvalContext {
                         return Ok(());
                     }
                 }
-                Err(Error::render(format!("variable {name} not defined"), None))
+                Err(Error::render(
+                    format!("variable {name} not defined"),
+                    self.span,
+                ))
             }
         }
     }
 
-    fn project_field_segment(value: Value, part: &str) -> Result<Value, Error> {
-        match value {
-            Value::Object(map) => Ok(map.get(part).cloned().unwrap_or(Value::Null)),
-            Value::Array(list) => {
+    /// Reads `part` from `value`, calling a method of that name registered for
+    /// the value instead when there is one; `path` describes the accessed path
+    /// for missing-key errors. Borrowed values yield borrowed parts.
+    fn project_field_segment(
+        &mut self,
+        value: Cow<'data, Value>,
+        part: &str,
+        path: impl FnOnce() -> String,
+    ) -> Result<Cow<'data, Value>, Error> {
+        if let Some(method) = self.functions.method(&value, part) {
+            return self
+                .call_helper(part, None, |ctx| method(ctx, &value, &[]))
+                .map(Cow::Owned);
+        }
+        match &*value {
+            Value::Object(_) => match entry(value, part) {
+                Some(found) => Ok(found),
+                None => self.missing_value(part, path).map(Cow::Owned),
+            },
+            Value::Null => {
+                if self.options.missing_key() == MissingKey::Error {
+                    return Err(Error::render(
+                        format!("nil data; no entry for key {part:?} in {}", path()),
+                        self.span,
+                    ));
+                }
+                Ok(Cow::Owned(Value::Null))
+            }
+            Value::Array(_) => {
                 let index = part.parse::<usize>().map_err(|_| {
-                    Error::render(format!("array index must be integer, got {part}"), None)
+                    Error::render(
+                        format!("array index must be integer, got {part}"),
+                        self.span,
+                    )
                 })?;
-                Ok(list.get(index).cloned().unwrap_or(Value::Null))
+                Ok(element(value, index).unwrap_or(Cow::Owned(Value::Null)))
             }
             _ => Err(Error::render(
                 format!("cannot access field {part} on non-container value"),
-                None,
+                self.span,
+            )),
+        }
+    }
+
+    /// Applies the `missingkey` option to a lookup of `key` that found nothing.
+    fn missing_value(&self, key: &str, path: impl FnOnce() -> String) -> Result<Value, Error> {
+        match self.options.missing_key() {
+            MissingKey::Default | MissingKey::Zero => Ok(Value::Null),
+            MissingKey::Error => Err(Error::render(
+                format!("map has no entry for key {key:?} in {}", path()),
+                self.span,
             )),
         }
     }
@@ -398,23 +1236,25 @@ impl EvalContext {
         }
     }
 
+    /// Binds the variables a range declares; `value` is copied only when a
+    /// variable takes it.
     pub(crate) fn assign_range_bindings(
         &mut self,
         pipeline: &Pipeline,
         key: Option<Value>,
-        value: Value,
+        value: &Value,
     ) -> Result<(), Error> {
         if let Some(decls) = &pipeline.declarations {
             match decls.variables.len() {
                 0 => {}
                 1 => {
-                    self.set_variable(&decls.variables[0], decls.kind, value)?;
+                    self.set_variable(&decls.variables[0], decls.kind, value.clone())?;
                 }
                 _ => {
                     let key_value = key.unwrap_or(Value::Null);
                     self.set_variable(&decls.variables[0], decls.kind, key_value)?;
                     if let Some(second) = decls.variables.get(1) {
-                        self.set_variable(second, decls.kind, value)?;
+                        self.set_variable(second, decls.kind, value.clone())?;
                     }
                 }
             }
@@ -423,6 +1263,52 @@ impl EvalContext {
     }
 }
 
+/// Reports whether the size of `value`, as measured for [`Limit::ValueSize`],
+/// exceeds `max`. Stops walking the value as soon as it does.
+fn value_exceeds(value: &Value, max: usize) -> bool {
+    fn fits(value: &Value, budget: &mut usize) -> bool {
+        let cost = match value {
+            Value::String(text) => text.len(),
+            Value::Array(_) | Value::Object(_) => 0,
+            Value::Null | Value::Bool(_) | Value::Number(_) => 1,
+        };
+        let Some(rest) = budget.checked_sub(cost) else {
+            return false;
+        };
+        *budget = rest;
+        match value {
+            Value::Array(items) => items.iter().all(|item| fits(item, budget)),
+            Value::Object(map) => map.iter().all(|(key, item)| {
+                budget.checked_sub(key.len()).is_some_and(|rest| {
+                    *budget = rest;
+                    fits(item, budget)
+                })
+            }),
+            _ => true,
+        }
+    }
+    !fits(value, &mut { max })
+}
+
+/// Takes `key` out of an object, moving it out of owned values and borrowing
+/// it from borrowed ones.
+fn entry<'v>(value: Cow<'v, Value>, key: &str) -> Option<Cow<'v, Value>> {
+    match value {
+        Cow::Borrowed(value) => value.get(key).map(Cow::Borrowed),
+        Cow::Owned(Value::Object(mut map)) => map.remove(key).map(Cow::Owned),
+        Cow::Owned(_) => None,
+    }
+}
+
+/// Takes the item at `index` out of an array; see [`entry`].
+fn element<'v>(value: Cow<'v, Value>, index: usize) -> Option<Cow<'v, Value>> {
+    match value {
+        Cow::Borrowed(value) => value.get(index).map(Cow::Borrowed),
+        Cow::Owned(Value::Array(items)) => items.into_iter().nth(index).map(Cow::Owned),
+        Cow::Owned(_) => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -436,6 +1322,42 @@ mod tests {
         FunctionRegistry::from_builder(builder)
     }
 
+    #[test]
+    fn lazy_function_receives_unevaluated_arguments() {
+        let mut builder = FunctionRegistry::builder();
+        builder.register_lazy("first", |ctx, args| {
+            assert_eq!(args.len(), 3);
+            args.eval(ctx, 0)
+        });
+        builder.register("fail", |_, _| Err(Error::render("evaluated", None)));
+        let registry = FunctionRegistry::from_builder(builder);
+        let mut ctx = EvalContext::new(json!({"x": 1}), registry.clone());
+        let command = Command::new(
+            Expression::Identifier("first".into()),
+            vec![
+                Expression::Field(vec!["x".into()]),
+                Expression::PipelineExpr(Pipeline::new(
+                    None,
+                    vec![Command::new(
+                        Expression::Identifier("fail".into()),
+                        Vec::new(),
+                    )],
+                )),
+            ],
+        );
+
+        let value = ctx
+            .eval_command(&command, Some(json!("piped")))
+            .expect("unused argument must not be evaluated");
+        assert_eq!(value, json!(1));
+
+        let eager = registry
+            .get("first")
+            .expect("lazy helper is callable eagerly");
+        let value = eager(&mut ctx, &[json!("a"), json!("b"), json!("c")]).unwrap();
+        assert_eq!(value, json!("a"));
+    }
+
     #[test]
     fn resolve_command_target_detects_function() {
         let registry = registry_with_echo();
@@ -535,6 +1457,82 @@ mod tests {
             .to_string()
             .contains("cannot pipe value into non-function expression"));
     }
+
+    #[test]
+    fn borrowed_data_is_read_in_place() {
+        let data = json!({"user": {"tags": ["a", "b"]}});
+        let mut ctx = EvalContext::with_data(Cow::Borrowed(&data), FunctionRegistry::empty());
+        let dot = ctx.dot().unwrap();
+        assert!(std::ptr::eq(&*dot, &data));
+
+        let user = ctx.project_field_segment(dot, "user", String::new).unwrap();
+        let tags = ctx
+            .project_field_segment(user, "tags", String::new)
+            .unwrap();
+        let tag = ctx.project_field_segment(tags, "1", String::new).unwrap();
+        assert!(
+            matches!(tag, Cow::Borrowed(value) if std::ptr::eq(value, &data["user"]["tags"][1]))
+        );
+
+        let path = Expression::Field(vec!["user".to_string(), "tags".to_string()]);
+        let pipeline = Pipeline::new(None, vec![Command::new(path, Vec::new())]);
+        let tags = ctx.eval_pipeline_cow(&pipeline).unwrap();
+        assert!(matches!(tags, Cow::Borrowed(value) if std::ptr::eq(value, &data["user"]["tags"])));
+        let Cow::Borrowed(Value::Array(items)) = tags else {
+            unreachable!()
+        };
+        ctx.push_scope_cow(Cow::Borrowed(&items[0]));
+        assert!(std::ptr::eq(&*ctx.dot().unwrap(), &data["user"]["tags"][0]));
+        ctx.pop_scope();
+
+        ctx.push_scope(json!({"name": "owned"}));
+        let name = ctx
+            .project_field_segment(ctx.dot().unwrap(), "name", String::new)
+            .unwrap();
+        assert!(matches!(name, Cow::Owned(Value::String(name)) if name == "owned"));
+    }
+
+    #[test]
+    fn parse_number_follows_go_literal_grammar() {
+        let int = |text: &str| parse_number(text).unwrap().as_i64();
+        assert_eq!(int("0x1F"), Some(31));
+        assert_eq!(int("0o17"), Some(15));
+        assert_eq!(int("017"), Some(15));
+        assert_eq!(int("0b101"), Some(5));
+        assert_eq!(int("1_000_000"), Some(1_000_000));
+        assert_eq!(int("-0x_10"), Some(-16));
+        assert_eq!(int("'a'"), Some(97));
+        assert_eq!(int("'\\u00e9'"), Some(0xe9));
+        assert_eq!(int("-9223372036854775808"), Some(i64::MIN));
+        assert_eq!(
+            parse_number("18446744073709551615").unwrap().as_u64(),
+            Some(u64::MAX)
+        );
+
+        let float = |text: &str| parse_number(text).unwrap().as_f64();
+        assert_eq!(float("017.5"), Some(17.5));
+        assert_eq!(float("1e3"), Some(1000.0));
+        assert_eq!(float("1_0.2_5"), Some(10.25));
+        assert_eq!(float("0x1p-2"), Some(0.25));
+        assert_eq!(float("0x1.8p1"), Some(3.0));
+
+        for (text, message) in [
+            ("18446744073709551616", "integer overflow"),
+            ("-9223372036854775809", "integer overflow"),
+            ("1e400", "float overflow"),
+            ("08", "illegal number syntax"),
+            ("1__0", "illegal number syntax"),
+            ("1_", "illegal number syntax"),
+            ("0x", "illegal number syntax"),
+            ("0x1.8", "illegal number syntax"),
+            ("'ab'", "malformed character constant"),
+            ("'\\q'", "malformed character constant"),
+            ("2i", "complex number literals are not supported"),
+        ] {
+            let err = parse_number(text).expect_err(text);
+            assert!(err.to_string().contains(message), "{text}: {err}");
+        }
+    }
 }
 
 pub fn value_to_string(value: &Value) -> String {
@@ -564,17 +1562,118 @@ pub fn value_to_string(value: &Value) -> String {
     }
 }
 
-pub fn parse_number(text: &str) -> Option<Number> {
-    if !text.contains(['.', 'e', 'E']) {
-        if let Ok(value) = text.parse::<i64>() {
-            return Some(Number::from(value));
+/// Converts a number literal into a JSON number, following Go's grammar:
+/// signed decimal, hex (`0x1F`), octal (`0o17`, `017`) and binary (`0b101`)
+/// integers, `_` digit separators, decimal and hex floats (`1e3`, `0x1p-2`)
+/// and character constants (`'a'`). Imaginary literals are valid Go but have
+/// no JSON representation, so they are rejected.
+pub fn parse_number(text: &str) -> Result<Number, Error> {
+    let illegal = || Error::parse(format!("illegal number syntax: {text:?}"), None);
+    if text.starts_with('\'') {
+        let ch = crate::lexer::unquote_char(text)?;
+        return Ok(Number::from(u32::from(ch)));
+    }
+    if text.ends_with('i') {
+        return Err(Error::parse(
+            format!("complex number literals are not supported: {text:?}"),
+            None,
+        ));
+    }
+
+    let (negative, body) = match text.as_bytes().first() {
+        Some(b'-') => (true, &text[1..]),
+        Some(b'+') => (false, &text[1..]),
+        _ => (false, text),
+    };
+    let (radix, digits, prefixed) = split_radix(body);
+    if digits.is_empty() || !underscores_ok(digits, radix, prefixed) {
+        return Err(illegal());
+    }
+    let cleaned = digits.replace('_', "");
+
+    let float = match radix {
+        16 => cleaned.contains(['.', 'p', 'P']),
+        10 => cleaned.contains(['.', 'e', 'E']),
+        _ => false,
+    };
+    if float {
+        let value = if radix == 16 {
+            parse_hex_float(&cleaned).ok_or_else(illegal)?
+        } else {
+            cleaned.parse::<f64>().map_err(|_| illegal())?
+        };
+        if !value.is_finite() {
+            return Err(Error::parse(format!("float overflow: {text:?}"), None));
         }
-        if let Ok(value) = text.parse::<u64>() {
-            return Some(Number::from(value));
+        let value = if negative { -value } else { value };
+        return Number::from_f64(value).ok_or_else(illegal);
+    }
+
+    let magnitude = u64::from_str_radix(&cleaned, radix).map_err(|err| {
+        if matches!(err.kind(), std::num::IntErrorKind::PosOverflow) {
+            Error::parse(format!("integer overflow: {text:?}"), None)
+        } else {
+            illegal()
         }
+    })?;
+    if negative {
+        let value = i64::try_from(-i128::from(magnitude))
+            .map_err(|_| Error::parse(format!("integer overflow: {text:?}"), None))?;
+        Ok(Number::from(value))
+    } else {
+        Ok(Number::from(magnitude))
     }
+}
 
-    text.parse::<f64>().ok().and_then(Number::from_f64)
+/// Splits a base prefix off an unsigned literal, returning the radix, the
+/// remaining digits and whether a prefix was present.
+fn split_radix(body: &str) -> (u32, &str, bool) {
+    let bytes = body.as_bytes();
+    if bytes.len() > 1 && bytes[0] == b'0' {
+        match bytes[1] {
+            b'x' | b'X' => return (16, &body[2..], true),
+            b'o' | b'O' => return (8, &body[2..], true),
+            b'b' | b'B' => return (2, &body[2..], true),
+            // A leading zero means octal for integers only (`017`, not `017.5`).
+            _ if !body.contains(['.', 'e', 'E']) => return (8, &body[1..], true),
+            _ => {}
+        }
+    }
+    (10, body, false)
+}
+
+/// Reports whether every `_` sits between two digits (or directly after a
+/// base prefix), as Go requires.
+fn underscores_ok(digits: &str, radix: u32, prefixed: bool) -> bool {
+    let chars: Vec<char> = digits.chars().collect();
+    chars.iter().enumerate().all(|(idx, ch)| {
+        if *ch != '_' {
+            return true;
+        }
+        let before = if idx == 0 {
+            prefixed
+        } else {
+            chars[idx - 1].is_digit(radix)
+        };
+        let after = chars.get(idx + 1).is_some_and(|next| next.is_digit(radix));
+        before && after
+    })
+}
+
+/// Evaluates a hex float body such as `1.8p3`; Go requires the `p` exponent.
+fn parse_hex_float(body: &str) -> Option<f64> {
+    let (mantissa, exponent) = body.split_once(['p', 'P'])?;
+    let exponent: i32 = exponent.parse().ok()?;
+    let (int_part, frac_part) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if int_part.is_empty() && frac_part.is_empty() {
+        return None;
+    }
+    let mut value = 0f64;
+    for ch in int_part.chars().chain(frac_part.chars()) {
+        value = value * 16.0 + f64::from(ch.to_digit(16)?);
+    }
+    let frac_bits = i32::try_from(frac_part.len()).ok()?.checked_mul(4)?;
+    Some(value * 2f64.powi(exponent.saturating_sub(frac_bits)))
 }
 
 pub fn is_empty(value: &Value) -> bool {
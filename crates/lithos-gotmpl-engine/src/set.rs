@@ -0,0 +1,399 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use serde_json::Value;
+
+use crate::ast::{Ast, Block, Node, Span};
+use crate::error::Error;
+use crate::options::RenderOptions;
+use crate::parser::ParseOptions;
+use crate::runtime::{FunctionRegistry, TemplateMap};
+use crate::source_map::SourceMap;
+use crate::Template;
+
+/// Source of each named template, keyed like [`TemplateMap`].
+pub(crate) type SourceMaps = Arc<HashMap<String, Arc<SourceMap>>>;
+
+/// Collection of associated templates that can invoke one another by name.
+///
+/// This mirrors the association Go keeps behind `*template.Template`: every
+/// call to [`TemplateSet::parse`] adds the parsed template plus all of its
+/// `{{define}}` and `{{block}}` definitions to the set. Later parses may
+/// redefine existing names: a non-empty later definition wins, while an empty
+/// one never replaces a non-empty one. A `{{block}}` default therefore
+/// replaces an override that was parsed before it, as in Go.
+#[derive(Clone, Default)]
+pub struct TemplateSet {
+    trees: TemplateMap,
+    sources: SourceMaps,
+    functions: FunctionRegistry,
+    options: ParseOptions,
+}
+
+impl fmt::Debug for TemplateSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TemplateSet")
+            .field("templates", &self.names())
+            .finish()
+    }
+}
+
+impl TemplateSet {
+    /// Creates an empty set without helper functions.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Consumes the set and returns a new instance with the provided function registry.
+    pub fn with_functions(mut self, functions: FunctionRegistry) -> Self {
+        self.functions = functions;
+        self
+    }
+
+    /// Replaces the function registry shared by all templates in the set.
+    pub fn set_functions(&mut self, functions: FunctionRegistry) {
+        self.functions = functions;
+    }
+
+    /// Returns a clone of the function registry in use.
+    pub fn functions(&self) -> FunctionRegistry {
+        self.functions.clone()
+    }
+
+    /// Consumes the set and returns a new instance that parses with `options`.
+    pub fn with_parse_options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Replaces the options used by subsequent [`TemplateSet::parse`] calls,
+    /// e.g. to switch delimiters like Go's `Delims`. Templates that are
+    /// already associated keep the delimiters they were parsed with.
+    pub fn set_parse_options(&mut self, options: ParseOptions) {
+        self.options = options;
+    }
+
+    /// Returns the options used when parsing new templates.
+    pub fn parse_options(&self) -> &ParseOptions {
+        &self.options
+    }
+
+    /// Parses `source` as the template `name` and associates it, together with
+    /// every template it defines, with the set. The set is left untouched when
+    /// parsing fails.
+    pub fn parse(&mut self, name: &str, source: &str) -> Result<(), Error> {
+        let ast = crate::parse_ast(name, source, &self.functions, &self.options)?;
+        let mut trees = (*self.trees).clone();
+        let source = Arc::new(SourceMap::new(source));
+        let installed = associate_all(&mut trees, Arc::new(ast))?;
+        let sources = Arc::make_mut(&mut self.sources);
+        for name in installed {
+            sources.insert(name, source.clone());
+        }
+        self.trees = Arc::new(trees);
+        Ok(())
+    }
+
+    /// Returns the template associated with `name`, if any.
+    pub fn lookup(&self, name: &str) -> Option<Template> {
+        let tree = self.trees.get(name)?;
+        Some(Template::from_parts(
+            name,
+            self.sources.get(name).cloned().unwrap_or_default(),
+            tree.clone(),
+            self.functions.clone(),
+            self.trees.clone(),
+            self.sources.clone(),
+        ))
+    }
+
+    /// Returns every associated template, ordered by name.
+    pub fn templates(&self) -> Vec<Template> {
+        self.names()
+            .iter()
+            .filter_map(|name| self.lookup(name))
+            .collect()
+    }
+
+    /// Returns the sorted names of all associated templates.
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.trees.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Returns every associated tree with the source it was parsed from,
+    /// ordered by name.
+    #[cfg(feature = "artifact")]
+    pub(crate) fn trees(&self) -> Vec<(&Arc<Ast>, Option<&Arc<SourceMap>>)> {
+        let mut trees: Vec<_> = self.trees.iter().collect();
+        trees.sort_by(|a, b| a.0.cmp(b.0));
+        trees
+            .into_iter()
+            .map(|(name, tree)| (tree, self.sources.get(name)))
+            .collect()
+    }
+
+    #[cfg(feature = "artifact")]
+    pub(crate) fn from_parts(
+        trees: HashMap<String, Arc<Ast>>,
+        sources: HashMap<String, Arc<SourceMap>>,
+        functions: FunctionRegistry,
+        options: ParseOptions,
+    ) -> Self {
+        Self {
+            trees: Arc::new(trees),
+            sources: Arc::new(sources),
+            functions,
+            options,
+        }
+    }
+
+    /// Reports whether the set contains no templates.
+    pub fn is_empty(&self) -> bool {
+        self.trees.is_empty()
+    }
+
+    /// Renders the template `name` against the provided data, like Go's
+    /// `ExecuteTemplate`.
+    pub fn render(&self, name: &str, data: &Value) -> Result<String, Error> {
+        self.render_with_options(name, data, &RenderOptions::default())
+    }
+
+    /// Renders the template `name` with execution options such as `missingkey`.
+    pub fn render_with_options(
+        &self,
+        name: &str,
+        data: &Value,
+        options: &RenderOptions,
+    ) -> Result<String, Error> {
+        self.lookup(name)
+            .ok_or_else(|| Error::render(format!("no template {name:?} in set"), None))?
+            .render_with_options(data, options)
+    }
+
+    /// Renders the template `name` with async helpers; see
+    /// [`Template::render_async`].
+    pub async fn render_async(&self, name: &str, data: &Value) -> Result<String, Error> {
+        self.render_async_with_options(name, data, &RenderOptions::default())
+            .await
+    }
+
+    /// Variant of [`TemplateSet::render_async`] with execution options.
+    pub async fn render_async_with_options(
+        &self,
+        name: &str,
+        data: &Value,
+        options: &RenderOptions,
+    ) -> Result<String, Error> {
+        self.lookup(name)
+            .ok_or_else(|| Error::render(format!("no template {name:?} in set"), None))?
+            .render_async_with_options(data, options)
+            .await
+    }
+}
+
+/// Associates the parsed template and the definitions it contains with `trees`,
+/// returning the names that were installed.
+pub(crate) fn associate_all(
+    trees: &mut HashMap<String, Arc<Ast>>,
+    ast: Arc<Ast>,
+) -> Result<Vec<String>, Error> {
+    let mut parsed: Vec<(Arc<Ast>, Option<Span>)> = Vec::new();
+    collect_definitions(&ast.root, &ast, &mut parsed);
+    parsed.push((ast, None));
+
+    // Within a single parse, two non-empty definitions of the same name conflict.
+    let mut local: Vec<Arc<Ast>> = Vec::with_capacity(parsed.len());
+    for (tree, span) in parsed {
+        match local.iter_mut().find(|existing| existing.name == tree.name) {
+            None => local.push(tree),
+            Some(existing) if is_empty_tree(&existing.root) => *existing = tree,
+            Some(_) if !is_empty_tree(&tree.root) => {
+                return Err(Error::parse(
+                    format!("multiple definition of template {:?}", tree.name),
+                    span,
+                ));
+            }
+            Some(_) => {}
+        }
+    }
+
+    let mut installed = Vec::with_capacity(local.len());
+    for tree in local {
+        if trees.contains_key(&tree.name) && is_empty_tree(&tree.root) {
+            continue;
+        }
+        installed.push(tree.name.clone());
+        trees.insert(tree.name.clone(), tree);
+    }
+    Ok(installed)
+}
+
+fn collect_definitions(block: &Block, parent: &Ast, out: &mut Vec<(Arc<Ast>, Option<Span>)>) {
+    for node in &block.nodes {
+        match node {
+            Node::Define(define) => {
+                out.push((
+                    Arc::new(parent.nested(define.name.clone(), define.block.clone())),
+                    Some(define.span),
+                ));
+                collect_definitions(&define.block, parent, out);
+            }
+            Node::Block(block_node) => {
+                out.push((
+                    Arc::new(parent.nested(block_node.name.clone(), block_node.block.clone())),
+                    Some(block_node.span),
+                ));
+                collect_definitions(&block_node.block, parent, out);
+            }
+            Node::If(if_node) => {
+                collect_definitions(&if_node.then_block, parent, out);
+                for branch in &if_node.else_if_branches {
+                    collect_definitions(&branch.block, parent, out);
+                }
+                if let Some(else_block) = &if_node.else_block {
+                    collect_definitions(else_block, parent, out);
+                }
+            }
+            Node::Range(range_node) => {
+                collect_definitions(&range_node.then_block, parent, out);
+                if let Some(else_block) = &range_node.else_block {
+                    collect_definitions(else_block, parent, out);
+                }
+            }
+            Node::With(with_node) => {
+                collect_definitions(&with_node.then_block, parent, out);
+                if let Some(else_block) = &with_node.else_block {
+                    collect_definitions(else_block, parent, out);
+                }
+            }
+            Node::Text(_)
+            | Node::Action(_)
+            | Node::Comment(_)
+            | Node::Template(_)
+            | Node::Break(_)
+            | Node::Continue(_) => {}
+        }
+    }
+}
+
+/// Mirrors Go's `parse.IsEmptyTree`: only whitespace, comments, and
+/// definitions count as empty.
+fn is_empty_tree(block: &Block) -> bool {
+    block.nodes.iter().all(|node| match node {
+        Node::Text(text) => text.text.trim().is_empty(),
+        Node::Comment(_) | Node::Define(_) => true,
+        _ => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn template_invokes_definition_from_another_parse() {
+        let mut set = TemplateSet::new();
+        set.parse("partials", r#"{{define "greet"}}Hello, {{.}}!{{end}}"#)
+            .unwrap();
+        set.parse("page", r#"{{template "greet" .name}}"#).unwrap();
+
+        let rendered = set.render("page", &json!({"name": "Lithos"})).unwrap();
+        assert_eq!(rendered, "Hello, Lithos!");
+        assert_eq!(set.names(), vec!["greet", "page", "partials"]);
+    }
+
+    #[test]
+    fn later_non_empty_definition_wins() {
+        let base = r#"<{{block "body" .}}default{{end}}>"#;
+        let overlay = r#"{{define "body"}}custom{{end}}"#;
+
+        let mut first = TemplateSet::new();
+        first.parse("base", base).unwrap();
+        first.parse("overlay", overlay).unwrap();
+        assert_eq!(first.render("base", &json!({})).unwrap(), "<custom>");
+
+        let mut second = TemplateSet::new();
+        second.parse("overlay", overlay).unwrap();
+        assert_eq!(
+            second.lookup("body").unwrap().render(&json!({})).unwrap(),
+            "custom"
+        );
+        second.parse("base", base).unwrap();
+        assert_eq!(second.render("base", &json!({})).unwrap(), "<default>");
+    }
+
+    #[test]
+    fn empty_definition_does_not_replace_existing_template() {
+        let mut set = TemplateSet::new();
+        set.parse("a", "content").unwrap();
+        set.parse("b", r#"{{define "a"}} {{end}}"#).unwrap();
+        assert_eq!(set.render("a", &json!({})).unwrap(), "content");
+    }
+
+    #[test]
+    fn duplicate_definitions_in_one_parse_are_rejected() {
+        let mut set = TemplateSet::new();
+        let err = set
+            .parse("dup", r#"{{define "x"}}a{{end}}{{define "x"}}b{{end}}"#)
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("multiple definition of template \"x\""));
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn lookup_returns_none_for_unknown_template() {
+        let set = TemplateSet::new();
+        assert!(set.lookup("missing").is_none());
+        assert!(set.render("missing", &json!({})).is_err());
+    }
+
+    #[test]
+    fn delimiters_apply_to_subsequent_parses() {
+        let mut set = TemplateSet::new();
+        set.parse("braces", r#"{{define "a"}}A{{end}}"#).unwrap();
+        set.set_parse_options(ParseOptions::new().with_delimiters("[[", "]]"));
+        set.parse("page", r#"{{.}} [[template "a"]] [[template "b" .]]"#)
+            .unwrap();
+        set.parse("b", "<[[.]]>").unwrap();
+
+        let rendered = set.render("page", &json!("x")).unwrap();
+        assert_eq!(rendered, "{{.}} A <x>");
+        assert_eq!(set.lookup("b").unwrap().to_template_string(), "<[[.]]>");
+    }
+
+    #[test]
+    fn errors_in_other_members_resolve_against_their_source() {
+        let mut functions = FunctionRegistry::builder();
+        functions.register("index", |_ctx, args| {
+            Err(Error::render(
+                format!("index out of range: {}", args[1]),
+                None,
+            ))
+        });
+        let mut set = TemplateSet::new().with_functions(functions.build());
+        set.parse("lib", "{{define \"x\"}}\n\n\n   {{ index .a 5 }}{{end}}")
+            .unwrap();
+        set.parse("main", r#"hello {{template "x" .}}"#).unwrap();
+
+        let main = set.lookup("main").unwrap();
+        let err = main.render(&json!({"a": [1]})).unwrap_err();
+        assert_eq!(err.template(), Some("x"));
+        assert_eq!(
+            main.format_error(&err),
+            r#"template: lib:4:6: executing "x" at <index .a 5>: error calling index: index out of range: 5"#
+        );
+
+        let err = set.render("main", &json!({"a": [1]})).unwrap_err();
+        assert_eq!(
+            set.lookup("lib").unwrap().format_error(&err),
+            main.format_error(&err)
+        );
+    }
+}
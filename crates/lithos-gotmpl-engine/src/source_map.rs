@@ -0,0 +1,228 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+use crate::ast::Span;
+
+/// 1-based line and column of a position in a template source.
+///
+/// Columns count UTF-8 bytes or UTF-16 code units depending on the
+/// [`SourceMap`] method that produced them; editors speaking LSP expect the
+/// latter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LineColumn {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl LineColumn {
+    pub fn new(line: usize, column: usize) -> Self {
+        Self { line, column }
+    }
+}
+
+/// Start and end positions of a [`Span`], end exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SourceRange {
+    pub start: LineColumn,
+    pub end: LineColumn,
+}
+
+/// Line index over a template source, built once and shared by everything
+/// that needs to turn byte spans into line/column positions or back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceMap {
+    source: String,
+    line_starts: Vec<usize>,
+}
+
+impl SourceMap {
+    /// Indexes the line starts of `source`. Lines end at `\n`, as in Go.
+    pub fn new(source: impl Into<String>) -> Self {
+        let source = source.into();
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(idx, _)| idx + 1))
+            .collect();
+        Self {
+            source,
+            line_starts,
+        }
+    }
+
+    /// Returns the indexed source text.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns the number of lines; an empty source has one empty line.
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Returns the text of the 1-based `line`, without its line break.
+    pub fn line(&self, line: usize) -> Option<&str> {
+        let (start, end) = self.line_bounds(line)?;
+        Some(&self.source[start..end])
+    }
+
+    /// Converts a byte offset into a line and UTF-8 byte column. Offsets past
+    /// the end clamp to the end; offsets inside a character snap to its start.
+    pub fn line_column(&self, offset: usize) -> LineColumn {
+        let offset = self.floor(offset);
+        let line = self.line_of(offset);
+        LineColumn::new(line + 1, offset - self.line_starts[line] + 1)
+    }
+
+    /// Converts a byte offset into a line and UTF-16 code unit column.
+    pub fn line_column_utf16(&self, offset: usize) -> LineColumn {
+        let offset = self.floor(offset);
+        let line = self.line_of(offset);
+        let units = self.source[self.line_starts[line]..offset]
+            .encode_utf16()
+            .count();
+        LineColumn::new(line + 1, units + 1)
+    }
+
+    /// Converts a line and UTF-8 byte column back into a byte offset. Returns
+    /// `None` past the end of the line or inside a multi-byte character.
+    pub fn offset(&self, position: LineColumn) -> Option<usize> {
+        let (start, end) = self.line_bounds(position.line)?;
+        let offset = start.checked_add(position.column.checked_sub(1)?)?;
+        (offset <= end && self.source.is_char_boundary(offset)).then_some(offset)
+    }
+
+    /// Converts a line and UTF-16 column back into a byte offset. Returns
+    /// `None` past the end of the line or between the halves of a surrogate pair.
+    pub fn offset_utf16(&self, position: LineColumn) -> Option<usize> {
+        let (start, end) = self.line_bounds(position.line)?;
+        let target = position.column.checked_sub(1)?;
+        let mut units = 0;
+        for (idx, ch) in self.source[start..end].char_indices() {
+            if units == target {
+                return Some(start + idx);
+            }
+            units += ch.len_utf16();
+            if units > target {
+                return None;
+            }
+        }
+        (units == target).then_some(end)
+    }
+
+    /// Resolves `span` to UTF-8 line/column positions.
+    pub fn range(&self, span: Span) -> SourceRange {
+        SourceRange {
+            start: self.line_column(span.start),
+            end: self.line_column(span.end),
+        }
+    }
+
+    /// Resolves `span` to UTF-16 line/column positions.
+    pub fn range_utf16(&self, span: Span) -> SourceRange {
+        SourceRange {
+            start: self.line_column_utf16(span.start),
+            end: self.line_column_utf16(span.end),
+        }
+    }
+
+    /// Converts UTF-8 line/column positions back into a byte span.
+    pub fn span(&self, range: SourceRange) -> Option<Span> {
+        Some(Span::new(
+            self.offset(range.start)?,
+            self.offset(range.end)?,
+        ))
+    }
+
+    /// Converts UTF-16 line/column positions back into a byte span.
+    pub fn span_utf16(&self, range: SourceRange) -> Option<Span> {
+        Some(Span::new(
+            self.offset_utf16(range.start)?,
+            self.offset_utf16(range.end)?,
+        ))
+    }
+
+    /// Returns the source text covered by `span`, clamped to the source and to
+    /// character boundaries.
+    pub fn snippet(&self, span: Span) -> &str {
+        let start = self.floor(span.start);
+        let end = self.floor(span.end.max(start));
+        &self.source[start..end]
+    }
+
+    fn line_of(&self, offset: usize) -> usize {
+        self.line_starts
+            .partition_point(|start| *start <= offset)
+            .saturating_sub(1)
+    }
+
+    fn line_bounds(&self, line: usize) -> Option<(usize, usize)> {
+        let start = *self.line_starts.get(line.checked_sub(1)?)?;
+        let end = self
+            .line_starts
+            .get(line)
+            .map_or(self.source.len(), |next| next - 1);
+        Some((start, end))
+    }
+
+    fn floor(&self, offset: usize) -> usize {
+        let mut offset = offset.min(self.source.len());
+        while !self.source.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        offset
+    }
+}
+
+impl Default for SourceMap {
+    fn default() -> Self {
+        Self::new(String::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_offsets_to_line_columns_and_back() {
+        let map = SourceMap::new("ab\n{{ .é }}😀x\n");
+        assert_eq!(map.line_count(), 3);
+        assert_eq!(map.line(2), Some("{{ .é }}😀x"));
+        assert_eq!(map.line(3), Some(""));
+        assert_eq!(map.line(4), None);
+
+        assert_eq!(map.line_column(0), LineColumn::new(1, 1));
+        assert_eq!(map.line_column(2), LineColumn::new(1, 3));
+        assert_eq!(map.line_column(3), LineColumn::new(2, 1));
+        // `😀` starts after `{{ .é }}` (9 bytes, 8 UTF-16 units).
+        let emoji = 3 + "{{ .é }}".len();
+        assert_eq!(map.line_column(emoji + 4), LineColumn::new(2, 14));
+        assert_eq!(map.line_column_utf16(emoji + 4), LineColumn::new(2, 11));
+        assert_eq!(map.line_column(emoji + 2), map.line_column(emoji));
+        assert_eq!(map.line_column(1000), LineColumn::new(3, 1));
+
+        for offset in [0, 2, 3, emoji, emoji + 4, emoji + 5] {
+            assert_eq!(map.offset(map.line_column(offset)), Some(offset));
+            assert_eq!(
+                map.offset_utf16(map.line_column_utf16(offset)),
+                Some(offset)
+            );
+        }
+        assert_eq!(map.offset(LineColumn::new(2, 12)), None);
+        assert_eq!(map.offset_utf16(LineColumn::new(2, 10)), None);
+        assert_eq!(map.offset(LineColumn::new(1, 4)), None);
+        assert_eq!(map.offset(LineColumn::new(0, 1)), None);
+    }
+
+    #[test]
+    fn converts_spans_and_extracts_snippets() {
+        let map = SourceMap::new("x\n{{ .name }}\n");
+        let span = Span::new(5, 10);
+        assert_eq!(map.snippet(span), ".name");
+        let range = map.range(span);
+        assert_eq!(range.start, LineColumn::new(2, 4));
+        assert_eq!(range.end, LineColumn::new(2, 9));
+        assert_eq!(map.span(range), Some(span));
+        assert_eq!(map.span_utf16(map.range_utf16(span)), Some(span));
+        assert_eq!(map.snippet(Span::new(12, 99)), "}\n");
+    }
+}
@@ -1,6 +1,8 @@
 // SPDX-License-Identifier: Apache-2.0 OR MIT
-use lithos_gotmpl_engine::ControlKind;
-use lithos_gotmpl_engine::{FunctionRegistryBuilder, Template};
+use lithos_gotmpl_engine::{Certainty, ControlKind};
+use lithos_gotmpl_engine::{
+    Error, FunctionRegistryBuilder, LineColumn, Span, Template, TemplateSet,
+};
 use serde_json::Value;
 
 #[test]
@@ -36,15 +38,15 @@ fn analysis_reports_variables_and_functions() {
 }
 
 #[test]
-fn analysis_marks_dynamic_template() {
-    let tmpl = Template::parse_str("tmpl", r#"{{ template .name . }}"#).unwrap();
+fn template_names_must_be_string_literals() {
+    let err = Template::parse_str("tmpl", r#"{{ template .name . }}"#).unwrap_err();
+    assert!(err.to_string().contains("in template clause"));
+
+    let tmpl = Template::parse_str("tmpl", r#"{{ template "row" . }}"#).unwrap();
     let report = tmpl.analyze();
     assert!(report.has_template_invocation);
-    assert!(matches!(
-        report.precision,
-        lithos_gotmpl_engine::Precision::Conservative
-    ));
-    assert!(!report.templates.is_empty());
+    assert_eq!(report.templates[0].name.as_deref(), Some("row"));
+    assert!(!report.templates[0].indirect);
 }
 
 #[test]
@@ -110,3 +112,101 @@ fn parser_rejects_else_without_if() {
         msg
     );
 }
+
+#[test]
+fn analysis_resolves_template_calls_against_set() {
+    let mut set = TemplateSet::new();
+    set.parse("partials", r#"{{define "header"}}{{.title}}{{end}}"#)
+        .unwrap();
+    set.parse(
+        "page",
+        r#"{{template "header" .}}{{template "footer" .}}{{block "body" .}}{{.text}}{{end}}"#,
+    )
+    .unwrap();
+
+    let report = set.lookup("page").unwrap().analyze();
+    assert!(report.has_template_invocation);
+    let resolved: Vec<_> = report
+        .templates
+        .iter()
+        .map(|call| (call.name.as_deref(), call.resolved))
+        .collect();
+    assert_eq!(
+        resolved,
+        vec![
+            (Some("header"), true),
+            (Some("footer"), false),
+            (Some("body"), true)
+        ]
+    );
+    assert_eq!(report.unknown_templates.len(), 1);
+    assert_eq!(report.unknown_templates[0].name.as_deref(), Some("footer"));
+
+    let kinds: Vec<_> = report.controls.iter().map(|c| c.kind).collect();
+    assert!(kinds.contains(&ControlKind::Block));
+}
+
+#[test]
+fn analysis_reports_paths_reached_through_chains() {
+    let mut builder = FunctionRegistryBuilder::new();
+    builder.register("index", |_ctx, _args| Ok(Value::Null));
+    let tmpl = Template::parse_with_functions(
+        "chain",
+        "{{ (.user).name }}{{ ($).id }}{{ (index .items 0).name }}",
+        builder.build(),
+    )
+    .unwrap();
+    let report = tmpl.analyze();
+
+    let find = |path: &str| report.variables.iter().find(|v| v.path == path);
+    assert_eq!(find(".user.name").unwrap().certainty, Certainty::Certain);
+    assert_eq!(find("$.id").unwrap().certainty, Certainty::Certain);
+    assert!(find(".items").is_some());
+    assert_eq!(
+        find("(index .items 0).name").unwrap().certainty,
+        Certainty::Uncertain
+    );
+}
+
+#[test]
+fn analysis_and_errors_resolve_to_line_columns() {
+    let mut builder = FunctionRegistryBuilder::new();
+    builder.register("fail", |_ctx, _args| Err(Error::render("boom", None)));
+    let tmpl = Template::parse_with_functions(
+        "pos",
+        "héllo 😀\n{{ if .ok }}\n  {{ .name }} {{ fail }}{{ end }}",
+        builder.build(),
+    )
+    .unwrap();
+    let report = tmpl.analyze();
+    let map = tmpl.source_map();
+
+    let name = report.variables.iter().find(|v| v.path == ".name").unwrap();
+    let range = name.range(map);
+    assert_eq!(range.start, LineColumn::new(3, 3));
+    assert_eq!(range.end, LineColumn::new(3, 14));
+    assert_eq!(map.snippet(name.span), "{{ .name }}");
+    assert_eq!(map.span(range), Some(name.span));
+
+    let control = report
+        .controls
+        .iter()
+        .find(|c| c.kind == ControlKind::If)
+        .unwrap();
+    assert_eq!(control.range(map).start.line, 2);
+    assert_eq!(report.functions[0].range(map).start, LineColumn::new(3, 15));
+
+    let err = tmpl.render(&serde_json::json!({"ok": true})).unwrap_err();
+    let range = err.range(map).unwrap();
+    assert_eq!(range.start, LineColumn::new(3, 18));
+    assert_eq!(
+        map.line(range.start.line),
+        Some("  {{ .name }} {{ fail }}{{ end }}")
+    );
+
+    // `é` is two bytes but one UTF-16 unit; `😀` is four bytes and two units.
+    let utf16 = map.range_utf16(Span::new(0, "héllo 😀".len()));
+    assert_eq!(utf16.end, LineColumn::new(1, 9));
+    assert_eq!(map.line_column("héllo 😀".len()), LineColumn::new(1, 12));
+    assert_eq!(map.span_utf16(utf16), Some(Span::new(0, "héllo 😀".len())));
+}
@@ -0,0 +1,118 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+#![cfg(feature = "artifact")]
+
+mod common;
+
+use common::engine_cases;
+use lithos_gotmpl_engine::{Dialect, FunctionRegistry, ParseOptions, Template, TemplateSet};
+use serde_json::{json, Value};
+
+fn registry(names: &[&str]) -> FunctionRegistry {
+    let mut builder = FunctionRegistry::builder();
+    for name in names {
+        builder.register(*name, |_ctx, args| {
+            Ok(args.first().cloned().unwrap_or(Value::Null))
+        });
+    }
+    builder.build()
+}
+
+#[test]
+fn loaded_fixtures_render_like_the_originals() {
+    for case in engine_cases() {
+        let Ok(original) =
+            Template::parse_with_functions(&case.name, &case.template, FunctionRegistry::empty())
+        else {
+            continue;
+        };
+        let bytes = original.to_artifact().unwrap();
+        let loaded = Template::from_artifact(&bytes, FunctionRegistry::empty())
+            .unwrap_or_else(|err| panic!("{}: {err}", case.name));
+        let render = |template: &Template| {
+            template
+                .render(&case.data)
+                .map_err(|err| err.message().to_string())
+        };
+        assert_eq!(render(&loaded), render(&original), "{}", case.name);
+        assert_eq!(loaded.source(), original.source());
+        assert_eq!(loaded.template_names(), original.template_names());
+    }
+}
+
+#[test]
+fn template_sets_round_trip_with_their_parse_options() {
+    let options = ParseOptions::new()
+        .with_delimiters("<<", ">>")
+        .with_dialect(Dialect::go());
+    let mut set = TemplateSet::new()
+        .with_functions(registry(&["shout"]))
+        .with_parse_options(options);
+    set.parse(
+        "layout",
+        r#"<<define "title">>untitled<<end>>[<<template "title">>] <<block "body" .>>-<<end>>"#,
+    )
+    .unwrap();
+    set.parse("page", r#"<<define "body">><<shout .>><<end>>"#)
+        .unwrap();
+
+    let bytes = set.to_artifact().unwrap();
+    let mut loaded = TemplateSet::from_artifact(&bytes, registry(&["shout"])).unwrap();
+    assert_eq!(loaded.names(), set.names());
+    assert_eq!(
+        loaded.render("layout", &json!("hi")).unwrap(),
+        "[untitled] hi"
+    );
+    assert_eq!(
+        loaded.lookup("body").unwrap().source(),
+        set.lookup("body").unwrap().source()
+    );
+
+    // Later parses use the set's delimiters and dialect.
+    loaded.parse("extra", "<<.>>").unwrap();
+    assert!(loaded
+        .parse("bad", "<<if eq 1 1>><<end>><<1 == 1>>")
+        .is_err());
+    assert_eq!(loaded.render("extra", &json!(7)).unwrap(), "7");
+}
+
+#[test]
+fn artifacts_are_validated_before_loading() {
+    let template =
+        Template::parse_with_functions("t", "{{ shout .name }}", registry(&["shout"])).unwrap();
+    let bytes = template.to_artifact().unwrap();
+    assert!(Template::from_artifact(&bytes, registry(&["shout"])).is_ok());
+
+    let err = Template::from_artifact(&bytes, registry(&["upper"])).unwrap_err();
+    assert_eq!(
+        err.message(),
+        r#"artifact was compiled with a different function registry (missing: ["shout"], unexpected: ["upper"])"#
+    );
+
+    let version = env!("CARGO_PKG_VERSION");
+    let at = bytes
+        .windows(version.len())
+        .position(|window| window == version.as_bytes())
+        .unwrap();
+    let mut stale = bytes.clone();
+    stale[at..at + version.len()].copy_from_slice("9".repeat(version.len()).as_bytes());
+    let err = Template::from_artifact(&stale, registry(&["shout"])).unwrap_err();
+    assert!(
+        err.message()
+            .starts_with("artifact was compiled by engine 9"),
+        "{err}"
+    );
+
+    let err = TemplateSet::from_artifact(&bytes, registry(&["shout"])).unwrap_err();
+    assert_eq!(
+        err.message(),
+        "artifact holds a template, not a template set"
+    );
+
+    let err = Template::from_artifact(b"{{ .name }}", FunctionRegistry::empty()).unwrap_err();
+    assert_eq!(err.message(), "not a template artifact");
+
+    let truncated = &bytes[..bytes.len() - 3];
+    let err = Template::from_artifact(truncated, registry(&["shout"])).unwrap_err();
+    assert_eq!(err.message(), "corrupt artifact payload");
+    assert!(std::error::Error::source(&err).is_some());
+}
@@ -0,0 +1,209 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+use std::future::Future;
+use std::pin::pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+
+use lithos_gotmpl_engine::{
+    CancellationToken, Error, FunctionRegistry, Limit, RenderOptions, Template, TemplateSet,
+};
+use serde_json::{json, Value};
+
+/// Minimal single-threaded executor, standing in for whatever runtime a
+/// service uses.
+fn block_on<F: Future>(future: F) -> F::Output {
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+/// Completes on its second poll, like a lookup waiting on I/O.
+struct YieldOnce(bool);
+
+impl Future for YieldOnce {
+    type Output = ();
+
+    fn poll(mut self: std::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            return Poll::Ready(());
+        }
+        self.0 = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+fn registry(lookups: &Arc<AtomicUsize>) -> FunctionRegistry {
+    let mut builder = FunctionRegistry::builder();
+    let counter = lookups.clone();
+    builder.register_async("secret", move |args| {
+        let counter = counter.clone();
+        async move {
+            YieldOnce(false).await;
+            counter.fetch_add(1, Ordering::SeqCst);
+            match args.first().and_then(Value::as_str) {
+                Some("missing") => Err(Error::render("no such secret", None)),
+                Some(key) => Ok(json!(format!("<{key}>"))),
+                None => Err(Error::render("secret needs a key", None)),
+            }
+        }
+    });
+    builder.register("upper", |_ctx, args| {
+        Ok(json!(args[0].as_str().unwrap_or_default().to_uppercase()))
+    });
+    builder.build()
+}
+
+#[test]
+fn async_helpers_are_awaited_once_per_argument_list() {
+    let lookups = Arc::new(AtomicUsize::new(0));
+    let template = Template::parse_with_functions(
+        "config",
+        r#"{{ range .keys }}{{ secret . | upper }};{{ end }}{{ secret "db" }}"#,
+        registry(&lookups),
+    )
+    .unwrap();
+    let data = json!({"keys": ["db", "api", "db"]});
+    let output = block_on(template.render_async(&data)).unwrap();
+    assert_eq!(output, "<DB>;<API>;<DB>;<db>");
+    assert_eq!(lookups.load(Ordering::SeqCst), 2);
+
+    fn assert_send<T: Send>(_: &T) {}
+    assert_send(&template.render_async(&data));
+}
+
+#[test]
+fn async_errors_point_at_the_call() {
+    let lookups = Arc::new(AtomicUsize::new(0));
+    let source = r#"ok {{ secret "missing" }}"#;
+    let template = Template::parse_with_functions("config", source, registry(&lookups)).unwrap();
+    let err = block_on(template.render_async(&Value::Null)).unwrap_err();
+    assert_eq!(err.message(), "error calling secret: no such secret");
+    assert_eq!(
+        err.span().map(|span| &source[span.start..span.end]),
+        Some(r#"secret "missing""#)
+    );
+
+    // Synchronous renders cannot await the helper.
+    let err = template.render(&Value::Null).unwrap_err();
+    assert_eq!(
+        err.message(),
+        "error calling secret: async helper requires Template::render_async"
+    );
+}
+
+#[test]
+fn sync_templates_render_the_same_through_the_async_path() {
+    let lookups = Arc::new(AtomicUsize::new(0));
+    let mut set = TemplateSet::new().with_functions(registry(&lookups));
+    set.parse(
+        "greet",
+        r#"{{ define "name" }}{{ upper . }}{{ end }}hi {{ template "name" .who }}"#,
+    )
+    .unwrap();
+    let data = json!({"who": "ada"});
+    assert_eq!(
+        block_on(set.render_async("greet", &data)).unwrap(),
+        set.render("greet", &data).unwrap()
+    );
+    assert_eq!(lookups.load(Ordering::SeqCst), 0);
+
+    let token = CancellationToken::new();
+    token.cancel();
+    let options = RenderOptions::new().with_cancellation(token);
+    let err = block_on(set.render_async_with_options("greet", &data, &options)).unwrap_err();
+    assert!(err.cancel_reason().is_some());
+}
+
+#[test]
+fn sync_helpers_run_once_across_passes() {
+    let lookups = Arc::new(AtomicUsize::new(0));
+    let ticks = Arc::new(AtomicUsize::new(0));
+    let mut builder = FunctionRegistry::builder();
+    builder.extend(&registry(&lookups));
+    let counter = ticks.clone();
+    builder.register("tick", move |_ctx, _args| {
+        Ok(json!(format!(
+            "t{}",
+            counter.fetch_add(1, Ordering::SeqCst)
+        )))
+    });
+    let template = Template::parse_with_functions(
+        "config",
+        r#"{{ tick }} {{ secret (tick) }} {{ secret "db" }} {{ tick }}"#,
+        builder.build(),
+    )
+    .unwrap();
+    let output = block_on(template.render_async(&Value::Null)).unwrap();
+    assert_eq!(output, "t0 <t1> <db> t2");
+    assert_eq!(ticks.load(Ordering::SeqCst), 3);
+    assert_eq!(lookups.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn changing_async_arguments_fail_instead_of_looping() {
+    let lookups = Arc::new(AtomicUsize::new(0));
+    let mut builder = FunctionRegistry::builder();
+    builder.extend(&registry(&lookups));
+    // Calls `secret` itself with a new key each time it runs.
+    let rolls = AtomicUsize::new(0);
+    builder.register("roll", move |ctx, _args| {
+        let secret = ctx.function("secret").expect("secret is registered");
+        let key = format!("k{}", rolls.fetch_add(1, Ordering::SeqCst));
+        secret(ctx, &[json!(key)])
+    });
+    let source = "{{ roll }}";
+    let template = Template::parse_with_functions("config", source, builder.build()).unwrap();
+    let err = block_on(template.render_async(&Value::Null)).unwrap_err();
+    assert!(matches!(err, Error::AsyncArgumentsChanged { ref helper, .. } if helper == "secret"));
+    assert_eq!(
+        err.span().map(|span| &source[span.start..span.end]),
+        Some("roll")
+    );
+    assert_eq!(lookups.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn async_renders_are_limited_like_sync_renders() {
+    let lookups = Arc::new(AtomicUsize::new(0));
+    let template = Template::parse_with_functions(
+        "config",
+        r#"{{ range .keys }}{{ secret . | upper }}{{ end }}"#,
+        registry(&lookups),
+    )
+    .unwrap();
+    let data = json!({"keys": ["a", "b", "c", "d"]});
+
+    let options = RenderOptions::new().with_limit(Limit::AsyncCalls, 2);
+    let err = block_on(template.render_async_with_options(&data, &options)).unwrap_err();
+    assert_eq!(err.limit(), Some(Limit::AsyncCalls));
+    assert_eq!(lookups.load(Ordering::SeqCst), 2);
+
+    // The render makes eight helper calls and four iterations, however many
+    // passes it takes.
+    let options = RenderOptions::new()
+        .with_limit(Limit::HelperCalls, 8)
+        .with_limit(Limit::Iterations, 4)
+        .with_limit(Limit::OutputBytes, 12);
+    let output = block_on(template.render_async_with_options(&data, &options)).unwrap();
+    assert_eq!(output, "<A><B><C><D>");
+    let options = RenderOptions::new().with_limit(Limit::HelperCalls, 7);
+    let err = block_on(template.render_async_with_options(&data, &options)).unwrap_err();
+    assert_eq!(err.limit(), Some(Limit::HelperCalls));
+}
@@ -0,0 +1,40 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+//! Loader for the shared engine fixtures in `test-cases/`, used by the
+//! integration tests and benches.
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+#[derive(Debug, Deserialize)]
+pub struct EngineCase {
+    pub name: String,
+    pub template: String,
+    #[serde(default)]
+    pub data: Value,
+    #[serde(default)]
+    pub expected: Option<String>,
+    #[serde(default)]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub go_error: Option<String>,
+}
+
+pub fn workspace_root() -> PathBuf {
+    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    manifest_dir
+        .parent()
+        .expect("missing crates directory")
+        .parent()
+        .expect("missing workspace root")
+        .to_path_buf()
+}
+
+pub fn engine_cases() -> Vec<EngineCase> {
+    let path = workspace_root().join("test-cases/lithos-gotmpl-engine.json");
+    let bytes = fs::read(&path).expect("missing engine test cases");
+    serde_json::from_slice(&bytes).expect("invalid engine test cases")
+}
@@ -0,0 +1,109 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+mod common;
+
+use common::engine_cases;
+use lithos_gotmpl_engine::{ActionSpacing, FormatOptions, FunctionRegistry, Template};
+use serde_json::{json, Value};
+
+fn option_sets() -> Vec<FormatOptions> {
+    vec![
+        FormatOptions::new(),
+        FormatOptions::new().with_spacing(ActionSpacing::Compact),
+        FormatOptions::new().with_indent(2),
+        FormatOptions::new()
+            .with_spacing(ActionSpacing::Compact)
+            .with_indent(4),
+    ]
+}
+
+/// Helpers the sources below call, so that they render output rather than
+/// "function not defined" errors.
+fn registry() -> FunctionRegistry {
+    let mut builder = FunctionRegistry::builder();
+    builder.register("upper", |_ctx, args| {
+        Ok(json!(args[0].as_str().unwrap_or_default().to_uppercase()))
+    });
+    builder.register("printf", |_ctx, args| {
+        let format = args[0].as_str().unwrap_or_default();
+        let arg = args.get(1).and_then(Value::as_str).unwrap_or_default();
+        Ok(json!(format.replacen("%s", arg, 1)))
+    });
+    builder.build()
+}
+
+/// Formats `source` with every option set and checks that the result renders
+/// the same output as the original and is stable under reformatting. Returns
+/// `false` when `source` does not parse or render, leaving nothing to compare.
+fn assert_render_equivalent(name: &str, source: &str, data: &Value) -> bool {
+    let Ok(original) = Template::parse_with_functions(name, source, registry()) else {
+        return false;
+    };
+    let Ok(expected) = original.render(data) else {
+        return false;
+    };
+    for options in option_sets() {
+        let formatted = original
+            .format(&options)
+            .unwrap_or_else(|err| panic!("{name}: formatting {source:?}: {err}"));
+        let reparsed = Template::parse_with_functions(name, &formatted, registry())
+            .unwrap_or_else(|err| panic!("{name}: formatted source {formatted:?}: {err}"));
+        assert_eq!(
+            reparsed.render(data).ok(),
+            Some(expected.clone()),
+            "{name}: {source:?} formatted as {formatted:?} with {options:?}"
+        );
+        assert!(
+            reparsed.is_formatted(&options).unwrap(),
+            "{name}: formatting {formatted:?} is not stable with {options:?}"
+        );
+    }
+    true
+}
+
+#[test]
+fn formatting_preserves_rendered_output_of_fixtures() {
+    let compared = engine_cases()
+        .iter()
+        .filter(|case| assert_render_equivalent(&case.name, &case.template, &case.data))
+        .count();
+    assert!(compared > 0);
+}
+
+#[test]
+fn formatting_preserves_rendered_output_of_nested_blocks() {
+    let data = json!({"items": [1, 0, 3], "user": {"name": "Ada"}, "empty": []});
+    let sources = [
+        "<ul>\n{{- range $i,$v := .items }}\n    {{- if $v }}\n<li>{{$i}}={{$v}}</li>\n{{- else -}}\n  zero\n       {{- end }}\n{{- end }}\n</ul>\n",
+        "{{- with .user -}}\n\t{{.name|printf \"%s\"}}  \n{{- end -}}\n\n",
+        "{{define \"row\"}}  {{- . -}}  {{end}}{{range .items}}[{{template \"row\" .}}]{{end}}",
+        "{{range .empty}}x{{else -}}\n   none   {{- /*done*/ -}}   \n{{end}}",
+    ];
+    for (idx, source) in sources.iter().enumerate() {
+        assert!(assert_render_equivalent(
+            &format!("nested-{idx}"),
+            source,
+            &data
+        ));
+    }
+}
+
+#[test]
+fn unmodified_trees_print_back_byte_for_byte() {
+    let sources = [
+        "a  \n {{- if .x -}} \t\n yes {{- else if .y }}maybe{{ else -}}\n\n no\n{{-  end  }} tail",
+        "{{/* c */}} {{- /* trimmed */ -}}\n{{define \"d\"}}{{- . -}}{{end}}",
+        "{{range $i, $v := .items}}{{if $i}}{{break}}{{end}}{{continue -}}  {{end}}",
+        "{{with .a}}x{{else}}y{{end}}{{block \"b\" .}}z{{end}}{{template \"d\"  . }}",
+        "{{-5}} {{ .x|upper }}\r\n",
+        "",
+    ];
+    for source in sources {
+        let template = Template::parse_str("lossless", source).unwrap();
+        assert_eq!(template.ast().to_source(), source);
+    }
+    for case in engine_cases() {
+        if let Ok(template) = Template::parse_str(&case.name, &case.template) {
+            assert_eq!(template.ast().to_source(), case.template, "{}", case.name);
+        }
+    }
+}
@@ -1,20 +1,14 @@
 // SPDX-License-Identifier: Apache-2.0 OR MIT
+mod common;
+
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
 use std::process::Command;
 
+use common::{engine_cases, workspace_root, EngineCase};
+use lithos_gotmpl_engine::{FunctionRegistry, SourceMap, Template};
 use serde::Deserialize;
 
-#[derive(Debug, Deserialize)]
-struct EngineCase {
-    name: String,
-    #[serde(default)]
-    expected: Option<String>,
-    #[serde(default)]
-    error: Option<String>,
-}
-
 #[derive(Debug, Deserialize)]
 struct GoSanityCase {
     #[serde(default)]
@@ -32,17 +26,9 @@ fn go_reference_confirms_engine_cases() {
         return;
     }
 
-    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-    let workspace_root = manifest_dir
-        .parent()
-        .expect("missing crates directory")
-        .parent()
-        .expect("missing workspace root");
-
+    let workspace_root = workspace_root();
     let cases_path = workspace_root.join("test-cases/lithos-gotmpl-engine.json");
-    let bytes = fs::read(&cases_path).expect("missing engine test cases");
-    let engine_cases: Vec<EngineCase> =
-        serde_json::from_slice(&bytes).expect("invalid engine test cases");
+    let engine_cases = engine_cases();
 
     let runner_dir = workspace_root.join("go-sanity");
     let go_cache = workspace_root.join("target/go-cache");
@@ -101,12 +87,25 @@ fn go_reference_confirms_engine_cases() {
                     .error
                     .as_ref()
                     .unwrap_or_else(|| panic!("go did not error for {}", case.name));
-                assert!(
-                    err.contains("unexpected <with>"),
-                    "go error for {} did not mention unexpected <with>: {}",
-                    case.name,
-                    err
-                );
+                if let Some(expected) = case.go_error.as_ref() {
+                    assert!(
+                        err.contains(expected),
+                        "go error for {} did not mention {}: {}",
+                        case.name,
+                        expected,
+                        err
+                    );
+                }
+                if let Some(ours) = engine_error(&case) {
+                    assert_eq!(
+                        error_location(&ours),
+                        error_location(err),
+                        "error location mismatch for {}: ours {:?}, go {:?}",
+                        case.name,
+                        ours,
+                        err
+                    );
+                }
             }
             _ => panic!(
                 "case {} must have either expected output or error",
@@ -115,3 +114,31 @@ fn go_reference_confirms_engine_cases() {
         }
     }
 }
+
+/// Renders `case` with the engine and returns its error in Go's format, or
+/// `None` when the error carries no span to compare. The go-sanity runner
+/// names every template `case`, so the engine error uses that name too.
+fn engine_error(case: &EngineCase) -> Option<String> {
+    let err =
+        match Template::parse_with_functions(&case.name, &case.template, FunctionRegistry::empty())
+        {
+            Ok(template) => template.render(&case.data).err()?,
+            Err(err) => err,
+        };
+    err.span()?;
+    Some(err.to_go_string("case", &SourceMap::new(case.template.as_str())))
+}
+
+/// Extracts the `template: name:line[:col]:` header, skipping the runner's
+/// `parse template:`/`execute template:` wrapping and leaving out the message
+/// wording, which is not expected to match Go verbatim.
+fn error_location(message: &str) -> &str {
+    let Some(start) = message.find("template: case") else {
+        return "";
+    };
+    let header = &message[start..];
+    let header_len = header["template: ".len()..]
+        .find(' ')
+        .map_or(header.len(), |idx| idx + "template: ".len());
+    &header[..header_len]
+}
@@ -0,0 +1,300 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+#![cfg(feature = "serde")]
+
+mod common;
+
+use std::collections::BTreeMap;
+
+use common::engine_cases;
+use lithos_gotmpl_engine::{
+    AnalysisDocument, Dialect, FunctionRegistry, MissingKey, ParseOptions, RenderOptions, Template,
+    TemplateDocument, TemplateSet, SCHEMA_VERSION,
+};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+fn round_trip(template: &Template) -> Template {
+    let json = serde_json::to_string(&template.to_document()).expect("serialize template");
+    let document: TemplateDocument = serde_json::from_str(&json).expect("deserialize template");
+    Template::from_document(document, template.functions()).expect("rebuild template")
+}
+
+fn render(template: &Template, data: &Value) -> Result<String, String> {
+    template
+        .render(data)
+        .map_err(|err| err.message().to_string())
+}
+
+#[test]
+fn deserialized_fixtures_render_like_the_originals() {
+    for case in engine_cases() {
+        let Ok(original) =
+            Template::parse_with_functions(&case.name, &case.template, FunctionRegistry::empty())
+        else {
+            continue;
+        };
+        let restored = round_trip(&original);
+        assert_eq!(
+            render(&restored, &case.data),
+            render(&original, &case.data),
+            "{}: {:?}",
+            case.name,
+            case.template
+        );
+        assert_eq!(restored.source(), original.source(), "{}", case.name);
+        assert_eq!(restored.ast().to_source(), case.template, "{}", case.name);
+        assert_eq!(restored.template_names(), original.template_names());
+    }
+}
+
+#[test]
+fn documents_keep_delimiters_dialect_and_set_members() {
+    let options = ParseOptions::new()
+        .with_delimiters("[[", "]]")
+        .with_dialect(Dialect::go());
+    let template = Template::parse_with_options(
+        "page",
+        r#"[[define "item"]]<[[.]]>[[end]][[range .]][[template "item" .]][[end]]"#,
+        FunctionRegistry::empty(),
+        &options,
+    )
+    .unwrap();
+    let restored = round_trip(&template);
+    assert_eq!(restored.ast().delimiters, template.ast().delimiters);
+    assert_eq!(restored.ast().dialect, Dialect::go());
+    assert_eq!(restored.render(&json!([1, 2])).unwrap(), "<1><2>");
+
+    let mut set = TemplateSet::new();
+    set.parse("header", "Hello {{.}}").unwrap();
+    set.parse("page", r#"{{template "header" .}}!"#).unwrap();
+    let page = round_trip(&set.lookup("page").unwrap());
+    assert_eq!(page.render(&json!("world")).unwrap(), "Hello world!");
+    assert_eq!(page.template_names(), ["header", "page"]);
+}
+
+#[test]
+fn documents_use_a_versioned_schema() {
+    let template =
+        Template::parse_str("t", "{{ if .ok }}{{ .name | printf \"%q\" }}{{ end }}").unwrap();
+    let mut document = serde_json::to_value(template.to_document()).unwrap();
+    assert_eq!(document["schema_version"], json!(SCHEMA_VERSION));
+    assert_eq!(document["name"], json!("t"));
+
+    document["schema_version"] = json!(SCHEMA_VERSION + 1);
+    let err = serde_json::from_value::<TemplateDocument>(document).unwrap_err();
+    assert!(
+        err.to_string().contains("unsupported schema version"),
+        "{err}"
+    );
+
+    // Go templates are checked against the registry they are rebuilt with.
+    let mut builder = FunctionRegistry::builder();
+    builder.register("upper", |_ctx, _args| Ok(Value::Null));
+    let registry = builder.build();
+    let go = Template::parse_with_options(
+        "go",
+        "{{ upper .name }}",
+        registry,
+        &ParseOptions::new().with_dialect(Dialect::go()),
+    )
+    .unwrap();
+    let err = Template::from_document(go.to_document(), FunctionRegistry::empty()).unwrap_err();
+    assert_eq!(err.message(), r#"function "upper" not defined"#);
+}
+
+/// Template and analysis documents covering every node kind, serialized with
+/// the current [`SCHEMA_VERSION`]. Any change to the serialized shape makes
+/// this test fail: bump the schema version and regenerate the fixture with
+/// `LITHOS_UPDATE_GOLDEN=1 cargo test -p lithos-gotmpl-engine --features serde`.
+#[test]
+fn documents_match_the_golden_schema() {
+    let source = concat!(
+        "{{/* header */}}{{define \"item\"}}<{{.}}>{{end}}",
+        "{{- $n := len .items -}}",
+        "{{if and .ok (eq $n 2)}}{{.user.name | printf \"%q\"}}",
+        "{{else if not .ok}}off{{else}}{{$n}}{{end}}",
+        "{{range $i, $v := .items}}{{if eq $i 1}}{{break}}{{end}}{{continue}}{{end}}",
+        "{{with (index .items 0).name}}{{template \"item\" .}}{{else}}-{{end}}",
+        "{{block \"footer\" .}}{{true}} {{nil}} {{1.5e3}} {{0x1F}} {{'a'}} {{`raw`}}{{end}}",
+        "{{if .a == .b}}{{$.root}}{{end}}",
+    );
+    let mut builder = FunctionRegistry::builder();
+    for name in ["len", "printf", "index", "eq", "not", "and"] {
+        builder.register(name, |_ctx, _args| Ok(Value::Null));
+    }
+    let template =
+        Template::parse_with_functions("golden", source, builder.build()).expect("parse golden");
+    let mut analysis = template.analyze();
+    analysis.version = "0.0.0".to_string();
+    let actual = json!({
+        "template": template.to_document(),
+        "analysis": AnalysisDocument::new(analysis),
+    });
+
+    let path = common::workspace_root()
+        .join("crates/lithos-gotmpl-engine/tests/fixtures/document-schema.json");
+    if std::env::var_os("LITHOS_UPDATE_GOLDEN").is_some() {
+        let json = serde_json::to_string_pretty(&actual).unwrap() + "\n";
+        std::fs::write(&path, json).expect("write golden document");
+    }
+    let expected: Value =
+        serde_json::from_slice(&std::fs::read(&path).expect("missing golden document"))
+            .expect("invalid golden document");
+    assert!(
+        actual == expected,
+        "serialized document shape changed; bump SCHEMA_VERSION and regenerate {}",
+        path.display()
+    );
+
+    let document: TemplateDocument =
+        serde_json::from_value(expected["template"].clone()).expect("golden template document");
+    let restored = Template::from_document(document, template.functions()).unwrap();
+    assert_eq!(restored.ast().to_source(), source);
+    let analysis: AnalysisDocument =
+        serde_json::from_value(expected["analysis"].clone()).expect("golden analysis document");
+    assert_eq!(analysis.analysis.version, "0.0.0");
+}
+
+#[test]
+fn analysis_round_trips_through_json() {
+    let template = Template::parse_str("t", "{{ range .items }}{{ .name }}{{ end }}").unwrap();
+    let analysis = template.analyze();
+    let json = serde_json::to_value(AnalysisDocument::new(analysis.clone())).unwrap();
+    assert_eq!(json["schema_version"], json!(SCHEMA_VERSION));
+    assert_eq!(json["analysis"]["version"], json!(analysis.version));
+    assert_eq!(json["analysis"]["precision"], json!("precise"));
+    assert_eq!(json["analysis"]["controls"][0]["kind"], json!("range"));
+
+    let restored: AnalysisDocument = serde_json::from_value(json.clone()).unwrap();
+    let restored = restored.analysis;
+    assert_eq!(restored.version, analysis.version);
+
+    // Documents keep the version of the engine that produced them.
+    let mut older = json;
+    older["analysis"]["version"] = json!("0.0.1");
+    let older: AnalysisDocument = serde_json::from_value(older).unwrap();
+    assert_eq!(older.analysis.version, "0.0.1");
+    assert_eq!(restored.precision, analysis.precision);
+    let paths = |analysis: &lithos_gotmpl_engine::TemplateAnalysis| {
+        analysis
+            .variables
+            .iter()
+            .map(|access| (access.path.clone(), access.span, access.certainty))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(paths(&restored), paths(&analysis));
+    assert_eq!(restored.controls.len(), analysis.controls.len());
+}
+
+/// Fixture data rebuilt from Rust types, so rendering goes through their
+/// `Serialize` impls rather than a ready-made [`Value`].
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Typed {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Uint(u64),
+    Float(f64),
+    Text(String),
+    List(Vec<Typed>),
+    Map(BTreeMap<String, Typed>),
+}
+
+impl From<&Value> for Typed {
+    fn from(value: &Value) -> Self {
+        match value {
+            Value::Null => Typed::Null,
+            Value::Bool(flag) => Typed::Bool(*flag),
+            Value::Number(number) => match (number.as_i64(), number.as_u64()) {
+                (Some(int), _) => Typed::Int(int),
+                (None, Some(uint)) => Typed::Uint(uint),
+                (None, None) => Typed::Float(number.as_f64().unwrap_or_default()),
+            },
+            Value::String(text) => Typed::Text(text.clone()),
+            Value::Array(items) => Typed::List(items.iter().map(Typed::from).collect()),
+            Value::Object(map) => Typed::Map(
+                map.iter()
+                    .map(|(key, value)| (key.clone(), Typed::from(value)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+#[test]
+fn serialized_fixture_data_renders_like_values() {
+    for case in engine_cases() {
+        let Ok(template) = Template::parse_str(&case.name, &case.template) else {
+            continue;
+        };
+        let serialized = template
+            .render_serialize(&Typed::from(&case.data))
+            .map_err(|err| err.message().to_string());
+        assert_eq!(serialized, render(&template, &case.data), "{}", case.name);
+    }
+}
+
+#[test]
+fn typed_data_renders_without_building_values_by_hand() {
+    #[derive(Serialize)]
+    #[serde(rename_all = "lowercase")]
+    enum Tier {
+        Web,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Service {
+        service_name: String,
+        replicas: u32,
+        max_bytes: u64,
+        offset: i64,
+        ratio: f64,
+        tags: Vec<&'static str>,
+        owner: Option<String>,
+        #[serde(rename = "env")]
+        environment: BTreeMap<String, String>,
+        tier: Tier,
+    }
+
+    let service = Service {
+        service_name: "api".to_string(),
+        replicas: 3,
+        max_bytes: u64::MAX,
+        offset: i64::MIN,
+        ratio: 0.5,
+        tags: vec!["a", "b"],
+        owner: None,
+        environment: BTreeMap::from([("region".to_string(), "eu".to_string())]),
+        tier: Tier::Web,
+    };
+    let template = Template::parse_str(
+        "service",
+        "{{ .serviceName }} x{{ .replicas }}{{ if .replicas }}+{{ end }} {{ .maxBytes }} \
+         {{ .offset }} {{ .ratio }} {{ range .tags }}[{{ . }}]{{ end }} {{ .owner }} \
+         {{ .env.region }} {{ .tier }}",
+    )
+    .unwrap();
+    let expected = "api x3+ 18446744073709551615 -9223372036854775808 0.5 [a][b] <no value> eu web";
+    assert_eq!(template.render_serialize(&service).unwrap(), expected);
+    let value = serde_json::to_value(&service).unwrap();
+    assert_eq!(template.render(&value).unwrap(), expected);
+
+    let options = RenderOptions::new().with_missing_key(MissingKey::Zero);
+    assert_eq!(
+        template
+            .render_serialize_with_options(&service, &options)
+            .unwrap(),
+        expected.replace("<no value>", "")
+    );
+
+    let bad = BTreeMap::from([(vec![1_u8], 1)]);
+    let err = template.render_serialize(&bad).unwrap_err();
+    assert!(
+        err.message()
+            .starts_with("cannot serialize template data: "),
+        "{err}"
+    );
+    assert!(std::error::Error::source(&err).is_some());
+}
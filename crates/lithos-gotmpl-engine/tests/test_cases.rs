@@ -1,22 +1,8 @@
 // SPDX-License-Identifier: Apache-2.0 OR MIT
-use std::fs;
-use std::path::PathBuf;
+mod common;
 
+use common::engine_cases;
 use lithos_gotmpl_engine::{FunctionRegistry, FunctionRegistryBuilder, Template};
-use serde::Deserialize;
-use serde_json::Value;
-
-#[derive(Debug, Deserialize)]
-struct EngineCase {
-    name: String,
-    template: String,
-    #[serde(default)]
-    data: Value,
-    #[serde(default)]
-    expected: Option<String>,
-    #[serde(default)]
-    error: Option<String>,
-}
 
 fn registry() -> FunctionRegistry {
     FunctionRegistryBuilder::new().build()
@@ -24,17 +10,7 @@ fn registry() -> FunctionRegistry {
 
 #[test]
 fn engine_test_cases_align_with_go_semantics() {
-    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-    let root = manifest_dir
-        .parent()
-        .expect("workspace root missing")
-        .parent()
-        .expect("workspace root missing");
-    let path = root.join("test-cases/lithos-gotmpl-engine.json");
-    let bytes = fs::read(&path).expect("missing engine test cases");
-    let cases: Vec<EngineCase> = serde_json::from_slice(&bytes).expect("invalid engine test cases");
-
-    for case in cases {
+    for case in engine_cases() {
         let parse_result = Template::parse_with_functions(&case.name, &case.template, registry());
 
         let template = match parse_result {
@@ -136,7 +136,7 @@ mod tests {
     use super::*;
     use serde_json::json;
 
-    fn ctx() -> EvalContext {
+    fn ctx() -> EvalContext<'static> {
         super::super::empty_context()
     }
 
@@ -196,8 +196,10 @@ fn collect_numeric_inputs(
 }
 
 fn to_number(name: &'static str, position: usize, value: &Value) -> Result<f64, Error> {
-    coerce_number(value)
-        .map_err(|_| Error::render(format!("{name} argument {position} must be numeric"), None))
+    coerce_number(value).map_err(|_| {
+        Error::render(format!("{name} argument {position} must be numeric"), None)
+            .with_argument(position - 1)
+    })
 }
 
 fn score_to_value(score: f64) -> Value {
@@ -221,7 +223,7 @@ mod tests {
     use super::*;
     use serde_json::json;
 
-    fn ctx() -> EvalContext {
+    fn ctx() -> EvalContext<'static> {
         super::super::empty_context()
     }
 
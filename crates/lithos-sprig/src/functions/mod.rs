@@ -67,7 +67,8 @@ pub(crate) fn expect_string(
         _ => Err(Error::render(
             format!("{name} argument {position} must be coercible to string, got {value:?}"),
             None,
-        )),
+        )
+        .with_argument(position - 1)),
     }
 }
 
@@ -82,7 +83,8 @@ pub(crate) fn expect_array(
         _ => Err(Error::render(
             format!("{name} argument {position} must be an array, got {value:?}"),
             None,
-        )),
+        )
+        .with_argument(position - 1)),
     }
 }
 
@@ -104,12 +106,14 @@ pub(crate) fn expect_usize(
                 format!("{name} argument {position} must be a non-negative integer, got {value:?}"),
                 None,
             )
+            .with_argument(position - 1)
         });
     }
     Err(Error::render(
         format!("{name} argument {position} must be a non-negative integer, got {value:?}"),
         None,
-    ))
+    )
+    .with_argument(position - 1))
 }
 
 pub(crate) fn clamp_char_range(
@@ -129,6 +133,6 @@ pub(crate) fn clamp_char_range(
 }
 
 #[cfg(test)]
-pub(crate) fn empty_context() -> EvalContext {
+pub(crate) fn empty_context() -> EvalContext<'static> {
     EvalContext::new(Value::Null, FunctionRegistryBuilder::new().build())
 }
@@ -241,17 +241,21 @@ pub fn trunc(_ctx: &mut EvalContext, args: &[Value]) -> Result<Value, Error> {
     Ok(Value::String(truncate_chars(&text, length)))
 }
 
-pub fn wrap(_ctx: &mut EvalContext, args: &[Value]) -> Result<Value, Error> {
+pub fn wrap(ctx: &mut EvalContext, args: &[Value]) -> Result<Value, Error> {
     expect_exact_args("wrap", args, 2)?;
     let width = expect_usize("wrap", &args[0], 1)?;
     let text = expect_string("wrap", &args[1], 2)?;
+    // Wrapping only collapses or replaces whitespace, so the input bounds the result.
+    ctx.ensure_value_size(text.len())?;
     Ok(Value::String(wrap_text(width, &text)))
 }
 
-pub fn indent(_ctx: &mut EvalContext, args: &[Value]) -> Result<Value, Error> {
+pub fn indent(ctx: &mut EvalContext, args: &[Value]) -> Result<Value, Error> {
     expect_exact_args("indent", args, 2)?;
     let spaces = expect_usize("indent", &args[0], 1)?;
     let input = expect_string("indent", &args[1], 2)?;
+    let padding = spaces.saturating_mul(input.lines().count());
+    ctx.ensure_value_size(input.len().saturating_add(padding))?;
     Ok(Value::String(indent_text(spaces, &input)))
 }
 
@@ -269,10 +273,11 @@ pub fn nospace(_ctx: &mut EvalContext, args: &[Value]) -> Result<Value, Error> {
     Ok(Value::String(remove_whitespace(&input)))
 }
 
-pub fn repeat(_ctx: &mut EvalContext, args: &[Value]) -> Result<Value, Error> {
+pub fn repeat(ctx: &mut EvalContext, args: &[Value]) -> Result<Value, Error> {
     expect_exact_args("repeat", args, 2)?;
     let count = expect_usize("repeat", &args[0], 1)?;
     let s = expect_string("repeat", &args[1], 2)?;
+    ctx.ensure_value_size(count.saturating_mul(s.len()))?;
     Ok(json!(s.repeat(count)))
 }
 
@@ -331,7 +336,7 @@ mod tests {
     use super::*;
     use serde_json::json;
 
-    fn ctx() -> EvalContext {
+    fn ctx() -> EvalContext<'static> {
         super::super::empty_context()
     }
 
@@ -361,6 +366,28 @@ mod tests {
         assert_eq!(out, json!("foo bar"));
     }
 
+    #[test]
+    fn repeat_wrap_and_indent_respect_value_size_budget() {
+        use lithos_gotmpl_engine::{Limit, RenderOptions};
+        let mut ctx = ctx().with_options(RenderOptions::new().with_limit(Limit::ValueSize, 8));
+        assert_eq!(ctx.remaining(Limit::ValueSize), Some(8));
+        assert_eq!(
+            super::repeat(&mut ctx, &[json!(4), json!("ab")]).unwrap(),
+            json!("abababab")
+        );
+        let err = super::repeat(&mut ctx, &[json!(1_000_000_000), json!("ab")]).unwrap_err();
+        assert_eq!(err.limit(), Some(Limit::ValueSize));
+        assert_eq!(err.message(), "exceeded maximum value size (8 bytes)");
+        let err = super::indent(&mut ctx, &[json!(4), json!("a\nb")]).unwrap_err();
+        assert_eq!(err.limit(), Some(Limit::ValueSize));
+        assert_eq!(
+            super::wrap(&mut ctx, &[json!(3), json!("ab cd")]).unwrap(),
+            json!("ab\ncd")
+        );
+        let err = super::wrap(&mut ctx, &[json!(3), json!("ab cd efg")]).unwrap_err();
+        assert_eq!(err.limit(), Some(Limit::ValueSize));
+    }
+
     #[test]
     fn repeat_rejects_negative_counts() {
         let mut ctx = ctx();
@@ -50,4 +50,16 @@ mod tests {
         let rendered = template.render(&json!({"name": "sprig"})).unwrap();
         assert_eq!(rendered, "SPRIG");
     }
+
+    #[test]
+    fn helper_errors_point_at_offending_argument() {
+        let source = r#"{{ toJson .x | indent "four" }}"#;
+        let template = Template::parse_with_functions("sprig", source, sprig_functions()).unwrap();
+        let err = template.render(&json!({"x": [1]})).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("error calling indent: indent argument 1"));
+        let span = err.span().expect("error should carry a span");
+        assert_eq!(&source[span.start..span.end], r#""four""#);
+    }
 }